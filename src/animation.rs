@@ -1,13 +1,107 @@
 use std::time::{Duration, SystemTime};
 
 use egui::Color32;
-use tween::{Tween, Tweener};
+use serde::{Deserialize, Serialize};
+use tween::{
+    BounceOut, CubicInOut, ExpoIn, ExpoInOut, ExpoOut, Linear, QuadIn, QuadInOut, QuadOut, SineIn,
+    SineInOut, SineOut, Tween, Tweener,
+};
 
 type StoredTweener = Tweener<f32, usize, Box<dyn Tween<f32>>>;
 
+/// The easing curves available for animations, by name; this is the single
+/// table used for Scenes as well as remote messages
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Easing {
+    Linear,
+    SineIn,
+    SineOut,
+    #[default]
+    SineInOut,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BounceOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 12] = [
+        Easing::Linear,
+        Easing::SineIn,
+        Easing::SineOut,
+        Easing::SineInOut,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicInOut,
+        Easing::ExpoIn,
+        Easing::ExpoOut,
+        Easing::ExpoInOut,
+        Easing::BounceOut,
+    ];
+
+    pub fn tween(&self) -> Box<dyn Tween<f32>> {
+        match self {
+            Easing::Linear => Box::new(Linear),
+            Easing::SineIn => Box::new(SineIn),
+            Easing::SineOut => Box::new(SineOut),
+            Easing::SineInOut => Box::new(SineInOut),
+            Easing::QuadIn => Box::new(QuadIn),
+            Easing::QuadOut => Box::new(QuadOut),
+            Easing::QuadInOut => Box::new(QuadInOut),
+            Easing::CubicInOut => Box::new(CubicInOut),
+            Easing::ExpoIn => Box::new(ExpoIn),
+            Easing::ExpoOut => Box::new(ExpoOut),
+            Easing::ExpoInOut => Box::new(ExpoInOut),
+            Easing::BounceOut => Box::new(BounceOut),
+        }
+    }
+}
+
+/// The length of an animation, either absolute or in musical beats; beats are
+/// converted using the tempo at the moment the animation is triggered, so that
+/// fades stay in time when the tempo changes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DurationSpec {
+    Ms(u64),
+    Beats(f32),
+}
+
+impl DurationSpec {
+    /// Duration in milliseconds, given the current tempo (if any); None for a
+    /// duration in beats if there is no tempo to convert with
+    pub fn to_ms(self, bpm: Option<f32>) -> Option<u64> {
+        match self {
+            DurationSpec::Ms(ms) => Some(ms),
+            DurationSpec::Beats(beats) => bpm
+                .filter(|bpm| *bpm > 0.)
+                .map(|bpm| (beats * 60_000. / bpm).round() as u64),
+        }
+    }
+}
+
+/// What an animation does once it reaches the end value
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum RepeatMode {
+    /// Stop at the end value
+    #[default]
+    Once,
+    /// Jump back to the start value and go again, until stopped
+    Loop,
+    /// Go back and forth between start and end values, until stopped
+    PingPong,
+}
+
 pub struct Animation {
     pub start_time: SystemTime,
     pub duration: Duration,
+    /// Hold at the start value for this long before the tween begins
+    pub delay: Duration,
+    pub repeat: RepeatMode,
     pub tweener: StoredTweener,
 }
 
@@ -22,23 +116,92 @@ impl Animation {
         Animation {
             start_time: SystemTime::now(),
             duration,
+            delay: Duration::ZERO,
+            repeat: RepeatMode::Once,
             tweener: Tweener::new(start_value, end_value, duration_ms, tween),
         }
     }
 
+    /// Delay the start of the animation, holding at the start value until then
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: RepeatMode) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
     /// Update the animation using delta time, get the value in the range `[0,1]`
     pub fn get_value(&mut self) -> f32 {
-        let elapsed = self.start_time.elapsed().unwrap().as_millis() as usize;
+        let elapsed = self
+            .start_time
+            .elapsed()
+            .unwrap()
+            .saturating_sub(self.delay)
+            .as_millis() as usize;
+
+        let duration_ms = self.tweener.duration;
+        let position = match (self.repeat, elapsed.checked_div(duration_ms)) {
+            (RepeatMode::Once, _) | (_, None) => elapsed,
+            (RepeatMode::Loop, Some(_)) => elapsed % duration_ms,
+            (RepeatMode::PingPong, Some(cycle)) if cycle % 2 == 1 => {
+                duration_ms - elapsed % duration_ms
+            }
+            (RepeatMode::PingPong, Some(_)) => elapsed % duration_ms,
+        };
 
-        self.tweener.move_to(elapsed)
+        self.tweener.move_to(position)
     }
 
     pub fn get_progress(&self) -> f32 {
         self.tweener.current_time as f32 / self.tweener.duration as f32
     }
 
+    /// Repeating animations are never done; they run until replaced or
+    /// stopped
     pub fn get_value_and_done(&mut self) -> (f32, bool) {
-        (self.get_value(), self.tweener.is_finished())
+        let value = self.get_value();
+        (
+            value,
+            self.repeat == RepeatMode::Once && self.tweener.is_finished(),
+        )
+    }
+}
+
+/// Ramps towards a target value at a limited rate (DMX units per second),
+/// rather than jumping there in a single frame
+pub struct Slew {
+    last_update: SystemTime,
+    position: f32,
+    target: f32,
+}
+
+impl Slew {
+    pub fn new(start_value: u8, target_value: u8) -> Self {
+        Slew {
+            last_update: SystemTime::now(),
+            position: start_value as f32,
+            target: target_value as f32,
+        }
+    }
+
+    /// Move towards the target by no more than `rate` units per second since the
+    /// last update; get the new value and whether the target has been reached
+    pub fn get_value_and_done(&mut self, rate: f32) -> (u8, bool) {
+        let elapsed = self.last_update.elapsed().unwrap_or_default().as_secs_f32();
+        self.last_update = SystemTime::now();
+
+        let max_step = rate * elapsed;
+        let distance = self.target - self.position;
+        if distance.abs() <= max_step {
+            self.position = self.target;
+        } else {
+            self.position += max_step * distance.signum();
+        }
+
+        (self.position.round() as u8, self.position == self.target)
     }
 }
 
@@ -1,102 +1,365 @@
 use serde::{Deserialize, Serialize};
 use std::{
-    net::{SocketAddr, ToSocketAddrs, UdpSocket},
-    time::{Duration, SystemTime},
+    collections::{BTreeMap, BTreeSet},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::mpsc::{self, Receiver},
+    thread::JoinHandle,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use artnet_protocol::{ArtCommand, Output};
-use log::{debug, trace};
+use anyhow::anyhow;
+use artnet_protocol::{ArtCommand, Output, Poll, PollReply, ARTNET_PROTOCOL_VERSION};
+use log::{debug, error, info, trace, warn};
 use rand::Rng;
 
 use crate::{
-    project::fixture::{CMYChannels, ChannelList, FixtureInstance, FixtureMacro, RGBWChannels},
-    settings::CHANNELS_PER_UNIVERSE,
+    output::{DmxOutput, MockOutput, RecordedFrames},
+    project::{
+        fixture::{
+            CMYChannels, ChannelList, ChannelRole, FixtureInstance, FixtureMacro, RGBWChannels,
+        },
+        pixelmap::PixelMap,
+    },
+    settings::{MergeMode, ARTNET_PORT, CHANNELS_PER_UNIVERSE, DEFAULT_UNIVERSE, MAX_UNIVERSE},
 };
 
+/// How many frames (one per universe) a dry run keeps; see `MockOutput`
+const DRY_RUN_FRAMES_KEPT: usize = 64;
+
 pub struct ArtNetInterface {
-    socket: UdpSocket,
-    destination: SocketAddr,
-    channels: Vec<u8>,
+    /// Where every frame is sent, e.g. an `ArtNetOutput` or `SacnInterface`
+    output: Box<dyn DmxOutput>,
+    /// In dry run mode, the frames kept by the `MockOutput`
+    recorded_frames: Option<RecordedFrames>,
+    /// Low-pass filter coefficient (0 = off); see `with_smoothing`
+    smoothing: f32,
+    /// Universe => target values for the next frame, before smoothing. This
+    /// always includes `DEFAULT_UNIVERSE`, plus every other universe with
+    /// fixtures or pixel maps patched on it
+    frames: BTreeMap<u16, Vec<u8>>,
+    /// Universe => channel values as rendered from the channels state, macros
+    /// and pixel maps, before anything that applies to the output only
+    /// (disabled fixtures, overrides, limits); this is the state read back by
+    /// the Model, so that none of those get baked into it
+    state: BTreeMap<u16, Vec<u8>>,
+    /// Every frame is sent with exactly this many channels
+    universe_size: usize,
+    /// Whether a channels state of the wrong length has been logged, so that
+    /// this is only logged once each time it starts
+    is_resizing: bool,
+    /// Universe => the values actually output in the previous frame, if
+    /// smoothing
+    smoothed: BTreeMap<u16, Vec<f32>>,
+    /// Universe and index of channels currently being clamped to fixture
+    /// limits, so that this is only logged once each time it starts
+    clamped_channels: BTreeSet<(u16, usize)>,
+    /// Frames are sent at least this often, even if nothing changed
     update_interval: Duration,
+    /// Frames are never sent more often than this, even if changes are pending
+    min_interval: Duration,
     last_sent: Option<SystemTime>,
-    mode_in_use: ArtNetMode,
+    /// When the latest frame was due to be built (whether or not it was then
+    /// sent); frames are built on this steady schedule
+    last_frame_due: Option<SystemTime>,
+    /// Channel values (before smoothing) for each universe in the frame sent
+    /// last
+    last_frames: BTreeMap<u16, Vec<u8>>,
+    /// Latest channel values received from an upstream console, if merging;
+    /// see `set_merge_input`
+    merge_input: Option<Vec<u8>>,
+    /// Whether new merge input has arrived since the last frame was sent
+    is_merge_input_pending: bool,
+    merge_mode: MergeMode,
+    /// For LTP: which channels were most recently changed by the input
+    /// (rather than locally)
+    input_owned: Vec<bool>,
+    /// For LTP: local output for the default universe in the previous frame,
+    /// to tell which channels have since changed locally
+    last_local_output: Vec<u8>,
+    /// Send all zeros, without touching the frames (state) themselves
+    blackout: bool,
+    /// Whether blackout or grand master changed since the last frame was sent
+    is_blackout_pending: bool,
+    /// Scales all Intensity channels on output, 255 being full
+    grand_master: u8,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ArtNetMode {
-    Broadcast,
+    /// Specify from (local) port + to (destination) port
+    Broadcast(u16, u16),
     /// Specify from (interface) + to (destination) addresses
     Unicast(SocketAddr, SocketAddr),
 }
 
 impl ArtNetInterface {
-    pub fn new(mode: ArtNetMode, update_frequency: u64) -> Result<Self, anyhow::Error> {
-        let channels = Vec::with_capacity(CHANNELS_PER_UNIVERSE as usize);
+    /// An interface which sends every frame via the given output
+    pub fn new(output: Box<dyn DmxOutput>, update_frequency: u64) -> Self {
+        ArtNetInterface {
+            output,
+            recorded_frames: None,
+            smoothing: 0.,
+            frames: BTreeMap::new(),
+            state: BTreeMap::new(),
+            universe_size: CHANNELS_PER_UNIVERSE as usize,
+            is_resizing: false,
+            smoothed: BTreeMap::new(),
+            clamped_channels: BTreeSet::new(),
+            update_interval: frame_interval(update_frequency),
+            min_interval: frame_interval(update_frequency),
+            last_sent: None,
+            last_frame_due: None,
+            last_frames: BTreeMap::new(),
+            merge_input: None,
+            is_merge_input_pending: false,
+            merge_mode: MergeMode::Htp,
+            input_owned: Vec::new(),
+            last_local_output: Vec::new(),
+            blackout: false,
+            is_blackout_pending: false,
+            grand_master: 255,
+        }
+    }
 
-        let update_interval = Duration::from_secs_f32(1.0 / update_frequency as f32);
+    /// An interface which never opens a socket; frames are logged (at debug
+    /// level) and kept by a `MockOutput` instead of being sent, e.g. for
+    /// development with no lighting network available
+    pub fn new_dry_run(update_frequency: u64) -> Self {
+        info!("ArtNet dry run; no packets will be sent");
+        let (output, frames) = MockOutput::new(DRY_RUN_FRAMES_KEPT);
+        let mut interface = Self::new(Box::new(output), update_frequency);
+        interface.recorded_frames = Some(frames);
+        interface
+    }
 
-        match mode {
-            ArtNetMode::Broadcast => {
-                let socket = UdpSocket::bind((String::from("0.0.0.0"), 6455))?;
-                let broadcast_addr = ("255.255.255.255", 6454).to_socket_addrs()?.next().unwrap();
-                socket.set_broadcast(true).unwrap();
-                debug!("Broadcast mode set up OK");
-                Ok(ArtNetInterface {
-                    socket,
-                    destination: broadcast_addr,
-                    channels,
-                    update_interval,
-                    last_sent: None,
-                    mode_in_use: mode.clone(),
-                })
+    pub fn is_dry_run(&self) -> bool {
+        !self.output.is_sending()
+    }
+
+    /// In dry run mode, the most recent frames which would have been sent
+    /// (oldest first, one entry per universe)
+    pub fn recorded_frames(&self) -> Option<Vec<(u16, Vec<u8>)>> {
+        self.recorded_frames
+            .as_ref()
+            .map(|frames| frames.lock().unwrap().clone())
+    }
+
+    /// Description of the output in use, e.g. "sACN multicast"
+    pub fn describe_output(&self) -> String {
+        self.output.describe()
+    }
+
+    /// Send this many channels in every frame (at most `CHANNELS_PER_UNIVERSE`)
+    pub fn with_universe_size(mut self, universe_size: u16) -> Self {
+        self.universe_size = universe_size.clamp(2, CHANNELS_PER_UNIVERSE) as usize;
+        self
+    }
+
+    /// Ease every channel towards its new value over successive frames, e.g.
+    /// to hide MIDI steps or emulate incandescent lamps. The coefficient is
+    /// the fraction of the remaining difference kept after each frame; 0 (the
+    /// default) disables smoothing. Values still always reach their targets.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        let smoothing = smoothing.clamp(0., 0.99);
+        if smoothing > 0. {
+            info!("Output smoothing enabled with coefficient {}", smoothing);
+        }
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Cap the frame rate: changes are sent as soon as they happen, but no more
+    /// than `max_fps` times per second. Unchanged frames are still sent at the
+    /// (keep-alive) update frequency. Without a cap, all frames are simply
+    /// sent at the update frequency.
+    pub fn with_max_fps(mut self, max_fps: Option<u64>) -> Self {
+        if let Some(max_fps) = max_fps.filter(|fps| *fps > 0) {
+            info!("Output capped at {} FPS", max_fps);
+            self.min_interval = frame_interval(max_fps);
+            if self.min_interval > self.update_interval {
+                warn!("Max FPS is lower than the update frequency, which it overrides");
             }
-            ArtNetMode::Unicast(src, destination) => {
-                debug!(
-                    "Will connect from interface {} to destination {}",
-                    &src, &destination
-                );
-                let socket = UdpSocket::bind(src)?;
+        }
+        self
+    }
 
-                socket.set_broadcast(false)?;
-                Ok(ArtNetInterface {
-                    socket,
-                    destination,
-                    channels,
-                    update_interval,
-                    last_sent: None,
-                    mode_in_use: mode.clone(),
-                })
+    /// Merge these channel values (e.g. from an upstream console) into every
+    /// frame sent from now on. With HTP, each channel outputs whichever is
+    /// higher of the local value and the input value; with LTP, whichever
+    /// changed most recently. Fixture channel limits still apply to the
+    /// merged result.
+    pub fn set_merge_input(&mut self, data: Vec<u8>, mode: MergeMode) {
+        if self.merge_input.as_ref() == Some(&data) && self.merge_mode == mode {
+            return;
+        }
+        if mode == MergeMode::Ltp {
+            self.input_owned.resize(data.len(), false);
+            let previous = self.merge_input.as_deref().unwrap_or_default();
+            for (i, value) in data.iter().enumerate() {
+                if previous.get(i).copied().unwrap_or(0) != *value {
+                    self.input_owned[i] = true;
+                }
             }
         }
+        self.merge_mode = mode;
+        self.merge_input = Some(data);
+        self.is_merge_input_pending = true;
+    }
+
+    /// While enabled, every channel is sent as zero; the channel values
+    /// themselves (and smoothing) carry on as normal, so that disabling
+    /// blackout restores exactly what would have been output.
+    pub fn set_blackout(&mut self, blackout: bool) {
+        if self.blackout != blackout {
+            self.blackout = blackout;
+            self.is_blackout_pending = true;
+        }
     }
 
+    /// Scale every channel with the Intensity role by this (0-255) on output;
+    /// like blackout, the channel values themselves are left untouched
+    pub fn set_grand_master(&mut self, grand_master: u8) {
+        if self.grand_master != grand_master {
+            self.grand_master = grand_master;
+            self.is_blackout_pending = true;
+        }
+    }
+
+    /// Send the next frame as soon as possible, regardless of the update rate
+    pub fn send_next_frame_now(&mut self) {
+        self.last_sent = None;
+        self.last_frame_due = None;
+    }
+
+    /// How long until the next frame is due to be built, and sent if anything
+    /// changed (zero if overdue)
+    pub fn time_until_next_send(&self) -> Duration {
+        match self.last_frame_due {
+            Some(t) => self
+                .min_interval
+                .saturating_sub(t.elapsed().unwrap_or(Duration::ZERO)),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Prepare (and, if due, send) the next frame. `channels_state` is the
+    /// default universe; `extra_universes` are any others, as kept by the
+    /// Model. Returns true if a frame was sent.
     pub fn update(
         &mut self,
         channels_state: &[u8],
+        extra_universes: &BTreeMap<u16, Vec<u8>>,
         fixtures: &[FixtureInstance],
+        pixel_maps: &[PixelMap],
         apply_macros: bool,
+        channel_overrides: &BTreeMap<u16, u8>,
     ) -> bool {
-        match self.last_sent {
-            Some(t) => {
-                if t.elapsed().unwrap() < self.update_interval {
-                    return false; // early return; not ready to send
-                }
+        self.update_at(
+            SystemTime::now(),
+            channels_state,
+            extra_universes,
+            fixtures,
+            pixel_maps,
+            apply_macros,
+            channel_overrides,
+        )
+    }
+
+    /// As `update`, at the given time (so that tests can control the clock)
+    #[allow(clippy::too_many_arguments)]
+    fn update_at(
+        &mut self,
+        now: SystemTime,
+        channels_state: &[u8],
+        extra_universes: &BTreeMap<u16, Vec<u8>>,
+        fixtures: &[FixtureInstance],
+        pixel_maps: &[PixelMap],
+        apply_macros: bool,
+        channel_overrides: &BTreeMap<u16, u8>,
+    ) -> bool {
+        if let Some(due) = self.last_frame_due {
+            let since_due = now.duration_since(due).unwrap_or(Duration::ZERO);
+            if since_due < self.min_interval {
+                return false; // early return; not ready to send
             }
-            None => self.last_sent = Some(SystemTime::now()),
+            // Keep to the schedule rather than drifting by however late this
+            // update is, unless so late that a whole frame was missed
+            self.last_frame_due = Some(if since_due < self.min_interval * 2 {
+                due + self.min_interval
+            } else {
+                now
+            });
+        } else {
+            self.last_frame_due = Some(now);
         }
+        let elapsed = self
+            .last_sent
+            .map(|t| now.duration_since(t).unwrap_or(Duration::ZERO));
 
-        // zero(&mut self.channels);
-        self.channels = channels_state.into(); // copy slice contents into Vec
+        // Copy slice contents into a Vec per universe
+        self.frames.clear();
+        self.frames.insert(DEFAULT_UNIVERSE, channels_state.into());
+        for (universe, state) in extra_universes.iter() {
+            self.frames.insert(*universe, state.clone());
+        }
+        if channels_state.len() != self.universe_size {
+            // e.g. state not (yet) initialised; never send a malformed frame
+            if !self.is_resizing {
+                warn!(
+                    "Channels state has length {}, expected {}; will be padded/truncated",
+                    channels_state.len(),
+                    self.universe_size
+                );
+                self.is_resizing = true;
+            }
+        } else {
+            self.is_resizing = false;
+        }
+        for channels in self.frames.values_mut() {
+            channels.resize(self.universe_size, 0);
+        }
 
         if apply_macros {
-            for f in fixtures {
+            for f in fixtures.iter().filter(|f| f.enabled) {
                 for m in &f.config.active_mode.macros {
                     match m {
+                        FixtureMacro::Wheel(wheel_macro) => {
+                            for c in &wheel_macro.channels {
+                                self.set_channel(
+                                    f.universe,
+                                    f.channel_index(*c),
+                                    wheel_macro.dmx_value(),
+                                );
+                            }
+                        }
                         FixtureMacro::Control(control_macro) => {
                             for c in &control_macro.channels {
-                                self.channels[(*c - 1 + f.offset_channels) as usize] =
-                                    control_macro.current_value;
+                                if control_macro.is_16bit {
+                                    let [coarse, fine] =
+                                        control_macro.output_value_16bit().to_be_bytes();
+                                    self.set_channel(f.universe, f.channel_index(*c), coarse);
+                                    if let Some(fine_channel) = f.fine_channel_for(*c) {
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(fine_channel),
+                                            fine,
+                                        );
+                                    }
+                                } else {
+                                    self.set_channel(
+                                        f.universe,
+                                        f.channel_index(*c),
+                                        control_macro.output_value(),
+                                    );
+                                }
+                            }
+                            for influence in &control_macro.influences {
+                                self.set_channel(
+                                    f.universe,
+                                    f.channel_index(influence.channel),
+                                    influence.output_value(control_macro.current_value),
+                                );
                             }
                         }
                         FixtureMacro::Colour(colour_macro) => {
@@ -112,16 +375,25 @@ impl ArtNetInterface {
                                     // Convert all rgb values from "opaque" version (ignoring alpha)
                                     let opaque = colour_macro.current_value.to_opaque();
                                     for c in red.iter() {
-                                        self.channels[(*c - 1 + f.offset_channels) as usize] =
-                                            opaque.r();
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(*c),
+                                            opaque.r(),
+                                        );
                                     }
                                     for c in green.iter() {
-                                        self.channels[(*c - 1 + f.offset_channels) as usize] =
-                                            opaque.g();
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(*c),
+                                            opaque.g(),
+                                        );
                                     }
                                     for c in blue.iter() {
-                                        self.channels[(*c - 1 + f.offset_channels) as usize] =
-                                            opaque.b();
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(*c),
+                                            opaque.b(),
+                                        );
                                     }
 
                                     // Use inverse of alpha for "white mix" , i.e.
@@ -129,8 +401,11 @@ impl ArtNetInterface {
                                     //  alpha = 0% => RGB the same, but mix in full white
                                     let white_inverse = 255 - colour_macro.current_value.a();
                                     for c in white.iter() {
-                                        self.channels[(*c - 1 + f.offset_channels) as usize] =
-                                            white_inverse;
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(*c),
+                                            white_inverse,
+                                        );
                                     }
                                 }
                                 ChannelList::Subtractive(cmy) => {
@@ -147,21 +422,20 @@ impl ArtNetInterface {
                                     let y = 255 - colour_macro.current_value.b();
 
                                     for channel in cyan.iter() {
-                                        self.channels
-                                            [(*channel - 1 + f.offset_channels) as usize] = c;
+                                        self.set_channel(f.universe, f.channel_index(*channel), c);
                                     }
                                     for channel in magenta.iter() {
-                                        self.channels
-                                            [(*channel - 1 + f.offset_channels) as usize] = m;
+                                        self.set_channel(f.universe, f.channel_index(*channel), m);
                                     }
                                     for channel in yellow.iter() {
-                                        self.channels
-                                            [(*channel - 1 + f.offset_channels) as usize] = y;
+                                        self.set_channel(f.universe, f.channel_index(*channel), y);
                                     }
                                     for channel in white.iter() {
-                                        self.channels
-                                            [(*channel - 1 + f.offset_channels) as usize] =
-                                            brightness;
+                                        self.set_channel(
+                                            f.universe,
+                                            f.channel_index(*channel),
+                                            brightness,
+                                        );
                                     }
                                 }
                             }
@@ -171,30 +445,527 @@ impl ArtNetInterface {
             }
         }
 
-        trace!("Channel state {:?}", self.channels);
-        let command = ArtCommand::Output(Output {
-            port_address: 0.into(),
-            data: self.channels.clone().into(), // make temp copy of self channel state (?)
-            ..Output::default()
-        });
+        if apply_macros {
+            let seconds = now
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            for map in pixel_maps.iter().filter(|m| m.enabled) {
+                if let Some(channels) = self.frames.get_mut(&map.universe) {
+                    map.write(channels, seconds);
+                }
+            }
+        }
+        self.state.clone_from(&self.frames);
+
+        // Disabled fixtures output nothing
+        for f in fixtures.iter().filter(|f| !f.enabled) {
+            for m in &f.config.active_mode.mappings {
+                self.set_channel(f.universe, f.channel_index(m.channel), 0);
+            }
+        }
 
-        let buff = command.write_to_buffer().unwrap();
-        self.socket.send_to(&buff, self.destination).unwrap();
+        // Overrides always win, whether or not macros are applied
+        for (channel, value) in channel_overrides.iter() {
+            self.set_channel(DEFAULT_UNIVERSE, (*channel - 1) as usize, *value);
+        }
+
+        // Safety limits are applied last of all, so that nothing can exceed them
+        for f in fixtures.iter() {
+            for (channel, [min, max]) in f.channel_limits.iter() {
+                let index = f.channel_index(*channel);
+                if let Some(c) = self
+                    .frames
+                    .get_mut(&f.universe)
+                    .and_then(|channels| channels.get_mut(index))
+                {
+                    let limited = (*c).clamp(*min, *max);
+                    if limited != *c {
+                        if self.clamped_channels.insert((f.universe, index)) {
+                            warn!(
+                                "Fixture \"{}\" channel {} value {} clamped to limits {}-{}",
+                                &f.label, channel, c, min, max
+                            );
+                        }
+                        *c = limited;
+                    } else {
+                        self.clamped_channels.remove(&(f.universe, index));
+                    }
+                }
+            }
+        }
+
+        let is_keep_alive_due = elapsed.map(|e| e >= self.update_interval).unwrap_or(true);
+        if !is_keep_alive_due
+            && !self.is_merge_input_pending
+            && !self.is_blackout_pending
+            && self.frames == self.last_frames
+            && self.is_smoothing_settled()
+        {
+            return false; // nothing new to send yet
+        }
+        self.last_sent = Some(now);
+        self.last_frames = self.frames.clone();
+        self.is_merge_input_pending = false;
+        self.is_blackout_pending = false;
+
+        let universes: Vec<u16> = self.frames.keys().copied().collect();
+        for universe in universes.iter() {
+            trace!(
+                "Universe {} channel state {:?}",
+                universe,
+                self.frames[universe]
+            );
+            let mut output = self.smoothed_output(*universe);
+            if *universe == DEFAULT_UNIVERSE {
+                if let Some(merge_input) = &self.merge_input {
+                    // Merged into the output only, so that input values never end up
+                    // in the state read back by the Model
+                    match self.merge_mode {
+                        MergeMode::Ltp => {
+                            self.input_owned.resize(output.len(), false);
+                            if self.last_local_output.len() != output.len() {
+                                // Nothing to compare with yet (e.g. on the first merged
+                                // frame), so nothing counts as a local change
+                                self.last_local_output.clone_from(&output);
+                            }
+                            for (i, c) in output.iter_mut().enumerate() {
+                                // A local change takes the channel back
+                                if self.last_local_output[i] != *c {
+                                    self.last_local_output[i] = *c;
+                                    self.input_owned[i] = false;
+                                }
+                                if self.input_owned[i] {
+                                    *c = merge_input.get(i).copied().unwrap_or(*c);
+                                }
+                            }
+                        }
+                        _ => {
+                            for (c, input) in output.iter_mut().zip(merge_input.iter()) {
+                                *c = (*c).max(*input);
+                            }
+                        }
+                    }
+                    for f in fixtures.iter().filter(|f| f.universe == DEFAULT_UNIVERSE) {
+                        for (channel, [min, max]) in f.channel_limits.iter() {
+                            if let Some(c) = output.get_mut(f.channel_index(*channel)) {
+                                *c = (*c).clamp(*min, *max);
+                            }
+                        }
+                    }
+                }
+            }
+            if self.grand_master < 255 {
+                for f in fixtures.iter().filter(|f| f.universe == *universe) {
+                    apply_grand_master(&mut output, f, self.grand_master);
+                }
+            }
+            if self.blackout {
+                output.fill(0);
+            }
+            if self.is_dry_run() {
+                debug!("Dry run frame for universe {}: {:?}", universe, output);
+            }
+            self.output.send_universe(*universe, &output);
+        }
+        self.output.end_frame(&universes);
 
         true
     }
 
+    /// The values to actually send this frame for the given universe: simply
+    /// the channel values, or (if smoothing) each previous output value moved
+    /// part of the way towards them. The channel values themselves are left
+    /// untouched, since they are compared against the next frame's.
+    fn smoothed_output(&mut self, universe: u16) -> Vec<u8> {
+        let channels = &self.frames[&universe];
+        let smoothed = self.smoothed.entry(universe).or_default();
+        if self.smoothing <= 0. || smoothed.len() != channels.len() {
+            // Nothing to ease from (yet), e.g. on the very first frame
+            *smoothed = channels.iter().map(|c| *c as f32).collect();
+            return channels.clone();
+        }
+        for (previous, target) in smoothed.iter_mut().zip(channels.iter()) {
+            let target = *target as f32;
+            let next = target + (*previous - target) * self.smoothing;
+            // Snap once close enough, so the exact target is always reached
+            *previous = if (next - target).abs() < 0.5 {
+                target
+            } else {
+                next
+            };
+        }
+        smoothed.iter().map(|c| c.round() as u8).collect()
+    }
+
+    /// Whether the output has caught up with the channel values in every
+    /// universe, i.e. there is no smoothing still in progress
+    fn is_smoothing_settled(&self) -> bool {
+        self.frames.iter().all(|(universe, channels)| {
+            self.smoothed.get(universe).is_some_and(|smoothed| {
+                smoothed.len() == channels.len()
+                    && smoothed
+                        .iter()
+                        .zip(channels.iter())
+                        .all(|(s, c)| *s == *c as f32)
+            })
+        })
+    }
+
+    /// Set a channel by universe and index, ignoring any beyond the end of the
+    /// universe (or on a universe not being output)
+    fn set_channel(&mut self, universe: u16, index: usize, value: u8) {
+        if let Some(c) = self
+            .frames
+            .get_mut(&universe)
+            .and_then(|channels| channels.get_mut(index))
+        {
+            *c = value;
+        }
+    }
+
+    /// Channel values for the default universe, as rendered for the latest
+    /// frame; see `state`
     pub fn get_state(&self) -> &[u8] {
-        &self.channels
+        self.state
+            .get(&DEFAULT_UNIVERSE)
+            .map(|channels| channels.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Channel values for every other universe, as rendered for the latest
+    /// frame; see `state`
+    pub fn get_extra_states(&self) -> BTreeMap<u16, Vec<u8>> {
+        self.state
+            .iter()
+            .filter(|(universe, _)| **universe != DEFAULT_UNIVERSE)
+            .map(|(universe, channels)| (*universe, channels.clone()))
+            .collect()
+    }
+}
+
+/// ArtNet transport: each universe is sent as an ArtDMX packet to the
+/// destination for its mode, or to the node it is routed to
+pub struct ArtNetOutput {
+    socket: UdpSocket,
+    destination: SocketAddr,
+    /// Universe => destination node, for universes which should not simply
+    /// go to the default destination
+    routes: BTreeMap<u16, SocketAddr>,
+    /// Whether to follow each frame with an ArtSync packet
+    sync: bool,
+    mode: ArtNetMode,
+}
+
+impl ArtNetOutput {
+    pub fn new(mode: ArtNetMode) -> Result<Self, anyhow::Error> {
+        let (socket, destination) = match &mode {
+            ArtNetMode::Broadcast(src_port, dst_port) => {
+                let socket = bind_socket(SocketAddr::from(([0, 0, 0, 0], *src_port)))?;
+                socket.set_broadcast(true)?;
+                debug!("Broadcast mode set up OK");
+                (socket, SocketAddr::from(([255, 255, 255, 255], *dst_port)))
+            }
+            ArtNetMode::Unicast(src, destination) => {
+                debug!(
+                    "Will connect from interface {} to destination {}",
+                    &src, &destination
+                );
+                let socket = bind_socket(*src)?;
+                socket.set_broadcast(false)?;
+                (socket, *destination)
+            }
+        };
+        Ok(ArtNetOutput {
+            socket,
+            destination,
+            routes: BTreeMap::new(),
+            sync: false,
+            mode,
+        })
+    }
+
+    /// Send the given universes to specific destination nodes, rather than the
+    /// default destination
+    pub fn with_routes(mut self, routes: BTreeMap<u16, SocketAddr>) -> Self {
+        for (universe, destination) in routes.iter() {
+            info!("Universe {} will be sent to {}", universe, destination);
+        }
+        self.routes = routes;
+        self
+    }
+
+    /// Follow each frame of ArtDMX packets with an ArtSync packet, so that
+    /// (compliant) nodes output all universes simultaneously
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        if sync {
+            info!("ArtSync enabled");
+        }
+        self.sync = sync;
+        self
+    }
+
+    pub fn mode(&self) -> &ArtNetMode {
+        &self.mode
+    }
+
+    /// The node that the given universe should be sent to; falls back to the
+    /// default destination if there is no explicit route
+    pub fn destination_for(&self, universe: u16) -> SocketAddr {
+        *self.routes.get(&universe).unwrap_or(&self.destination)
+    }
+
+    /// Send an ArtSync packet to every node that was sent ArtDMX data for the
+    /// given universes (which in Broadcast mode is the broadcast address)
+    fn send_sync(&self, universes: &[u16]) {
+        let mut buff = ArtCommand::Sync.write_to_buffer().unwrap();
+        // The library does not (yet) write the rest of the ArtSync packet:
+        // protocol version followed by two "aux" bytes, which must be zero
+        buff.extend_from_slice(&ARTNET_PROTOCOL_VERSION);
+        buff.extend_from_slice(&[0, 0]);
+
+        let mut destinations = Vec::new();
+        for destination in universes.iter().map(|u| self.destination_for(*u)) {
+            if !destinations.contains(&destination) {
+                destinations.push(destination);
+            }
+        }
+        for destination in destinations {
+            trace!("Send ArtSync to {}", destination);
+            self.send(&buff, destination);
+        }
+    }
+
+    fn send(&self, buff: &[u8], destination: SocketAddr) {
+        if let Err(e) = self.socket.send_to(buff, destination) {
+            error!("Failed to send ArtNet to {}: {}", destination, e);
+        }
+    }
+}
+
+impl DmxOutput for ArtNetOutput {
+    fn send_universe(&mut self, universe: u16, data: &[u8]) {
+        // Sequence 0 means sequencing is disabled, i.e. nodes must not reorder
+        match build_artdmx_packet(universe, 0, data) {
+            Ok(buff) => self.send(&buff, self.destination_for(universe)),
+            Err(e) => error!("Universe {} not sent: {}", universe, e),
+        }
+    }
+
+    fn end_frame(&mut self, universes: &[u16]) {
+        if self.sync {
+            self.send_sync(universes);
+        }
     }
 
-    pub fn mode_in_use(&self) -> &ArtNetMode {
-        &self.mode_in_use
+    fn describe(&self) -> String {
+        match &self.mode {
+            ArtNetMode::Broadcast(src_port, dst_port) => {
+                format!("Broadcast :{} => :{}", src_port, dst_port)
+            }
+            ArtNetMode::Unicast(src, dst) => format!("Unicast {} => {}", src, dst),
+        }
     }
 }
 
-pub fn zero(channels: &mut Vec<u8>) {
-    *channels = [0].repeat(CHANNELS_PER_UNIVERSE as usize);
+/// The time between frames at the given rate (per second), exact to the
+/// nanosecond. Keep-alive frames are only sent on the frame schedule, so
+/// e.g. at 10Hz with a 40 FPS cap, rounding up even slightly would push each
+/// one to the next slot after 100ms.
+fn frame_interval(per_second: u64) -> Duration {
+    Duration::from_secs(1) / per_second.clamp(1, u32::MAX as u64) as u32
+}
+
+/// Build a complete ArtDMX (OpOutput) packet, ready to send, for the given
+/// universe (15-bit port address), sequence number (0 to disable sequencing)
+/// and channel data. Odd-length data is padded with a zero, as required.
+/// Fails for a universe above `MAX_UNIVERSE`.
+pub fn build_artdmx_packet(universe: u16, sequence: u8, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let port_address = universe.try_into().map_err(|_| {
+        anyhow!(
+            "universe {} is out of range for an ArtNet port address (0-{})",
+            universe,
+            MAX_UNIVERSE
+        )
+    })?;
+    Ok(ArtCommand::Output(Output {
+        sequence,
+        port_address,
+        data: data.to_vec().into(),
+        ..Output::default()
+    })
+    .write_to_buffer()?)
+}
+
+/// An ArtNet node which replied to an ArtPoll; see `discover`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredNode {
+    pub address: Ipv4Addr,
+    pub short_name: String,
+    pub long_name: String,
+    /// Universes (15-bit port addresses) that the node outputs
+    pub universes: Vec<u16>,
+}
+
+impl DiscoveredNode {
+    fn from_reply(reply: &PollReply) -> Self {
+        let [net, sub_net] = reply.port_address;
+        let num_ports = (reply.num_ports[1] as usize).min(reply.swout.len());
+        DiscoveredNode {
+            address: reply.address,
+            short_name: null_terminated_string(&reply.short_name),
+            long_name: null_terminated_string(&reply.long_name),
+            universes: reply.swout[..num_ports]
+                .iter()
+                .map(|swout| {
+                    ((net as u16 & 0x7f) << 8)
+                        | ((sub_net as u16 & 0x0f) << 4)
+                        | (*swout as u16 & 0x0f)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn null_terminated_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+/// Broadcast an ArtPoll and collect the ArtPollReply from every node that
+/// answers within the timeout. The poll goes to both the "primary" ArtNet
+/// broadcast address (2.255.255.255) and the limited broadcast address, since
+/// many venue networks are not on the 2.x.x.x range. Replies are normally
+/// sent to port 6454, so discovery is most reliable while nothing else here
+/// (e.g. the merge input listener) is bound to it.
+pub fn discover(timeout: Duration) -> anyhow::Result<Vec<DiscoveredNode>> {
+    let socket = match UdpSocket::bind(("0.0.0.0", 6454)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(
+                "Could not bind ArtNet port for discovery ({}); only nodes which reply to the sending port will be found",
+                e
+            );
+            UdpSocket::bind(("0.0.0.0", 0))?
+        }
+    };
+    socket.set_broadcast(true)?;
+
+    let poll = ArtCommand::Poll(Poll::default()).write_to_buffer()?;
+    for broadcast_address in ["2.255.255.255", "255.255.255.255"] {
+        if let Err(e) = socket.send_to(&poll, (broadcast_address, 6454)) {
+            warn!("Failed to send ArtPoll to {}: {}", broadcast_address, e);
+        }
+    }
+    debug!(
+        "ArtPoll sent; waiting {}ms for replies",
+        timeout.as_millis()
+    );
+
+    let mut nodes: Vec<DiscoveredNode> = Vec::new();
+    let started = SystemTime::now();
+    let mut buff = [0u8; 1024];
+    loop {
+        let remaining = timeout.saturating_sub(started.elapsed().unwrap_or(timeout));
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let length = match socket.recv_from(&mut buff) {
+            Ok((length, _src)) => length,
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Ok(ArtCommand::PollReply(reply)) = ArtCommand::from_buffer(&buff[..length]) {
+            let node = DiscoveredNode::from_reply(&reply);
+            if !nodes.iter().any(|n| n.address == node.address) {
+                info!(
+                    "Discovered ArtNet node \"{}\" at {}, universes {:?}",
+                    &node.short_name, node.address, &node.universes
+                );
+                nodes.push(node);
+            }
+        }
+    }
+    Ok(nodes)
+}
+
+/// Bind the socket that ArtNet output is sent from, explaining the likely
+/// cause if that fails
+fn bind_socket(address: SocketAddr) -> anyhow::Result<UdpSocket> {
+    UdpSocket::bind(address).map_err(|e| {
+        let hint = match e.kind() {
+            std::io::ErrorKind::AddrInUse => {
+                "the port is in use, probably by another ArtNet application (see --artnet.srcPort)"
+            }
+            std::io::ErrorKind::AddrNotAvailable => {
+                "the IP address is not one of this machine's network interfaces"
+            }
+            _ => "check the interface address and source port",
+        };
+        anyhow!(
+            "Could not bind ArtNet source address {}: {}; {}",
+            address,
+            e,
+            hint
+        )
+    })
+}
+
+/// Listen for ArtDMX packets on the standard ArtNet port, e.g. from an
+/// upstream lighting console, and pass on the channel data for the given
+/// universe. Packets sent from our own (output) ports are ignored, so that
+/// Broadcast mode output is not merged back into itself. The thread ends on
+/// the first packet received after the receiver is dropped.
+pub fn spawn_input_listener(
+    universe: u16,
+    own_ports: Vec<u16>,
+) -> anyhow::Result<(Receiver<Vec<u8>>, JoinHandle<()>)> {
+    let socket = UdpSocket::bind(("0.0.0.0", ARTNET_PORT))?;
+    info!(
+        "Listening for ArtNet input on universe {} to merge",
+        universe
+    );
+
+    let (tx, rx) = mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        let mut buff = [0u8; 1024];
+        loop {
+            let (length, src) = match socket.recv_from(&mut buff) {
+                Ok(received) => received,
+                Err(e) => {
+                    error!("ArtNet input listener failed: {}", e);
+                    break;
+                }
+            };
+            if own_ports.contains(&src.port()) {
+                continue; // our own output
+            }
+            if let Ok(ArtCommand::Output(output)) = ArtCommand::from_buffer(&buff[..length]) {
+                if u16::from(output.port_address) == universe {
+                    trace!(
+                        "ArtNet input from {}: {} channels",
+                        src,
+                        output.data.as_ref().len()
+                    );
+                    if tx.send(output.data.as_ref().clone()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        debug!("ArtNet input listener ended");
+    });
+    Ok((rx, handle))
+}
+
+pub fn zero(channels: &mut [u8]) {
+    channels.fill(0);
 }
 
 pub fn random(channels: &mut [u8]) {
@@ -203,3 +974,469 @@ pub fn random(channels: &mut [u8]) {
         *c = rng.gen::<u8>();
     }
 }
+
+/// Scale the fixture's intensity channels by the grand master; a 16-bit
+/// (coarse/fine) intensity is scaled as one value, so that dimming keeps its
+/// full resolution
+fn apply_grand_master(output: &mut [u8], f: &FixtureInstance, grand_master: u8) {
+    let mappings = &f.config.active_mode.mappings;
+    // Fine channels are scaled along with their coarse channel
+    let fine_channels: Vec<u16> = mappings.iter().filter_map(|m| m.fine_channel).collect();
+    for m in mappings
+        .iter()
+        .filter(|m| m.role == ChannelRole::Intensity && !fine_channels.contains(&m.channel))
+    {
+        let coarse_index = f.channel_index(m.channel);
+        let Some(coarse) = output.get(coarse_index).copied() else {
+            continue;
+        };
+        let fine_index = m
+            .fine_channel
+            .map(|c| f.channel_index(c))
+            .filter(|i| *i < output.len());
+        match fine_index {
+            Some(fine_index) => {
+                let value = u16::from_be_bytes([coarse, output[fine_index]]);
+                let [coarse, fine] =
+                    ((value as u32 * grand_master as u32 / 255) as u16).to_be_bytes();
+                output[coarse_index] = coarse;
+                output[fine_index] = fine;
+            }
+            None => output[coarse_index] = (coarse as u16 * grand_master as u16 / 255) as u8,
+        }
+        for channel in std::iter::once(m.channel).chain(m.fine_channel) {
+            if let (Some(c), Some([min, max])) = (
+                output.get_mut(f.channel_index(channel)),
+                f.channel_limits.get(&channel),
+            ) {
+                *c = (*c).clamp(*min, *max);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::project::load_all_fixture_configs;
+
+    /// A bundled fixture config, patched in its default mode
+    fn fixture(config_name: &str, universe: u16, offset_channels: u16) -> FixtureInstance {
+        let mut fixture: FixtureInstance = serde_json::from_value(json!({
+            "label": config_name,
+            "configName": config_name,
+            "universe": universe,
+            "offsetChannels": offset_channels
+        }))
+        .unwrap();
+        let config = load_all_fixture_configs()
+            .into_iter()
+            .find(|c| c.name == config_name)
+            .unwrap();
+        fixture.set_config(&config).unwrap();
+        fixture
+    }
+
+    /// A Varytec HERO 340FX (16 channels; brightness on 6, home 0) with its
+    /// brightness macro at the given value
+    fn hero(universe: u16, offset_channels: u16, brightness: u8) -> FixtureInstance {
+        let mut fixture = fixture("Varytec HERO 340FX", universe, offset_channels);
+        for m in fixture.config.active_mode.macros.iter_mut() {
+            if let FixtureMacro::Control(control_macro) = m {
+                if control_macro.label == "brightness" {
+                    control_macro.current_value = brightness;
+                }
+            }
+        }
+        fixture
+    }
+
+    fn update(artnet: &mut ArtNetInterface, channels_state: &[u8]) -> bool {
+        artnet.update(
+            channels_state,
+            &BTreeMap::new(),
+            &[],
+            &[],
+            false,
+            &BTreeMap::new(),
+        )
+    }
+
+    #[test]
+    fn dry_run_records_frames() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(8);
+        assert!(artnet.is_dry_run());
+        assert_eq!(artnet.recorded_frames(), Some(Vec::new()));
+
+        assert!(update(&mut artnet, &[1, 2, 3, 4, 5, 6, 7, 8]));
+        assert_eq!(
+            artnet.recorded_frames(),
+            Some(vec![(DEFAULT_UNIVERSE, vec![1, 2, 3, 4, 5, 6, 7, 8])])
+        );
+    }
+
+    #[test]
+    fn dry_run_records_blackout_as_output() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(4);
+        artnet.set_blackout(true);
+        assert!(update(&mut artnet, &[255; 4]));
+        assert_eq!(
+            artnet.recorded_frames().unwrap().last(),
+            Some(&(DEFAULT_UNIVERSE, vec![0; 4]))
+        );
+        // ...while the state read back is untouched
+        assert_eq!(artnet.get_state(), &[255; 4]);
+    }
+
+    #[test]
+    fn fixture_writes_to_its_own_universe() {
+        let mut artnet = ArtNetInterface::new_dry_run(40);
+        let extra_universes = BTreeMap::from([(2, vec![0; 512])]);
+        artnet.update(
+            &[0; 512],
+            &extra_universes,
+            &[hero(2, 1, 77)],
+            &[],
+            true,
+            &BTreeMap::new(),
+        );
+        // Channel 6 of a fixture at offset 1 is index 6
+        assert_eq!(artnet.get_extra_states()[&2][6], 77);
+        assert!(artnet.get_state().iter().all(|c| *c == 0));
+    }
+
+    #[test]
+    fn fixture_past_end_of_universe_is_truncated() {
+        let mut artnet = ArtNetInterface::new_dry_run(40);
+        let extra_universes = BTreeMap::from([(1, vec![0; 512]), (2, vec![0; 512])]);
+        // Brightness (channel 6) would be index 512, i.e. beyond universe 1
+        artnet.update(
+            &[0; 512],
+            &extra_universes,
+            &[hero(1, 507, 255)],
+            &[],
+            true,
+            &BTreeMap::new(),
+        );
+        let states = artnet.get_extra_states();
+        assert_eq!(states[&1].len(), 512);
+        assert!(states[&1].iter().all(|c| *c == 0));
+        assert!(states[&2].iter().all(|c| *c == 0));
+    }
+
+    #[test]
+    fn grand_master_scales_16bit_intensity_as_one_value() {
+        // Dimmer on 5, fine on 6
+        let opus = fixture("Cameo Opus S5", DEFAULT_UNIVERSE, 0);
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(32);
+        artnet.set_grand_master(128);
+        let mut channels_state = [0; 32];
+        channels_state[4] = 1; // 256 as 16-bit
+        artnet.update(
+            &channels_state,
+            &BTreeMap::new(),
+            &[opus],
+            &[],
+            false,
+            &BTreeMap::new(),
+        );
+        let (_, frame) = artnet.recorded_frames().unwrap().pop().unwrap();
+        // Scaled separately, both bytes would round down to 0
+        assert_eq!((frame[4], frame[5]), (0, 128));
+    }
+
+    #[test]
+    fn grand_master_scales_8bit_intensity() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(16);
+        artnet.set_grand_master(128);
+        artnet.update(
+            &[255; 16],
+            &BTreeMap::new(),
+            &[hero(DEFAULT_UNIVERSE, 0, 0)],
+            &[],
+            false,
+            &BTreeMap::new(),
+        );
+        let (_, frame) = artnet.recorded_frames().unwrap().pop().unwrap();
+        assert_eq!(frame[5], 128);
+        // Only intensity channels are scaled
+        assert_eq!(frame[6], 255);
+    }
+
+    #[test]
+    fn artdmx_packet_header() {
+        let packet = build_artdmx_packet(0x1234, 7, &[10, 20, 30, 40]).unwrap();
+        assert_eq!(&packet[0..8], b"Art-Net\0");
+        assert_eq!(&packet[8..10], &[0x00, 0x50]); // OpOutput, little-endian
+        assert_eq!(&packet[10..12], &[0, 14]); // protocol version, big-endian
+        assert_eq!(packet[12], 7); // sequence
+        assert_eq!(&packet[14..16], &[0x34, 0x12]); // universe, little-endian
+        assert_eq!(&packet[16..18], &[0, 4]); // length, big-endian
+        assert_eq!(&packet[18..], &[10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn artdmx_packet_full_universe() {
+        let packet = build_artdmx_packet(MAX_UNIVERSE, 0, &[255; 512]).unwrap();
+        assert_eq!(&packet[14..16], &[0xff, 0x7f]);
+        assert_eq!(&packet[16..18], &[0x02, 0x00]);
+        assert_eq!(packet.len(), 18 + 512);
+    }
+
+    #[test]
+    fn artdmx_packet_odd_length_is_padded() {
+        let packet = build_artdmx_packet(0, 0, &[1, 2, 3]).unwrap();
+        assert_eq!(&packet[16..18], &[0, 4]);
+        assert_eq!(&packet[18..], &[1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn artdmx_packet_universe_out_of_range() {
+        assert!(build_artdmx_packet(MAX_UNIVERSE + 1, 0, &[0; 2]).is_err());
+        assert!(build_artdmx_packet(u16::MAX, 0, &[0; 2]).is_err());
+    }
+
+    /// A socket to send ArtNet to over loopback, which gives up waiting
+    /// for a packet after a while
+    fn receiver() -> UdpSocket {
+        let socket = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        socket
+    }
+
+    /// An ArtNet output sending (unicast) over loopback to the given receiver
+    fn loopback_output(receiver: &UdpSocket) -> ArtNetOutput {
+        ArtNetOutput::new(ArtNetMode::Unicast(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            receiver.local_addr().unwrap(),
+        ))
+        .unwrap()
+    }
+
+    /// The next packet received, if any
+    fn receive(socket: &UdpSocket) -> Option<Vec<u8>> {
+        let mut buff = [0u8; 1024];
+        socket
+            .recv(&mut buff)
+            .ok()
+            .map(|length| buff[..length].to_vec())
+    }
+
+    /// The universe and channel data of an ArtDMX packet
+    fn artdmx(packet: &[u8]) -> (u16, Vec<u8>) {
+        match ArtCommand::from_buffer(packet).unwrap() {
+            ArtCommand::Output(output) => {
+                (u16::from(output.port_address), output.data.as_ref().clone())
+            }
+            _ => panic!("not an ArtDMX packet"),
+        }
+    }
+
+    #[test]
+    fn artnet_output_sends_artdmx() {
+        let receiver = receiver();
+        let mut output = loopback_output(&receiver);
+        output.send_universe(3, &[1, 2, 3, 4]);
+        output.end_frame(&[3]);
+        assert_eq!(artdmx(&receive(&receiver).unwrap()), (3, vec![1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn universe_out_of_range_is_skipped() {
+        let receiver = receiver();
+        let mut output = loopback_output(&receiver);
+        output.send_universe(u16::MAX, &[0; 512]);
+        output.send_universe(1, &[0; 512]);
+        assert_eq!(artdmx(&receive(&receiver).unwrap()).0, 1);
+    }
+
+    #[test]
+    fn artsync_is_sent_once_per_node() {
+        let receiver = receiver();
+        let mut output = loopback_output(&receiver).with_sync(true);
+        output.send_universe(0, &[0; 2]);
+        output.send_universe(1, &[0; 2]);
+        output.end_frame(&[0, 1]);
+        assert_eq!(artdmx(&receive(&receiver).unwrap()).0, 0);
+        assert_eq!(artdmx(&receive(&receiver).unwrap()).0, 1);
+        let sync = receive(&receiver).unwrap();
+        assert_eq!(sync, {
+            let mut expected = ArtCommand::Sync.write_to_buffer().unwrap();
+            expected.extend_from_slice(&ARTNET_PROTOCOL_VERSION);
+            expected.extend_from_slice(&[0, 0]);
+            expected
+        });
+        receiver
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        assert_eq!(receive(&receiver), None);
+    }
+
+    #[test]
+    fn routed_universe_goes_to_its_node() {
+        let default_node = receiver();
+        let routed_node = receiver();
+        let mut output = loopback_output(&default_node)
+            .with_routes(BTreeMap::from([(1, routed_node.local_addr().unwrap())]));
+        output.send_universe(0, &[0; 2]);
+        output.send_universe(1, &[0; 2]);
+        assert_eq!(artdmx(&receive(&default_node).unwrap()).0, 0);
+        assert_eq!(artdmx(&receive(&routed_node).unwrap()).0, 1);
+    }
+
+    #[test]
+    fn state_read_back_excludes_output_only_changes() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(32);
+        let mut disabled = hero(0, 0, 0);
+        disabled.enabled = false;
+        let mut limited = hero(0, 16, 0);
+        limited.channel_limits.insert(1, [0, 100]);
+        artnet.update(
+            &[200; 32],
+            &BTreeMap::new(),
+            &[disabled, limited],
+            &[],
+            false,
+            &BTreeMap::from([(32, 5)]),
+        );
+        let output = &artnet.recorded_frames().unwrap()[0].1;
+        assert_eq!(output[0], 0); // disabled
+        assert_eq!(output[16], 100); // limited
+        assert_eq!(output[31], 5); // overridden
+        assert_eq!(artnet.get_state(), &[200; 32]);
+    }
+
+    #[test]
+    fn ltp_merge_follows_latest_change() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(4);
+        let next_frame = |artnet: &mut ArtNetInterface, state: &[u8]| {
+            artnet.send_next_frame_now();
+            update(artnet, state);
+            artnet.recorded_frames().unwrap().last().unwrap().1.clone()
+        };
+        assert_eq!(next_frame(&mut artnet, &[10; 4]), vec![10; 4]);
+
+        // The input takes over the channels it changes...
+        artnet.set_merge_input(vec![50, 50, 0, 0], MergeMode::Ltp);
+        assert_eq!(next_frame(&mut artnet, &[10; 4]), vec![50, 50, 10, 10]);
+        assert_eq!(next_frame(&mut artnet, &[10; 4]), vec![50, 50, 10, 10]);
+
+        // ...until a local change takes one back...
+        assert_eq!(
+            next_frame(&mut artnet, &[20, 10, 10, 10]),
+            vec![20, 50, 10, 10]
+        );
+        assert_eq!(artnet.get_state(), &[20, 10, 10, 10]);
+
+        // ...and the input can change it again
+        artnet.set_merge_input(vec![60, 50, 0, 0], MergeMode::Ltp);
+        assert_eq!(
+            next_frame(&mut artnet, &[20, 10, 10, 10]),
+            vec![60, 50, 10, 10]
+        );
+    }
+
+    #[test]
+    fn small_universe_truncates_or_pads_state() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(8);
+        update(&mut artnet, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(
+            artnet.recorded_frames().unwrap().last().unwrap().1,
+            vec![1, 2, 3, 4, 5, 6, 7, 8]
+        );
+
+        artnet.send_next_frame_now();
+        update(&mut artnet, &[1, 2, 3]);
+        assert_eq!(
+            artnet.recorded_frames().unwrap().last().unwrap().1,
+            vec![1, 2, 3, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn fixture_past_end_of_small_universe_is_truncated() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(8);
+        let mut fixture = hero(0, 0, 200);
+        for m in fixture.config.active_mode.macros.iter_mut() {
+            if let FixtureMacro::Colour(colour_macro) = m {
+                colour_macro.current_value = egui::Color32::from_rgb(10, 20, 30);
+            }
+        }
+        // Brightness on channel 6 fits; of RGBW on channels 8-11, only red does
+        artnet.update(
+            &[0; 8],
+            &BTreeMap::new(),
+            &[fixture],
+            &[],
+            true,
+            &BTreeMap::new(),
+        );
+        let output = &artnet.recorded_frames().unwrap()[0].1;
+        assert_eq!(output.len(), 8);
+        assert_eq!(output[5], 200);
+        assert_eq!(output[7], 10);
+    }
+
+    #[test]
+    fn universe_size_is_clamped() {
+        let mut artnet = ArtNetInterface::new_dry_run(40).with_universe_size(1000);
+        update(&mut artnet, &[]);
+        assert_eq!(artnet.recorded_frames().unwrap()[0].1.len(), 512);
+    }
+
+    /// Update every millisecond for one (simulated) second, with the channel
+    /// values given for each millisecond; returns how many frames were sent
+    fn frames_sent_in_one_second(artnet: &mut ArtNetInterface, state_at: fn(u64) -> u8) -> usize {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        (0..1000)
+            .filter(|ms| {
+                artnet.update_at(
+                    start + Duration::from_millis(*ms),
+                    &[state_at(*ms); 8],
+                    &BTreeMap::new(),
+                    &[],
+                    &[],
+                    false,
+                    &BTreeMap::new(),
+                )
+            })
+            .count()
+    }
+
+    #[test]
+    fn burst_of_changes_is_capped_at_max_fps() {
+        let mut artnet = ArtNetInterface::new_dry_run(10)
+            .with_universe_size(8)
+            .with_max_fps(Some(40));
+        // A change every millisecond
+        let sent = frames_sent_in_one_second(&mut artnet, |ms| ms as u8);
+        assert!((39..=41).contains(&sent), "sent {} frames", sent);
+    }
+
+    #[test]
+    fn unchanged_frames_repeat_at_update_frequency() {
+        let mut artnet = ArtNetInterface::new_dry_run(10)
+            .with_universe_size(8)
+            .with_max_fps(Some(40));
+        let sent = frames_sent_in_one_second(&mut artnet, |_| 0);
+        assert!((10..=11).contains(&sent), "sent {} frames", sent);
+    }
+
+    #[test]
+    fn without_max_fps_changes_wait_for_update_frequency() {
+        let mut artnet = ArtNetInterface::new_dry_run(10).with_universe_size(8);
+        let sent = frames_sent_in_one_second(&mut artnet, |ms| ms as u8);
+        assert!((10..=11).contains(&sent), "sent {} frames", sent);
+    }
+
+    #[test]
+    fn only_dry_run_records_frames() {
+        let (output, _frames) = MockOutput::new(1);
+        let artnet = ArtNetInterface::new(Box::new(output), 40);
+        assert_eq!(artnet.recorded_frames(), None);
+    }
+}
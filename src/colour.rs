@@ -0,0 +1,127 @@
+use egui::{ecolor::Hsva, Color32};
+use serde::{Deserialize, Serialize};
+
+use crate::animation::animate_colour;
+
+/// Warmest colour temperature offered, roughly tungsten
+pub const MIN_KELVIN: u16 = 2700;
+/// Coolest colour temperature offered, roughly daylight
+pub const MAX_KELVIN: u16 = 6500;
+pub const DEFAULT_KELVIN: u16 = 3200;
+
+/// How a colour animation gets from one colour to another
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ColourSpace {
+    /// Straight blend of each RGB component
+    #[default]
+    Rgb,
+    /// Sweep around the hue circle; see `interpolate_hsv`
+    Hsv,
+}
+
+impl ColourSpace {
+    pub fn interpolate(&self, start: &Color32, end: &Color32, progress: f32) -> Color32 {
+        match self {
+            ColourSpace::Rgb => animate_colour(start, end, progress),
+            ColourSpace::Hsv => interpolate_hsv(*start, *end, progress),
+        }
+    }
+}
+
+/// A single component of a Colour Macro, which can be addressed on its own
+/// by adding a suffix to the macro label, e.g. "Colour.r"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColourComponent {
+    Red,
+    Green,
+    Blue,
+}
+
+impl ColourComponent {
+    /// Split a label such as "Colour.r" into the macro label and component;
+    /// None if the label has no (valid) component suffix
+    pub fn split_label(label: &str) -> Option<(&str, ColourComponent)> {
+        let (macro_label, suffix) = label.rsplit_once('.')?;
+        let component = match suffix.to_ascii_lowercase().as_str() {
+            "r" => ColourComponent::Red,
+            "g" => ColourComponent::Green,
+            "b" => ColourComponent::Blue,
+            _ => return None,
+        };
+        Some((macro_label, component))
+    }
+
+    pub fn get(&self, colour: Color32) -> u8 {
+        match self {
+            ColourComponent::Red => colour.r(),
+            ColourComponent::Green => colour.g(),
+            ColourComponent::Blue => colour.b(),
+        }
+    }
+
+    /// A copy of the colour with only this component changed
+    pub fn set(&self, colour: Color32, value: u8) -> Color32 {
+        let [r, g, b, a] = colour.to_array();
+        match self {
+            ColourComponent::Red => Color32::from_rgba_premultiplied(value, g, b, a),
+            ColourComponent::Green => Color32::from_rgba_premultiplied(r, value, b, a),
+            ColourComponent::Blue => Color32::from_rgba_premultiplied(r, g, value, a),
+        }
+    }
+}
+
+/// Approximate the RGB colour of a blackbody radiator at the given temperature
+/// (in Kelvin), using the curve fit by Tanner Helland. The alpha component is
+/// set as given, so that brightness/white mix can be preserved.
+pub fn kelvin_to_colour(kelvin: u16, alpha: u8) -> Color32 {
+    let temp = kelvin.clamp(MIN_KELVIN, MAX_KELVIN) as f32 / 100.0;
+
+    let r = if temp <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (temp - 60.0).powf(-0.133_204_76)
+    };
+
+    let g = if temp <= 66.0 {
+        99.470_8 * temp.ln() - 161.119_57
+    } else {
+        288.122_17 * (temp - 60.0).powf(-0.075_514_85)
+    };
+
+    let b = if temp >= 66.0 {
+        255.0
+    } else if temp <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (temp - 10.0).ln() - 305.044_8
+    };
+
+    Color32::from_rgba_unmultiplied(
+        r.clamp(0., 255.) as u8,
+        g.clamp(0., 255.) as u8,
+        b.clamp(0., 255.) as u8,
+        alpha,
+    )
+}
+
+/// Interpolate between two colours in HSV space (taking the shorter way round
+/// the hue circle), which avoids the muddy middle of a straight RGB blend,
+/// e.g. red to blue passes through magenta rather than dark purple
+pub fn interpolate_hsv(start: Color32, end: Color32, progress: f32) -> Color32 {
+    let a = Hsva::from(start);
+    let b = Hsva::from(end);
+    let mut hue_distance = b.h - a.h;
+    if hue_distance > 0.5 {
+        hue_distance -= 1.0;
+    } else if hue_distance < -0.5 {
+        hue_distance += 1.0;
+    }
+    let lerp = |x: f32, y: f32| x + (y - x) * progress;
+    Color32::from(Hsva {
+        h: (a.h + hue_distance * progress).rem_euclid(1.0),
+        s: lerp(a.s, b.s),
+        v: lerp(a.v, b.v),
+        a: lerp(a.a, b.a),
+    })
+}
@@ -9,9 +9,15 @@ use crate::{model::Model, settings::Cli, ui::NARROW_WINDOW};
 
 mod animation;
 mod artnet;
+mod colour;
 mod model;
+mod osc;
+mod output;
 pub mod project;
+mod sacn;
 mod settings;
+mod state;
+mod status;
 mod tether_interface;
 mod ui;
 
@@ -50,9 +56,11 @@ fn main() {
                 info!("Headless loop should quit");
                 should_quit = true;
             }
-            std::thread::sleep(Duration::from_millis(1));
+            model.wait_for_next_update();
             model.update();
         }
+        // The GUI does this on closing the window
+        model.reset_before_quit();
     } else {
         info!("Running graphics mode; close the window to quit");
         let options = eframe::NativeOptions {
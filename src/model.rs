@@ -1,26 +1,47 @@
 use std::{
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use anyhow::anyhow;
 use egui::Color32;
+use indexmap::IndexSet;
 use log::{debug, error, info, trace, warn};
 use tween::SineInOut;
 
 use crate::{
-    animation::{animate_colour, Animation},
-    artnet::{random, zero, ArtNetInterface},
+    animation::{Animation, DurationSpec},
+    artnet::{discover, random, spawn_input_listener, zero, ArtNetInterface, DiscoveredNode},
+    colour::{interpolate_hsv, kelvin_to_colour, ColourComponent, ColourSpace},
+    osc::spawn_osc_listener,
     project::{
         artnetconfig::{get_artnet_interface, ArtNetConfigMode},
         fixture::{FixtureConfig, FixtureInstance, FixtureMacro},
-        load_all_fixture_configs, Project, SceneValue,
+        gdtf::load_gdtf,
+        load_all_fixture_configs, load_fixture_config,
+        midiconfig::MidiBinding,
+        ofl::load_ofl,
+        palette_colour, save_imported_fixture_config, Project, SceneValue,
+    },
+    settings::{
+        Cli, MergeMode, CHANNELS_PER_UNIVERSE, DEFAULT_BROADCAST_SRC_PORT,
+        DEFAULT_UNICAST_SRC_PORT, DEFAULT_UNIVERSE, IMPORTED_FIXTURES_DIR,
+        MACRO_FEEDBACK_INTERVAL_MS, MAX_UNIVERSE, STATE_FILE_PATH, UNDO_LIMIT,
     },
-    settings::{Cli, CHANNELS_PER_UNIVERSE},
+    state::PersistentState,
+    status::{MacroFeedbackPublisher, StatusPublisher},
     tether_interface::{
-        RemoteControlMessage, RemoteMacroMessage, RemoteMacroValue, RemoteSceneMessage,
-        TetherControlChangePayload, TetherInterface, TetherKnobPayload, TetherMidiMessage,
-        TetherNotePayload,
+        CueListCommand, RemoteChannelControlMessage, RemoteChannelMessage, RemoteControlMessage,
+        RemoteCueListMessage, RemoteFixtureModeMessage, RemoteFixtureState,
+        RemoteMacroBatchMessage, RemoteMacroMessage, RemoteMacroValue, RemotePresetMessage,
+        RemoteProjectState, RemoteSceneMessage, TetherControlChangePayload, TetherInterface,
+        TetherKnobPayload, TetherMidiMessage, TetherNotePayload, TetherOutgoingMessage,
     },
     ui::{render_gui, ViewMode},
 };
@@ -35,14 +56,113 @@ pub enum BehaviourOnExit {
 pub enum TetherStatus {
     NotConnected,
     Connected,
+    /// Was connected, but lost the connection; attempting to reconnect
+    Reconnecting,
     Errored(String),
 }
 
+/// Position in a Cue List being played back
+pub struct CuePlayback {
+    pub cue_list_index: usize,
+    pub cue_index: usize,
+    /// When to advance to the next cue automatically; None to wait for GO
+    pub next_at: Option<Instant>,
+}
+
+/// Fading in from zero to the home values on startup
+pub struct StartupFade {
+    animation: Animation,
+    home_values: Vec<u8>,
+    /// What the fade last wrote to each channel; None once something else has
+    /// written a different value, after which the fade leaves that channel alone
+    faded: Vec<Option<u8>>,
+}
+
+impl StartupFade {
+    /// Starts from zero, so the channels should be zeroed at the same time
+    pub fn new(duration: Duration, home_values: Vec<u8>) -> Self {
+        StartupFade {
+            animation: Animation::new(duration, 0., 1., Box::new(SineInOut)),
+            faded: vec![Some(0); home_values.len()],
+            home_values,
+        }
+    }
+
+    /// Step the fade for every channel which has not been written since the
+    /// fade started; returns true when the fade is done
+    fn apply(&mut self, channels_state: &mut [u8]) -> bool {
+        let (progress, is_done) = self.animation.get_value_and_done();
+        for ((c, home), faded) in channels_state
+            .iter_mut()
+            .zip(self.home_values.iter())
+            .zip(self.faded.iter_mut())
+        {
+            if *faded != Some(*c) {
+                *faded = None;
+                continue;
+            }
+            *c = (*home as f32 * progress) as u8;
+            *faded = Some(*c);
+        }
+        is_done
+    }
+}
+
 pub struct Model {
     pub settings: Cli,
     pub handles: Vec<JoinHandle<()>>,
+    /// Channel values for `DEFAULT_UNIVERSE`, which is also the universe shown
+    /// (and directly editable) in the GUI
     pub channels_state: Vec<u8>,
+    /// Universe => channel values, for every other universe that has fixtures
+    /// or pixel maps patched on it
+    pub extra_universes: BTreeMap<u16, Vec<u8>>,
+    /// Number of channels in each universe output; at most
+    /// `CHANNELS_PER_UNIVERSE`
+    pub universe_size: u16,
+    /// Which channels of `DEFAULT_UNIVERSE` are taken by fixtures
     pub channels_assigned: Vec<bool>,
+    /// Channel number (1-512) => value; these are applied on top of everything
+    /// else, including macros, until cleared. They only apply to the output:
+    /// `channels_state` keeps the underlying (e.g. macro) values, which are
+    /// output again once the override is cleared.
+    pub channel_overrides: BTreeMap<u16, u8>,
+    /// While fading in on startup
+    pub startup_fade: Option<StartupFade>,
+    /// Ticks once per second (from a timer thread) while the auto random/zero
+    /// test modes are enabled
+    pub auto_tick_rx: Option<Receiver<()>>,
+    /// Result of an ArtNet node discovery in progress, if any
+    pub discovery_rx: Option<Receiver<anyhow::Result<Vec<DiscoveredNode>>>>,
+    /// Nodes found by the latest ArtNet node discovery
+    pub discovered_nodes: Vec<DiscoveredNode>,
+    /// Channel values from an upstream console, if merging ArtNet input
+    pub merge_input_rx: Option<Receiver<Vec<u8>>>,
+    /// The label of the Scene applied most recently, if any
+    pub last_scene: Option<String>,
+    /// Which set of nine Scenes the number keys 1-9 trigger: bank 0 is Scenes
+    /// 1-9, bank 1 is Scenes 10-18, etc.
+    pub scene_bank: usize,
+    /// The index and label of a Scene waiting for confirmation before it is
+    /// deleted
+    pub confirm_delete_scene: Option<(usize, String)>,
+    /// The Cue List currently playing, if any
+    pub cue_playback: Option<CuePlayback>,
+    /// The state as last saved to disk, if state is being persisted
+    pub saved_state: Option<PersistentState>,
+    /// Snapshots of the Project from before each edit, most recent last
+    pub undo_stack: VecDeque<Project>,
+    /// Snapshots of the Project from before each undo, most recent last
+    pub redo_stack: Vec<Project>,
+    /// The Project (and its JSON) as of the last recorded edit, to compare
+    /// against for the next one
+    pub undo_checkpoint: Option<(String, Project)>,
+    /// None if status (heartbeat) messages are disabled
+    pub status_publisher: Option<StatusPublisher>,
+    /// Publishes changed macro values, if enabled via `--tether.publishState`
+    pub macro_feedback: Option<MacroFeedbackPublisher>,
+    /// UI for adding a channel override; channel number and value
+    pub new_channel_override: (u16, u8),
     pub tether_interface: TetherInterface,
     pub tether_status: TetherStatus,
     /// A working, connected ArtNet interface, or None if disconnected
@@ -54,6 +174,10 @@ pub struct Model {
     pub project: Project,
     /// If None, we are in a New/Unsaved project
     pub current_project_path: Option<String>,
+    /// The Project JSON as last (auto-)saved or loaded, to tell whether there
+    /// is anything new to auto-save
+    pub last_saved_project: Option<String>,
+    pub last_auto_save: Instant,
     pub adding_new_fixture: bool,
     pub new_fixture_to_add: Option<FixtureInstance>,
     pub known_fixtures: Vec<FixtureConfig>,
@@ -62,12 +186,39 @@ pub struct Model {
     /// It is important that this is _disabled_ when adjusting channel
     /// values directly, e.g. in Setup mode.
     pub apply_macros: bool,
+    /// Output all zeros, while leaving channels and macros untouched
+    pub blackout: bool,
+    /// Scales all Intensity channels on output (255 = full)
+    pub grand_master: u8,
     /// Determines which macros are adjusted via MIDI
     pub selected_macro_group_index: usize,
+    /// MIDI Learn: fixture and macro label to bind to the next incoming
+    /// Control Change message
+    pub midi_learn_target: Option<(String, String)>,
+    /// UI for comparing two Scenes, by index
+    pub compare_scenes: (usize, usize),
+    /// Labels of fixtures selected for bulk macro edits
+    pub selected_fixtures: IndexSet<String>,
+    /// Bulk edits: fade duration, and stagger between successive fixtures
+    /// (0 for none)
+    pub bulk_fade_ms: u64,
+    pub bulk_stagger_ms: u64,
+    /// Index of the fixture last clicked (without Shift), where a Shift-click
+    /// range selection starts from
+    pub selection_anchor: Option<usize>,
+    /// UI for applying a gradient across all fixtures; see `apply_gradient`
+    pub gradient_colours: (Color32, Color32),
+    /// Tempo in BPM, if set, for animation durations given in beats
+    pub current_bpm: Option<f32>,
+    /// The colour most recently applied to all fixtures; see `set_all_colour`
+    pub master_colour: Color32,
     pub view_mode: ViewMode,
     pub exit_mode: BehaviourOnExit,
     pub save_on_exit: bool,
     pub show_confirm_exit: bool,
+    /// Why ArtNet output could not be started, shown (in the GUI) until
+    /// dismissed
+    pub artnet_error: Option<String>,
     pub allowed_to_close: bool,
     pub should_quit: Arc<Mutex<bool>>,
 }
@@ -109,111 +260,612 @@ impl Model {
         };
 
         let artnet = get_artnet_interface(&cli, &project);
+        let mut model = Model::from_parts(cli, project, artnet, current_project_path);
 
-        let fixtures_clone = project.clone().fixtures;
+        if !model.settings.tether_disable_autoconnect {
+            info!("Auto connect Tether enabled; will attempt to connect now...");
+            attempt_connection(&mut model)
+        }
 
-        let mut channels_assigned: Vec<bool> = [false].repeat(CHANNELS_PER_UNIVERSE as usize);
-        for fixture in fixtures_clone.iter() {
-            let current_mode = &fixture.config.modes[0];
-            for m in &current_mode.mappings {
-                let channel_index = m.channel + fixture.offset_channels - 1;
-                channels_assigned[channel_index as usize] = true;
+        if model.settings.auto_random || model.settings.auto_zero {
+            let (auto_tick_tx, auto_tick_rx) = mpsc::channel();
+            model.auto_tick_rx = Some(auto_tick_rx);
+            model.handles.push(std::thread::spawn(move || loop {
+                std::thread::sleep(Duration::from_secs(1));
+                if auto_tick_tx.send(()).is_err() {
+                    break;
+                }
+            }));
+        }
+
+        if model.settings.artnet_merge_input != MergeMode::Off {
+            // Spawned once here rather than by the ArtNet interface, which may
+            // be re-created while the listener keeps its socket
+            let own_ports = match model.settings.artnet_src_port {
+                Some(port) => vec![port],
+                None => vec![DEFAULT_UNICAST_SRC_PORT, DEFAULT_BROADCAST_SRC_PORT],
+            };
+            match spawn_input_listener(DEFAULT_UNIVERSE, own_ports) {
+                Ok((merge_input_rx, handle)) => {
+                    model.merge_input_rx = Some(merge_input_rx);
+                    model.handles.push(handle);
+                }
+                Err(e) => error!("Failed to listen for ArtNet input to merge: {}", e),
             }
         }
 
+        if let Some(port) = model.settings.osc_port {
+            match spawn_osc_listener(port, model.tether_interface.message_sender()) {
+                Ok(handle) => model.handles.push(handle),
+                Err(e) => error!("Failed to listen for OSC on port {}: {}", port, e),
+            }
+        }
+
+        if model.settings.startup_fade_ms > 0 {
+            info!(
+                "Fade in to home values over {}ms",
+                model.settings.startup_fade_ms
+            );
+            let home_values = model.channels_state.clone();
+            zero(&mut model.channels_state);
+            model.startup_fade = Some(StartupFade::new(
+                Duration::from_millis(model.settings.startup_fade_ms),
+                home_values,
+            ));
+        }
+
+        if model.settings.restore_state {
+            model.restore_state();
+        }
+
+        model
+    }
+
+    /// For tests: a Model for the given Project which sends every frame via
+    /// the given output (e.g. a `MockOutput`), without loading any files,
+    /// connecting to Tether or starting any threads; remote messages can
+    /// still be sent via `tether_interface.message_sender()`
+    #[cfg(test)]
+    pub fn with_output(
+        cli: Cli,
+        project: Project,
+        output: Box<dyn crate::output::DmxOutput>,
+    ) -> Model {
+        let artnet = ArtNetInterface::new(output, cli.artnet_update_frequency)
+            .with_smoothing(cli.artnet_smoothing)
+            .with_universe_size(cli.universe_size)
+            .with_max_fps(cli.artnet_max_fps);
+        Model::from_parts(cli, project, Ok(artnet), None)
+    }
+
+    /// The Model with everything initialised and home values applied, but
+    /// nothing started
+    fn from_parts(
+        cli: Cli,
+        project: Project,
+        artnet: anyhow::Result<ArtNetInterface>,
+        current_project_path: Option<String>,
+    ) -> Model {
+        let artnet_error = artnet.as_ref().err().map(|e| e.to_string());
+        let last_saved_project = Project::to_json(&project).ok();
+
         let should_quit = Arc::new(Mutex::new(false));
 
         let tether_interface = TetherInterface::new();
 
-        let should_auto_connect = !cli.tether_disable_autoconnect;
+        let universe_size = cli.universe_size.clamp(2, CHANNELS_PER_UNIVERSE);
+        if universe_size != cli.universe_size {
+            warn!(
+                "Universe size {} out of range; will use {}",
+                cli.universe_size, universe_size
+            );
+        }
 
         let mut model = Model {
             tether_status: TetherStatus::NotConnected,
             handles: Vec::new(),
             tether_interface,
             channels_state: Vec::new(),
-            channels_assigned,
+            extra_universes: BTreeMap::new(),
+            universe_size,
+            channels_assigned: Vec::new(),
+            channel_overrides: BTreeMap::new(),
+            new_channel_override: (1, 0),
+            startup_fade: None,
+            auto_tick_rx: None,
+            merge_input_rx: None,
+            discovery_rx: None,
+            discovered_nodes: Vec::new(),
+            last_scene: None,
+            scene_bank: 0,
+            confirm_delete_scene: None,
+            cue_playback: None,
+            saved_state: None,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            undo_checkpoint: None,
+            status_publisher: if cli.status_interval_ms > 0 {
+                Some(StatusPublisher::new(Duration::from_millis(
+                    cli.status_interval_ms,
+                )))
+            } else {
+                None
+            },
+            macro_feedback: if cli.publish_state {
+                Some(MacroFeedbackPublisher::new(Duration::from_millis(
+                    MACRO_FEEDBACK_INTERVAL_MS,
+                )))
+            } else {
+                None
+            },
             settings: cli,
             artnet: match artnet {
                 Ok(artnet) => Some(artnet),
-                Err(_) => None,
+                Err(e) => {
+                    error!("ArtNet output is disabled: {}", e);
+                    None
+                }
             },
             artnet_edit_mode: ArtNetConfigMode::Broadcast,
             project,
+            last_saved_project,
+            last_auto_save: Instant::now(),
             // ----
             known_fixtures: load_all_fixture_configs(),
             adding_new_fixture: false,
             new_fixture_to_add: None,
             // ----
             current_project_path,
+            blackout: false,
+            grand_master: 255,
             selected_macro_group_index: 0,
+            midi_learn_target: None,
+            master_colour: Color32::WHITE,
+            current_bpm: None,
+            gradient_colours: (Color32::RED, Color32::BLUE),
+            compare_scenes: (0, 1),
+            selected_fixtures: IndexSet::new(),
+            bulk_fade_ms: 0,
+            bulk_stagger_ms: 0,
+            selection_anchor: None,
             apply_macros: false,
             view_mode: ViewMode::Scenes,
             exit_mode: BehaviourOnExit::Home,
             save_on_exit: true,
             show_confirm_exit: false,
+            artnet_error,
             allowed_to_close: false,
             should_quit,
         };
 
-        if should_auto_connect {
-            info!("Auto connect Tether enabled; will attempt to connect now...");
-            attempt_connection(&mut model)
-        }
-
+        model.update_channels_assigned();
         model.apply_home_values();
 
         model
     }
 
-    pub fn update(&mut self) {
-        let mut work_done = false;
+    fn restore_state(&mut self) {
+        let state = match PersistentState::load(STATE_FILE_PATH) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!(
+                    "No saved state restored from \"{}\": {}",
+                    STATE_FILE_PATH, e
+                );
+                return;
+            }
+        };
+        self.apply_macros = state.apply_macros;
+        self.selected_macro_group_index = state.selected_macro_group_index;
+        if let Some(label) = &state.last_scene {
+            match self
+                .project
+                .scenes
+                .iter()
+                .position(|s| s.label.eq_ignore_ascii_case(label))
+            {
+                Some(index) => {
+                    info!("Restore last Scene \"{}\"", label);
+                    self.apply_scene(index, None, None, None);
+                    self.project.scenes[index].last_active = true;
+                }
+                None => warn!("Last Scene \"{}\" not found in Project", label),
+            }
+        }
+        self.saved_state = Some(state);
+    }
+
+    fn current_state(&self) -> PersistentState {
+        PersistentState {
+            apply_macros: self.apply_macros,
+            selected_macro_group_index: self.selected_macro_group_index,
+            last_scene: self.last_scene.clone(),
+        }
+    }
 
-        while let Ok(m) = self.tether_interface.message_rx.try_recv() {
-            work_done = true;
-            self.apply_macros = true;
-            match m {
-                RemoteControlMessage::Midi(midi_msg) => {
-                    self.handle_midi_message(midi_msg);
+    /// Re-derive which channels are taken by fixtures in the project, e.g. after
+    /// fixtures have been added, removed or re-addressed. Pixel maps overlapping
+    /// any other channels are reported as conflicts (see Project::validate for
+    /// overlaps between fixtures). This also decides which universes (other than the default)
+    /// need to be output.
+    pub fn update_channels_assigned(&mut self) {
+        let empty_universe: Vec<bool> = [false].repeat(self.universe_size as usize);
+        let mut assigned_by_universe: BTreeMap<u16, Vec<bool>> = BTreeMap::new();
+        assigned_by_universe.insert(DEFAULT_UNIVERSE, empty_universe.clone());
+        for fixture in self.project.fixtures.iter() {
+            if fixture.config.modes.get(fixture.mode_index).is_none() {
+                warn!(
+                    "Fixture \"{}\" has no valid mode with index {}; no channels assigned",
+                    &fixture.label, fixture.mode_index
+                );
+                continue;
+            }
+            if fixture.universe > MAX_UNIVERSE {
+                continue; // reported by Project::validate; never output
+            }
+            let channels_assigned = assigned_by_universe
+                .entry(fixture.universe)
+                .or_insert_with(|| empty_universe.clone());
+            let current_mode = &fixture.config.active_mode;
+            for m in &current_mode.mappings {
+                let channel_index = fixture.channel_index(m.channel);
+                match channels_assigned.get_mut(channel_index) {
+                    // Overlaps between fixtures are reported by Project::validate
+                    Some(assigned) => *assigned = true,
+                    None => error!(
+                        "Channel #{} for fixture \"{}\" is beyond the universe size {}; it will not be output",
+                        channel_index.wrapping_add(1),
+                        &fixture.label,
+                        self.universe_size
+                    ),
                 }
-                RemoteControlMessage::MacroAnimation(animation_msg) => {
-                    self.handle_macro_message(animation_msg);
+            }
+        }
+        for map in self
+            .project
+            .pixel_maps
+            .iter()
+            .filter(|m| m.universe <= MAX_UNIVERSE)
+        {
+            let channels_assigned = assigned_by_universe
+                .entry(map.universe)
+                .or_insert_with(|| empty_universe.clone());
+            for channel_index in map.channel_indexes() {
+                match channels_assigned.get_mut(channel_index) {
+                    Some(assigned) => {
+                        if *assigned {
+                            warn!(
+                                "Channel #{} (universe {}) for pixel map \"{}\" conflicts with another fixture",
+                                channel_index + 1,
+                                map.universe,
+                                &map.label
+                            );
+                        }
+                        *assigned = true;
+                    }
+                    None => {
+                        warn!(
+                            "Pixel map \"{}\" extends beyond the universe size {}",
+                            &map.label, self.universe_size
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Keep the state of universes still in use; start any new ones at zero
+        let universe_size = self.universe_size as usize;
+        let mut extra_universes = BTreeMap::new();
+        for universe in assigned_by_universe
+            .keys()
+            .filter(|u| **u != DEFAULT_UNIVERSE)
+        {
+            let state = self.extra_universes.remove(universe).unwrap_or_else(|| {
+                info!("Universe {} will be output", universe);
+                [0].repeat(universe_size)
+            });
+            extra_universes.insert(*universe, state);
+        }
+        self.extra_universes = extra_universes;
+        self.channels_assigned = assigned_by_universe
+            .remove(&DEFAULT_UNIVERSE)
+            .unwrap_or(empty_universe);
+    }
+
+    /// Handle any pending messages, apply animations and send ArtNet output
+    /// (if due). Returns true if state changed in a way that should be shown
+    /// in the GUI straight away.
+    pub fn update(&mut self) -> bool {
+        let mut state_changed = false;
+
+        while let Ok(m) = self.tether_interface.message_rx.try_recv() {
+            state_changed = true;
+            self.handle_remote_message(m);
+        }
+
+        while let Ok(is_connected) = self.tether_interface.connection_rx.try_recv() {
+            state_changed = true;
+            self.tether_status = if is_connected {
+                info!("Tether status: connected");
+                TetherStatus::Connected
+            } else {
+                warn!("Tether status: reconnecting");
+                TetherStatus::Reconnecting
+            };
+        }
+
+        if self
+            .cue_playback
+            .as_ref()
+            .and_then(|p| p.next_at)
+            .is_some_and(|t| Instant::now() >= t)
+        {
+            state_changed = true;
+            self.next_cue();
+        }
+
+        if let Some(startup_fade) = &mut self.startup_fade {
+            if startup_fade.apply(&mut self.channels_state) {
+                debug!("Startup fade done");
+                self.startup_fade = None;
+            }
+        }
+
+        if self
+            .auto_tick_rx
+            .as_ref()
+            .is_some_and(|rx| rx.try_recv().is_ok())
+        {
+            if self.settings.auto_random {
+                self.random_all();
+            } else if self.settings.auto_zero {
+                self.zero_all();
+            }
+            state_changed = true;
+        }
+
+        if let Some(merge_input_rx) = &self.merge_input_rx {
+            // Only the latest input matters
+            if let Some(data) = merge_input_rx.try_iter().last() {
+                if let Some(artnet) = &mut self.artnet {
+                    artnet.set_merge_input(data, self.settings.artnet_merge_input);
                 }
-                RemoteControlMessage::SceneAnimation(scene_msg) => {
-                    self.handle_scene_message(scene_msg);
+            }
+        }
+
+        if let Some(discovery_rx) = &self.discovery_rx {
+            if let Ok(result) = discovery_rx.try_recv() {
+                state_changed = true;
+                self.discovery_rx = None;
+                match result {
+                    Ok(nodes) => {
+                        info!("ArtNet discovery found {} node(s)", nodes.len());
+                        self.discovered_nodes = nodes;
+                    }
+                    Err(e) => error!("ArtNet discovery failed: {}", e),
                 }
             }
         }
 
-        if self.settings.auto_random {
-            random(&mut self.channels_state);
-        } else if self.settings.auto_zero {
-            zero(&mut self.channels_state);
+        // Animations only need to step once per frame actually sent
+        if self.apply_macros && self.is_frame_due() {
+            self.animate_macros();
         }
+
         if let Some(artnet) = &mut self.artnet {
+            artnet.set_blackout(self.blackout);
+            artnet.set_grand_master(self.grand_master);
             if artnet.update(
                 &self.channels_state,
+                &self.extra_universes,
                 &self.project.fixtures,
+                &self.project.pixel_maps,
                 self.apply_macros,
+                &self.channel_overrides,
             ) {
                 trace!("Artnet did update");
-                work_done = true;
+                if let Some(status_publisher) = &mut self.status_publisher {
+                    status_publisher.count_frame();
+                }
+            }
+        }
+
+        // Only published once connected, so the Agent ID is known
+        if let (Some(status_publisher), Some(agent_id)) =
+            (&mut self.status_publisher, self.tether_interface.agent_id())
+        {
+            if let Some(status) =
+                status_publisher.poll(agent_id, self.last_scene.as_deref(), self.artnet.as_ref())
+            {
+                trace!("Publish status: {:?}", status);
+                self.tether_interface
+                    .publish(TetherOutgoingMessage::Status(status));
+            }
+        }
+
+        if let Some(macro_feedback) = &mut self.macro_feedback {
+            if let Some(values) = macro_feedback.poll(&self.project.fixtures) {
+                trace!("Publish {} changed macro values", values.len());
+                self.tether_interface
+                    .publish(TetherOutgoingMessage::MacroValues(values));
             }
         }
 
         if self.apply_macros {
-            work_done = true;
-            self.animate_macros();
             if let Some(artnet) = &self.artnet {
                 self.channels_state = artnet.get_state().to_vec();
+                self.extra_universes = artnet.get_extra_states();
             }
         }
 
-        if self.settings.auto_random || self.settings.auto_zero {
-            std::thread::sleep(Duration::from_secs(1));
+        if self.settings.auto_save_secs > 0
+            && self.last_auto_save.elapsed() >= Duration::from_secs(self.settings.auto_save_secs)
+        {
+            self.last_auto_save = Instant::now();
+            self.auto_save_project();
         }
-        if !work_done {
-            std::thread::sleep(Duration::from_millis(1));
+
+        if self.settings.restore_state {
+            let state = self.current_state();
+            if self.saved_state.as_ref() != Some(&state) {
+                if let Err(e) = state.save(STATE_FILE_PATH) {
+                    error!("Failed to save state: {}", e);
+                }
+                self.saved_state = Some(state);
+            }
+        }
+
+        state_changed
+    }
+
+    /// Whether the next frame is due to be sent (always true if there is no
+    /// ArtNet interface, so that animations still run)
+    fn is_frame_due(&self) -> bool {
+        match &self.artnet {
+            Some(artnet) => artnet.time_until_next_send().is_zero(),
+            None => true,
+        }
+    }
+
+    /// How long until the next ArtNet frame is due
+    pub fn time_until_next_frame(&self) -> Duration {
+        match &self.artnet {
+            Some(artnet) => artnet.time_until_next_send(),
+            None => Duration::from_secs_f32(1.0 / self.settings.artnet_update_frequency as f32),
+        }
+    }
+
+    /// For headless mode: block until either a remote message arrives (which is
+    /// handled immediately) or the next ArtNet frame is due, instead of spinning
+    pub fn wait_for_next_update(&mut self) {
+        let timeout = self.time_until_next_frame();
+        match self.tether_interface.message_rx.recv_timeout(timeout) {
+            Ok(m) => self.handle_remote_message(m),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                error!("Remote message channel disconnected");
+                std::thread::sleep(timeout);
+            }
+        }
+    }
+
+    fn handle_remote_message(&mut self, m: RemoteControlMessage) {
+        if let RemoteControlMessage::ProjectStateRequest = m {
+            // Read-only, so unlike all other messages this does not
+            // (re)activate macros
+            debug!("Remote message requests Project state");
+            self.tether_interface
+                .publish(TetherOutgoingMessage::ProjectState(self.project_state()));
+            return;
+        }
+        if let RemoteControlMessage::Blackout(blackout) = m {
+            // Likewise leaves macros exactly as they are, to be restored
+            info!("Remote message sets blackout: {}", blackout);
+            self.blackout = blackout;
+            return;
+        }
+        if let RemoteControlMessage::GrandMaster(grand_master) = m {
+            debug!("Remote message sets grand master: {}", grand_master);
+            self.grand_master = grand_master;
+            return;
+        }
+        self.apply_macros = true;
+        match m {
+            RemoteControlMessage::Midi(midi_msg) => {
+                self.handle_midi_message(midi_msg);
+            }
+            RemoteControlMessage::MacroAnimation(animation_msg) => {
+                self.handle_macro_message(animation_msg);
+            }
+            RemoteControlMessage::MacroBatch(batch_msg) => {
+                self.handle_macro_batch_message(batch_msg);
+            }
+            RemoteControlMessage::SceneAnimation(scene_msg) => {
+                self.handle_scene_message(scene_msg);
+            }
+            RemoteControlMessage::CueList(cue_list_msg) => {
+                self.handle_cue_list_message(cue_list_msg);
+            }
+            RemoteControlMessage::StopAnimation(stop_msg) => {
+                if self.stop_animations(&stop_msg.fixture_labels, &stop_msg.macro_label) == 0 {
+                    warn!(
+                        "Stop Animation: no macro \"{}\" matched",
+                        &stop_msg.macro_label
+                    );
+                }
+            }
+            RemoteControlMessage::ReleaseScene(release_msg) => {
+                let ms = self.resolve_duration(release_msg.ms, release_msg.beats);
+                match self
+                    .project
+                    .scenes
+                    .iter()
+                    .position(|s| s.label.eq_ignore_ascii_case(&release_msg.scene_label))
+                {
+                    Some(index) => self.release_scene(index, ms),
+                    None => error!(
+                        "Failed to find matching scene to release for \"{}\"",
+                        &release_msg.scene_label
+                    ),
+                }
+            }
+            RemoteControlMessage::SetApplyMacros(should_apply) => {
+                info!(
+                    "Remote message sets apply macros: {}",
+                    if should_apply { "active" } else { "frozen" }
+                );
+                self.apply_macros = should_apply;
+            }
+            RemoteControlMessage::SetChannel(RemoteChannelMessage { channel, value }) => {
+                if let Err(e) = self.set_channel_override(channel, value) {
+                    error!("Remote Channel Override message failed: {}", e);
+                }
+            }
+            RemoteControlMessage::Reset => {
+                info!("Remote message requests reset all");
+                self.reset_all();
+            }
+            RemoteControlMessage::Preset(RemotePresetMessage {
+                fixture_label,
+                preset_label,
+            }) => {
+                if let Err(e) = self.apply_preset(&fixture_label, &preset_label) {
+                    error!("Remote Preset message failed: {}", e);
+                }
+            }
+            RemoteControlMessage::FixtureMode(RemoteFixtureModeMessage {
+                fixture_label,
+                mode_name,
+            }) => {
+                if let Err(e) = self.set_fixture_mode_by_name(&fixture_label, &mode_name) {
+                    error!("Remote Fixture Mode message failed: {}", e);
+                }
+            }
+            RemoteControlMessage::MasterColour(colour) => {
+                info!("Remote message sets all colour to {:?}", colour);
+                self.set_all_colour(colour);
+            }
+            RemoteControlMessage::ProjectStateRequest
+            | RemoteControlMessage::Blackout(_)
+            | RemoteControlMessage::GrandMaster(_) => {} // handled above
+            RemoteControlMessage::LoadProject(path) => {
+                if let Err(e) = self.load_project(&path) {
+                    error!("Remote Load Project message failed for \"{}\": {}", path, e);
+                }
+            }
+            RemoteControlMessage::SetTempo(bpm) => {
+                info!("Remote message sets tempo {} BPM", bpm);
+                self.current_bpm = if bpm > 0. { Some(bpm) } else { None };
+            }
+            RemoteControlMessage::Channel(channel_msg) => {
+                // Same as moving a channel slider: macros would otherwise
+                // overwrite the assigned channels on the next frame
+                self.apply_macros = false;
+                if let Err(e) = self.set_channels_direct(channel_msg) {
+                    error!("Remote Channel Control message failed: {}", e);
+                }
+            }
         }
     }
 
@@ -224,14 +876,27 @@ impl Model {
                     FixtureMacro::Control(control_macro) => {
                         if let Some(animation) = &mut control_macro.animation {
                             let (value, is_done) = animation.get_value_and_done();
-                            let dmx_value = (value * 255.0) as u8;
-                            control_macro.current_value = dmx_value;
+                            if control_macro.is_16bit {
+                                control_macro.set_value_16bit((value * 65535.0) as u16);
+                            } else {
+                                control_macro.current_value = (value * 255.0) as u8;
+                            }
 
                             // NB: Check if done AFTER applying value
                             if is_done {
                                 debug!("Animation done; delete");
                                 control_macro.animation = None;
                             }
+                        } else if let (Some(slew), Some(rate)) =
+                            (&mut control_macro.slew, control_macro.slew_rate)
+                        {
+                            let (dmx_value, is_done) = slew.get_value_and_done(rate);
+                            control_macro.current_value = dmx_value;
+
+                            if is_done {
+                                trace!("Slew reached target; delete");
+                                control_macro.slew = None;
+                            }
                         }
                     }
                     FixtureMacro::Colour(colour_macro) => {
@@ -239,8 +904,11 @@ impl Model {
                             &mut colour_macro.animation
                         {
                             let (progress, is_done) = animation.get_value_and_done();
-                            colour_macro.current_value =
-                                animate_colour(start_colour, end_colour, progress);
+                            colour_macro.current_value = colour_macro.colour_space.interpolate(
+                                start_colour,
+                                end_colour,
+                                progress,
+                            );
 
                             // NB: Check if done AFTER applying value
                             if is_done {
@@ -249,6 +917,9 @@ impl Model {
                             }
                         }
                     }
+                    FixtureMacro::Wheel(_) => {
+                        // Wheel slots are discrete; nothing to animate
+                    }
                 }
             }
         }
@@ -256,7 +927,10 @@ impl Model {
 
     fn handle_midi_message(&mut self, m: TetherMidiMessage) {
         match m {
-            // TetherMidiMessage::Raw(_) => todo!(),
+            TetherMidiMessage::Raw(bytes) => match TetherMidiMessage::from_raw(&bytes) {
+                Some(m) => self.handle_midi_message(m),
+                None => trace!("Ignoring raw MIDI message {:?}", bytes),
+            },
             TetherMidiMessage::NoteOn(note) => {
                 let TetherNotePayload {
                     note,
@@ -264,11 +938,17 @@ impl Model {
                     velocity: _,
                 } = note;
                 let start_note = self.project.midi_config.note_start;
-                let index = note - start_note;
+                let Some(index) = note.checked_sub(start_note) else {
+                    debug!("Note {} is below note start {}; ignore", note, start_note);
+                    return;
+                };
                 debug!("Note {} => macro group index {}", note, index);
                 self.selected_macro_group_index = index as usize;
             }
-            // TetherMidiMessage::NoteOff(_) => todo!(),
+            TetherMidiMessage::NoteOff(note) => {
+                // Selecting a macro group "latches"; nothing to release
+                trace!("Note Off {}; ignore", note.note);
+            }
             TetherMidiMessage::ControlChange(cc) => {
                 let TetherControlChangePayload {
                     channel: _,
@@ -276,6 +956,45 @@ impl Model {
                     value,
                 } = cc;
 
+                if let Some((fixture_label, macro_label)) = self.midi_learn_target.take() {
+                    info!(
+                        "MIDI Learn: controller {} => {} / {}",
+                        controller, &fixture_label, &macro_label
+                    );
+                    self.project
+                        .midi_config
+                        .bind(controller, &fixture_label, &macro_label);
+                    return;
+                }
+
+                let bindings: Vec<MidiBinding> = self
+                    .project
+                    .midi_config
+                    .bindings
+                    .iter()
+                    .filter(|b| b.controller == controller)
+                    .cloned()
+                    .collect();
+                if !bindings.is_empty() {
+                    for binding in bindings {
+                        match self
+                            .project
+                            .fixtures
+                            .iter_mut()
+                            .filter(|f| f.enabled && f.label == binding.fixture_label)
+                            .flat_map(|f| f.config.active_mode.macros.iter_mut())
+                            .find(|m| m.label() == binding.macro_label)
+                        {
+                            Some(m) => apply_midi_value(m, value),
+                            None => warn!(
+                                "No macro \"{}\" on enabled fixture \"{}\" for controller {}",
+                                &binding.macro_label, &binding.fixture_label, controller
+                            ),
+                        }
+                    }
+                    return;
+                }
+
                 let controller_start = self.project.midi_config.controller_start;
 
                 if controller < controller_start {
@@ -283,7 +1002,7 @@ impl Model {
                 }
 
                 for (i, fixture) in self.project.fixtures.iter_mut().enumerate() {
-                    if self.selected_macro_group_index == i {
+                    if self.selected_macro_group_index == i && fixture.enabled {
                         let target_macro_index = controller - controller_start;
                         debug!(
                             "Controller number {} => target macro index {}",
@@ -295,23 +1014,7 @@ impl Model {
                             .macros
                             .get_mut(target_macro_index as usize)
                         {
-                            Some(m) => match m {
-                                FixtureMacro::Control(control_macro) => {
-                                    let value = value * 2;
-                                    debug!("Adjust {} to {}", &control_macro.label, value);
-                                    control_macro.current_value = value;
-                                }
-                                FixtureMacro::Colour(colour_macro) => {
-                                    let value = value * 2;
-
-                                    let [r, g, b, a] = colour_macro.current_value.to_array();
-
-                                    colour_macro.current_value =
-                                        Color32::from_rgba_premultiplied(r, g, b, value);
-
-                                    debug!("Color a {} => {}", a, colour_macro.current_value.a());
-                                }
-                            },
+                            Some(m) => apply_midi_value(m, value),
                             None => {
                                 error!("Failed to match macro control");
                             }
@@ -320,16 +1023,16 @@ impl Model {
                 }
             }
             TetherMidiMessage::Knob(TetherKnobPayload { index, position }) => {
-                for fixture in self.project.fixtures.iter_mut() {
+                for fixture in self.project.fixtures.iter_mut().filter(|f| f.enabled) {
                     for m in fixture.config.active_mode.macros.iter_mut() {
                         match m {
                             FixtureMacro::Control(control_macro) => {
                                 if index == control_macro.global_index {
-                                    control_macro.current_value = (255.0 * position) as u8;
+                                    control_macro.set_target_value((255.0 * position) as u8);
                                 }
                             }
-                            FixtureMacro::Colour(_colour_macro) => {
-                                // Ignore colour macros for now
+                            FixtureMacro::Colour(_) | FixtureMacro::Wheel(_) => {
+                                // Ignore colour and wheel macros for now
                             }
                         }
                     }
@@ -338,66 +1041,159 @@ impl Model {
         }
     }
 
-    pub fn handle_macro_message(&mut self, msg: RemoteMacroMessage) {
+    /// Apply a remote macro message to all matching fixtures; returns the number
+    /// of macros that matched.
+    ///
+    /// If an animation is already in progress on a macro, the newest animation
+    /// always replaces it, starting from the live (interpolated) value at the
+    /// moment of replacement; only one animation per macro ever remains.
+    pub fn handle_macro_message(&mut self, msg: RemoteMacroMessage) -> usize {
+        let mut matched_count = 0;
+        let ms = self.resolve_duration(msg.ms, msg.beats);
+        let colour_component = ColourComponent::split_label(&msg.macro_label);
+        let named_colour = match &msg.value {
+            RemoteMacroValue::ColourName(name) => palette_colour(&self.project.palette, name),
+            _ => None,
+        };
+        let fixture_labels = self.project.resolve_fixture_labels(&msg.fixture_labels);
         for fixture in self.project.fixtures.iter_mut() {
-            if fixtures_list_contains(&msg.fixture_labels, &fixture.label) {
-                if let Some(target_macro) =
-                    fixture
-                        .config
-                        .active_mode
-                        .macros
-                        .iter_mut()
-                        .find(|m| match m {
-                            FixtureMacro::Control(m) => {
-                                m.label.eq_ignore_ascii_case(&msg.macro_label)
-                            }
-                            FixtureMacro::Colour(m) => {
-                                m.label.eq_ignore_ascii_case(&msg.macro_label)
-                            }
-                        })
+            if fixture.enabled && fixtures_list_contains(&fixture_labels, &fixture.label) {
+                let macros = &mut fixture.config.active_mode.macros;
+                // An exact label match always wins; otherwise, the label may
+                // address a single component of a Colour Macro, e.g. "Colour.r"
+                let (target_index, component) = match macros
+                    .iter()
+                    .position(|m| m.label().eq_ignore_ascii_case(&msg.macro_label))
                 {
+                    Some(index) => (Some(index), None),
+                    None => match colour_component {
+                        Some((macro_label, component)) => (
+                            macros.iter().position(|m| {
+                                matches!(m, FixtureMacro::Colour(_))
+                                    && m.label().eq_ignore_ascii_case(macro_label)
+                            }),
+                            Some(component),
+                        ),
+                        None => (None, None),
+                    },
+                };
+                if let Some(target_macro) = target_index.and_then(|i| macros.get_mut(i)) {
+                    let delay =
+                        Duration::from_millis(msg.stagger_ms.unwrap_or(0) * matched_count as u64);
+                    matched_count += 1;
                     match target_macro {
                         FixtureMacro::Control(control_macro) => {
-                            match msg.value {
-                                RemoteMacroValue::ControlValue(target_value) => {
-                                    if let Some(ms) = msg.ms {
-                                        let duration = Duration::from_millis(ms);
-                                        let start_value =
-                                            control_macro.current_value as f32 / 255.0;
-                                        let end_value = target_value as f32 / 255.0;
-
-                                        control_macro.animation = Some(Animation::new(
+                            // Freeze any in-progress animation at its live value first
+                            control_macro.current_value = control_macro.live_value();
+                            let target_value = match msg.value {
+                                RemoteMacroValue::ControlValue(target_value) => Some(target_value),
+                                RemoteMacroValue::ControlDelta(delta) => {
+                                    Some((control_macro.current_value as i16 + delta).clamp(0, 255)
+                                        as u8)
+                                }
+                                RemoteMacroValue::ColourValue(_)
+                                | RemoteMacroValue::ColourName(_)
+                                | RemoteMacroValue::Kelvin(_)
+                                | RemoteMacroValue::Slot(_) => {
+                                    error!(
+                                        "Remote Animation Message targets Control Macro \"{}\", which expects ControlValue or ControlDelta, but provides {}",
+                                        &control_macro.label,
+                                        msg.value.kind()
+                                    );
+                                    None
+                                }
+                            };
+                            if let Some(target_value) = target_value {
+                                if let Some(ms) = ms {
+                                    let duration = Duration::from_millis(ms);
+                                    let start_value = control_macro.current_value as f32 / 255.0;
+                                    let end_value = target_value as f32 / 255.0;
+
+                                    control_macro.slew = None;
+                                    control_macro.animation = Some(
+                                        Animation::new(
                                             duration,
                                             start_value,
                                             end_value,
-                                            Box::new(SineInOut),
-                                        ));
+                                            msg.easing.unwrap_or_default().tween(),
+                                        )
+                                        .with_delay(delay)
+                                        .with_repeat(msg.repeat.unwrap_or_default()),
+                                    );
 
-                                        debug!(
-                                            "Added Control Value animation with duration {}ms, {} -> {}",
-                                            duration.as_millis(),
-                                            start_value,
-                                            end_value
+                                    debug!(
+                                        "Added Control Value animation with duration {}ms, {} -> {}",
+                                        duration.as_millis(),
+                                        start_value,
+                                        end_value
+                                    );
+                                } else {
+                                    debug!("No animation; immediately go to Control Macro value");
+                                    control_macro.animation = None; // cancel first
+                                    control_macro.set_target_value(target_value);
+                                }
+                            }
+                        }
+                        FixtureMacro::Colour(colour_macro) => {
+                            // Freeze any in-progress animation at its live value first
+                            colour_macro.current_value = colour_macro.live_value();
+                            let target_colour = if let Some(component) = component {
+                                let current = component.get(colour_macro.current_value);
+                                let target_value = match msg.value {
+                                    RemoteMacroValue::ControlValue(target_value) => {
+                                        Some(target_value)
+                                    }
+                                    RemoteMacroValue::ControlDelta(delta) => {
+                                        Some((current as i16 + delta).clamp(0, 255) as u8)
+                                    }
+                                    RemoteMacroValue::ColourValue(_)
+                                    | RemoteMacroValue::ColourName(_)
+                                    | RemoteMacroValue::Kelvin(_)
+                                    | RemoteMacroValue::Slot(_) => {
+                                        error!(
+                                            "Remote Animation Message targets a component of Colour Macro \"{}\", which expects ControlValue or ControlDelta, but provides {}",
+                                            &colour_macro.label,
+                                            msg.value.kind()
                                         );
-                                    } else {
-                                        debug!(
-                                            "No animation; immediately go to Control Macro value"
+                                        None
+                                    }
+                                };
+                                target_value.map(|v| {
+                                    colour_macro.kelvin = None;
+                                    component.set(colour_macro.current_value, v)
+                                })
+                            } else {
+                                match msg.value {
+                                    RemoteMacroValue::ControlValue(_)
+                                    | RemoteMacroValue::ControlDelta(_)
+                                    | RemoteMacroValue::Slot(_) => {
+                                        error!(
+                                            "Remote Animation Message targets Colour Macro \"{}\", which expects ColourValue, ColourName or Kelvin, but provides {}",
+                                            &colour_macro.label,
+                                            msg.value.kind()
                                         );
-                                        control_macro.animation = None; // cancel first
-                                        control_macro.current_value = target_value;
+                                        None
+                                    }
+                                    RemoteMacroValue::ColourValue(target_colour) => {
+                                        Some(target_colour)
+                                    }
+                                    RemoteMacroValue::ColourName(ref name) => {
+                                        if named_colour.is_none() {
+                                            error!("No colour \"{}\" in the palette", name);
+                                        }
+                                        named_colour
+                                    }
+                                    RemoteMacroValue::Kelvin(kelvin) => {
+                                        colour_macro.kelvin = Some(kelvin);
+                                        Some(kelvin_to_colour(
+                                            kelvin,
+                                            colour_macro.current_value.a(),
+                                        ))
                                     }
                                 }
-                                RemoteMacroValue::ColourValue(_) => {
-                                    error!("Remote Animation Message targets Control Macro, but provides Colour Value instead");
-                                }
-                            }
-                        }
-                        FixtureMacro::Colour(colour_macro) => match msg.value {
-                            RemoteMacroValue::ControlValue(_) => {
-                                error!("Remote Animation Message targets Colour Macro, but provices Control Value instead");
-                            }
-                            RemoteMacroValue::ColourValue(target_colour) => {
-                                if let Some(ms) = msg.ms {
+                            };
+                            if let Some(target_colour) = target_colour {
+                                if let Some(ms) = ms {
                                     let duration = Duration::from_millis(ms);
                                     let start_value = 0.;
                                     let end_value = 1.0;
@@ -406,8 +1202,10 @@ impl Model {
                                         duration,
                                         start_value,
                                         end_value,
-                                        Box::new(SineInOut),
-                                    );
+                                        msg.easing.unwrap_or_default().tween(),
+                                    )
+                                    .with_delay(delay)
+                                    .with_repeat(msg.repeat.unwrap_or_default());
                                     let start_colour = colour_macro.current_value;
                                     let end_colour = target_colour;
 
@@ -418,21 +1216,89 @@ impl Model {
                                         end_colour
                                     );
 
+                                    colour_macro.colour_space =
+                                        msg.colour_space.unwrap_or_default();
                                     colour_macro.animation =
                                         Some((animation, start_colour, end_colour));
                                 } else {
                                     debug!("No animation; immediately go to Colour Macro value");
+                                    colour_macro.animation = None; // cancel first
                                     colour_macro.current_value = target_colour;
                                 }
                             }
+                        }
+                        FixtureMacro::Wheel(wheel_macro) => match &msg.value {
+                            RemoteMacroValue::Slot(slot_label) => {
+                                if !wheel_macro.select_slot(slot_label) {
+                                    error!(
+                                        "Wheel Macro \"{}\" has no slot \"{}\"",
+                                        &wheel_macro.label, slot_label
+                                    );
+                                }
+                            }
+                            _ => {
+                                error!(
+                                    "Remote Animation Message targets Wheel Macro \"{}\", which expects Slot, but provides {}",
+                                    &wheel_macro.label,
+                                    msg.value.kind()
+                                );
+                            }
                         },
                     }
                 }
             }
         }
+        matched_count
+    }
+
+    /// Apply several macro values to a single fixture, all within the same
+    /// update, so that they are output together in the same frame
+    pub fn handle_macro_batch_message(&mut self, msg: RemoteMacroBatchMessage) {
+        match self
+            .project
+            .fixtures
+            .iter()
+            .find(|f| f.label.eq_ignore_ascii_case(&msg.fixture_label))
+        {
+            None => {
+                warn!(
+                    "Macro batch targets unknown fixture \"{}\"; ignored",
+                    &msg.fixture_label
+                );
+                return;
+            }
+            Some(fixture) if !fixture.enabled => {
+                debug!(
+                    "Macro batch targets disabled fixture \"{}\"; ignored",
+                    &msg.fixture_label
+                );
+                return;
+            }
+            Some(_) => {}
+        }
+        for (macro_label, value) in msg.values {
+            let matched_count = self.handle_macro_message(RemoteMacroMessage {
+                fixture_labels: Some(vec![String::from(&msg.fixture_label)]),
+                macro_label: String::from(&macro_label),
+                value,
+                ms: msg.ms,
+                beats: None,
+                colour_space: None,
+                stagger_ms: None,
+                repeat: None,
+                easing: None,
+            });
+            if matched_count == 0 {
+                warn!(
+                    "Macro batch: fixture \"{}\" has no macro \"{}\"; skipped",
+                    &msg.fixture_label, &macro_label
+                );
+            }
+        }
     }
 
     pub fn handle_scene_message(&mut self, msg: RemoteSceneMessage) {
+        let ms = self.resolve_duration(msg.ms, msg.beats);
         match self
             .project
             .scenes
@@ -443,37 +1309,242 @@ impl Model {
             Some((index, scene)) => {
                 debug!("Found scene \"{}\" at index {}", &scene.label, index);
                 scene.last_active = true;
-                self.apply_scene(index, msg.ms, msg.fixture_labels);
+                self.apply_scene(index, ms, msg.fixture_labels, msg.stagger_ms);
             }
             None => error!("Failed to find matching scene for \"{}\"", &msg.scene_label),
         }
     }
 
-    pub fn apply_scene(
+    /// Stop any animation on the matching macros, holding each at its live
+    /// value; returns the number of macros that matched.
+    pub fn stop_animations(
         &mut self,
-        scene_index: usize,
-        animation_ms: Option<u64>,
-        fixture_filters: Option<Vec<String>>,
-    ) {
-        match self.project.scenes.get(scene_index) {
-            Some(scene) => {
-                debug!("Match scene {}", &scene.label);
-                for fixture in self.project.fixtures.iter_mut() {
-                    for (fixture_label_in_scene, fixture_state_in_scene) in scene.state.iter() {
-                        // If there are fixtureFilters applied, check for matches against this list
-                        // as well as the name vs the key in the Scene. If no filters, just check
-                        // the name.
-                        let is_target_fixture = if let Some(filters) = &fixture_filters {
-                            filters.contains(fixture_label_in_scene)
-                                && fixture_label_in_scene.eq_ignore_ascii_case(&fixture.label)
-                        } else {
-                            fixture_label_in_scene.eq_ignore_ascii_case(&fixture.label)
-                        };
-                        if is_target_fixture {
-                            debug!(
-                                "Scene has match for fixture {} == {}",
+        fixture_labels: &Option<Vec<String>>,
+        macro_label: &str,
+    ) -> usize {
+        let mut matched_count = 0;
+        let fixture_labels = self.project.resolve_fixture_labels(fixture_labels);
+        for fixture in self
+            .project
+            .fixtures
+            .iter_mut()
+            .filter(|f| fixtures_list_contains(&fixture_labels, &f.label))
+        {
+            for m in fixture.config.active_mode.macros.iter_mut() {
+                if !m.label().eq_ignore_ascii_case(macro_label) {
+                    continue;
+                }
+                matched_count += 1;
+                match m {
+                    FixtureMacro::Control(control_macro) => {
+                        control_macro.current_value = control_macro.live_value();
+                        control_macro.animation = None;
+                    }
+                    FixtureMacro::Colour(colour_macro) => {
+                        colour_macro.current_value = colour_macro.live_value();
+                        colour_macro.animation = None;
+                    }
+                    FixtureMacro::Wheel(_) => {} // never animated
+                }
+            }
+        }
+        matched_count
+    }
+
+    pub fn handle_cue_list_message(&mut self, msg: RemoteCueListMessage) {
+        let Some(cue_list_index) = self
+            .project
+            .cue_lists
+            .iter()
+            .position(|c| c.label.eq_ignore_ascii_case(&msg.cue_list_label))
+        else {
+            error!(
+                "Failed to find matching Cue List for \"{}\"",
+                &msg.cue_list_label
+            );
+            return;
+        };
+        if msg.command != CueListCommand::Start
+            && self.cue_playback.as_ref().map(|p| p.cue_list_index) != Some(cue_list_index)
+        {
+            warn!(
+                "Cue List \"{}\" is not playing; ignore {:?}",
+                &msg.cue_list_label, msg.command
+            );
+            return;
+        }
+        match msg.command {
+            CueListCommand::Start => self.start_cue_list(cue_list_index),
+            CueListCommand::Stop => self.stop_cue_list(),
+            CueListCommand::Next => self.next_cue(),
+            CueListCommand::Previous => self.previous_cue(),
+        }
+    }
+
+    /// Start playing the Cue List from its first cue
+    pub fn start_cue_list(&mut self, cue_list_index: usize) {
+        match self.project.cue_lists.get(cue_list_index) {
+            Some(cue_list) if !cue_list.cues.is_empty() => self.go_to_cue(cue_list_index, 0),
+            Some(cue_list) => warn!("Cue List \"{}\" has no cues", &cue_list.label),
+            None => error!("Failed to find Cue List with index {}", cue_list_index),
+        }
+    }
+
+    /// Stop advancing through cues; whatever is playing stays as it is
+    pub fn stop_cue_list(&mut self) {
+        if self.cue_playback.take().is_some() {
+            info!("Cue List stopped");
+        }
+    }
+
+    /// Apply the given cue's Scene with its fade time, and schedule the next
+    /// cue (unless the cue waits for a manual GO)
+    pub fn go_to_cue(&mut self, cue_list_index: usize, cue_index: usize) {
+        let Some((cue_list_label, cue)) =
+            self.project.cue_lists.get(cue_list_index).and_then(|c| {
+                c.cues
+                    .get(cue_index)
+                    .map(|cue| (c.label.clone(), cue.clone()))
+            })
+        else {
+            error!(
+                "Failed to find cue {} in Cue List with index {}",
+                cue_index, cue_list_index
+            );
+            return;
+        };
+        info!(
+            "Cue List \"{}\" cue #{}: Scene \"{}\"",
+            &cue_list_label,
+            cue_index + 1,
+            &cue.scene_label
+        );
+        match self
+            .project
+            .scenes
+            .iter()
+            .position(|s| s.label.eq_ignore_ascii_case(&cue.scene_label))
+        {
+            Some(scene_index) => {
+                for (index, scene) in self.project.scenes.iter_mut().enumerate() {
+                    scene.last_active = index == scene_index;
+                }
+                self.apply_macros = true;
+                let fade_ms = if cue.fade_ms > 0 {
+                    Some(cue.fade_ms)
+                } else {
+                    None
+                };
+                self.apply_scene(scene_index, fade_ms, None, None);
+            }
+            None => error!(
+                "Cue refers to missing Scene \"{}\"; skipped",
+                &cue.scene_label
+            ),
+        }
+        self.cue_playback = Some(CuePlayback {
+            cue_list_index,
+            cue_index,
+            next_at: if cue.hold_ms > 0 {
+                Some(Instant::now() + Duration::from_millis(cue.fade_ms + cue.hold_ms))
+            } else {
+                None
+            },
+        });
+    }
+
+    /// Go to the next cue in the playing Cue List; after the last cue, either
+    /// start again (if looping) or stop
+    pub fn next_cue(&mut self) {
+        let Some(playback) = &self.cue_playback else {
+            return;
+        };
+        let (cue_list_index, next_index) = (playback.cue_list_index, playback.cue_index + 1);
+        match self.project.cue_lists.get(cue_list_index) {
+            Some(cue_list) if next_index < cue_list.cues.len() => {
+                self.go_to_cue(cue_list_index, next_index)
+            }
+            Some(cue_list) if cue_list.looping && !cue_list.cues.is_empty() => {
+                self.go_to_cue(cue_list_index, 0)
+            }
+            Some(cue_list) => {
+                info!("Cue List \"{}\" finished", &cue_list.label);
+                self.cue_playback = None;
+            }
+            None => self.cue_playback = None,
+        }
+    }
+
+    /// Go back to the previous cue (or restart the first) in the playing Cue List
+    pub fn previous_cue(&mut self) {
+        if let Some(playback) = &self.cue_playback {
+            let (cue_list_index, cue_index) = (playback.cue_list_index, playback.cue_index);
+            self.go_to_cue(cue_list_index, cue_index.saturating_sub(1));
+        }
+    }
+
+    /// Animation duration in ms for a message which may give a duration in
+    /// beats as well as (or instead of) ms. Beats are converted using the
+    /// current tempo; without one, fall back to ms.
+    fn resolve_duration(&self, ms: Option<u64>, beats: Option<f32>) -> Option<u64> {
+        if let Some(beats) = beats {
+            match DurationSpec::Beats(beats).to_ms(self.current_bpm) {
+                Some(beats_ms) => return Some(beats_ms),
+                None => warn!(
+                    "Duration of {} beats requested, but no tempo is set; using ms instead",
+                    beats
+                ),
+            }
+        }
+        ms.and_then(|ms| DurationSpec::Ms(ms).to_ms(self.current_bpm))
+    }
+
+    /// Apply the given Scene, optionally animating over `animation_ms`. If
+    /// `stagger_ms` is also specified, each successive targeted fixture holds
+    /// for that much longer before its animation begins.
+    ///
+    /// Fixtures are always visited in order of their labels (ignoring case),
+    /// regardless of their order in the Project, so that staggering (and
+    /// logging) is reproducible.
+    pub fn apply_scene(
+        &mut self,
+        scene_index: usize,
+        animation_ms: Option<u64>,
+        fixture_filters: Option<Vec<String>>,
+        stagger_ms: Option<u64>,
+    ) {
+        let fixture_filters = self.project.resolve_fixture_labels(&fixture_filters);
+        match self.project.scenes.get(scene_index) {
+            Some(scene) => {
+                debug!("Match scene {}", &scene.label);
+                self.last_scene = Some(String::from(&scene.label));
+                let mut target_index = 0;
+                let mut fixture_order: Vec<usize> = (0..self.project.fixtures.len()).collect();
+                fixture_order.sort_by_key(|i| self.project.fixtures[*i].label.to_lowercase());
+                for fixture_index in fixture_order {
+                    let fixture = &mut self.project.fixtures[fixture_index];
+                    for (fixture_label_in_scene, fixture_state_in_scene) in scene.state.iter() {
+                        // If there are fixtureFilters applied, check for matches against this list
+                        // as well as the name vs the key in the Scene. If no filters, just check
+                        // the name.
+                        let is_target_fixture =
+                            fixtures_list_contains(&fixture_filters, fixture_label_in_scene)
+                                && fixture_label_in_scene.eq_ignore_ascii_case(&fixture.label);
+                        // Fixtures excluded by the Scene itself (or disabled) are never affected
+                        let is_target_fixture = is_target_fixture
+                            && fixture.enabled
+                            && !scene
+                                .excluded_fixtures
+                                .iter()
+                                .any(|x| x.eq_ignore_ascii_case(&fixture.label));
+                        if is_target_fixture {
+                            debug!(
+                                "Scene has match for fixture {} == {}",
                                 &fixture.label, fixture_label_in_scene
                             );
+                            let delay =
+                                Duration::from_millis(stagger_ms.unwrap_or(0) * target_index);
+                            target_index += 1;
                             for m in fixture.config.active_mode.macros.iter_mut() {
                                 match m {
                                     FixtureMacro::Control(control_macro_in_fixture) => {
@@ -491,25 +1562,31 @@ impl Model {
                                                     );
                                                     if let Some(ms) = animation_ms {
                                                         debug!("Scene includes animation; animate Control Value over {}ms", ms);
-                                                        control_macro_in_fixture.animation =
-                                                            Some(Animation::new(
+                                                        // Crossfade from wherever any previous animation
+                                                        // has got to, not where it was last frame
+                                                        let start_value =
+                                                            control_macro_in_fixture.live_value();
+                                                        control_macro_in_fixture.slew = None;
+                                                        control_macro_in_fixture.animation = Some(
+                                                            Animation::new(
                                                                 Duration::from_millis(ms),
-                                                                control_macro_in_fixture
-                                                                    .current_value
-                                                                    as f32
-                                                                    / 255.0,
+                                                                start_value as f32 / 255.0,
                                                                 *control_macro_in_scene as f32
                                                                     / 255.0,
-                                                                Box::new(SineInOut),
-                                                            ))
+                                                                scene.easing.tween(),
+                                                            )
+                                                            .with_delay(delay),
+                                                        )
                                                     } else {
                                                         debug!("No Animation specified; change Control Value immediately");
-                                                        control_macro_in_fixture.current_value =
-                                                            *control_macro_in_scene;
+                                                        control_macro_in_fixture.set_target_value(
+                                                            *control_macro_in_scene,
+                                                        );
                                                     }
                                                 }
-                                                SceneValue::ColourValue(_) => {
-                                                    debug!("This is Colour Macro for fixture; Control Macro from scene will not apply");
+                                                SceneValue::ColourValue(_)
+                                                | SceneValue::Slot(_) => {
+                                                    debug!("This is not a Control Macro for fixture; Control Macro from scene will not apply");
                                                 }
                                             }
                                         }
@@ -519,8 +1596,9 @@ impl Model {
                                             .get(&colour_macro_in_fixture.label)
                                         {
                                             match macro_in_scene {
-                                                SceneValue::ControlValue(_) => {
-                                                    debug!("This is Control Macro for fixture; Colour Macro from scene will not apply");
+                                                SceneValue::ControlValue(_)
+                                                | SceneValue::Slot(_) => {
+                                                    debug!("This is not a Colour Macro for fixture; Colour Macro from scene will not apply");
                                                 }
                                                 SceneValue::ColourValue(colour_macro_in_scene) => {
                                                     debug!(
@@ -534,11 +1612,14 @@ impl Model {
                                                             Duration::from_millis(ms),
                                                             0.0,
                                                             1.0,
-                                                            Box::new(SineInOut),
-                                                        );
+                                                            scene.easing.tween(),
+                                                        )
+                                                        .with_delay(delay);
                                                         let start_colour =
-                                                            colour_macro_in_fixture.current_value;
+                                                            colour_macro_in_fixture.live_value();
                                                         let end_colour = *colour_macro_in_scene;
+                                                        colour_macro_in_fixture.colour_space =
+                                                            ColourSpace::Rgb;
                                                         colour_macro_in_fixture.animation = Some((
                                                             animation,
                                                             start_colour,
@@ -553,6 +1634,33 @@ impl Model {
                                             }
                                         }
                                     }
+                                    FixtureMacro::Wheel(wheel_macro_in_fixture) => {
+                                        if let Some(macro_in_scene) = fixture_state_in_scene
+                                            .get(&wheel_macro_in_fixture.label)
+                                        {
+                                            match macro_in_scene {
+                                                SceneValue::Slot(slot_label) => {
+                                                    debug!(
+                                                        "With fixture {}, Scene sets wheel macro {} to {}",
+                                                        &fixture.label,
+                                                        &wheel_macro_in_fixture.label, slot_label
+                                                    );
+                                                    if !wheel_macro_in_fixture
+                                                        .select_slot(slot_label)
+                                                    {
+                                                        warn!(
+                                                            "Wheel Macro {} has no slot {}",
+                                                            &wheel_macro_in_fixture.label,
+                                                            slot_label
+                                                        );
+                                                    }
+                                                }
+                                                _ => {
+                                                    debug!("This is not a Wheel Macro for fixture; Wheel Macro from scene will not apply");
+                                                }
+                                            }
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -566,23 +1674,705 @@ impl Model {
         }
     }
 
+    /// The opposite of `apply_scene`: every macro set by the Scene goes back
+    /// to its home value (see `FixtureInstance::home_state`), animated over
+    /// the given duration with the Scene's easing, or immediately if none.
+    /// Fixtures and macros not in the Scene are left alone.
+    pub fn release_scene(&mut self, scene_index: usize, animation_ms: Option<u64>) {
+        let scene = match self.project.scenes.get_mut(scene_index) {
+            Some(scene) => scene,
+            None => {
+                error!("Failed to find scene with index {}", scene_index);
+                return;
+            }
+        };
+        debug!("Release scene {}", &scene.label);
+        scene.last_active = false;
+        if self.last_scene.as_deref() == Some(&scene.label) {
+            self.last_scene = None;
+        }
+
+        for fixture in self.project.fixtures.iter_mut().filter(|f| {
+            f.enabled
+                && !scene
+                    .excluded_fixtures
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(&f.label))
+        }) {
+            let fixture_state_in_scene = match scene
+                .state
+                .iter()
+                .find(|(label, _)| label.eq_ignore_ascii_case(&fixture.label))
+            {
+                Some((_, state)) => state,
+                None => continue,
+            };
+            let home_state = fixture.home_state();
+            for m in fixture.config.active_mode.macros.iter_mut() {
+                if !fixture_state_in_scene.contains_key(m.label()) {
+                    continue;
+                }
+                let home_value = home_state.get(m.label());
+                match (m, home_value) {
+                    (
+                        FixtureMacro::Control(control_macro),
+                        Some(SceneValue::ControlValue(home)),
+                    ) => {
+                        if let Some(ms) = animation_ms {
+                            control_macro.slew = None;
+                            control_macro.animation = Some(Animation::new(
+                                Duration::from_millis(ms),
+                                control_macro.live_value() as f32 / 255.0,
+                                *home as f32 / 255.0,
+                                scene.easing.tween(),
+                            ));
+                        } else {
+                            control_macro.animation = None;
+                            control_macro.set_target_value(*home);
+                        }
+                    }
+                    (FixtureMacro::Colour(colour_macro), Some(SceneValue::ColourValue(home))) => {
+                        colour_macro.kelvin = None;
+                        if let Some(ms) = animation_ms {
+                            let start_colour = colour_macro.live_value();
+                            colour_macro.colour_space = ColourSpace::Rgb;
+                            colour_macro.animation = Some((
+                                Animation::new(
+                                    Duration::from_millis(ms),
+                                    0.0,
+                                    1.0,
+                                    scene.easing.tween(),
+                                ),
+                                start_colour,
+                                *home,
+                            ));
+                        } else {
+                            colour_macro.animation = None;
+                            colour_macro.current_value = *home;
+                        }
+                    }
+                    (FixtureMacro::Wheel(wheel_macro), Some(SceneValue::Slot(_))) => {
+                        // Wheels cannot fade; go home straight away
+                        wheel_macro.current_value = 0;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.apply_macros = true;
+    }
+
+    /// Look for ArtNet nodes in the background; the results end up in
+    /// `discovered_nodes`. Does nothing if a discovery is already running.
+    pub fn start_discovery(&mut self) {
+        if self.discovery_rx.is_some() {
+            return;
+        }
+        let timeout = Duration::from_millis(self.settings.artnet_discovery_ms);
+        let (tx, rx) = mpsc::channel();
+        self.discovery_rx = Some(rx);
+        self.handles.push(std::thread::spawn(move || {
+            tx.send(discover(timeout)).ok();
+        }));
+    }
+
+    /// Apply a fixture's named preset; all values are set immediately
+    pub fn apply_preset(&mut self, fixture_label: &str, preset_label: &str) -> anyhow::Result<()> {
+        let fixture = self
+            .project
+            .fixtures
+            .iter_mut()
+            .find(|f| f.label.eq_ignore_ascii_case(fixture_label))
+            .ok_or(anyhow!("No fixture \"{}\"", fixture_label))?;
+        if !fixture.enabled {
+            return Err(anyhow!("Fixture \"{}\" is disabled", fixture_label));
+        }
+        let preset = fixture
+            .presets
+            .iter()
+            .find(|(label, _)| label.eq_ignore_ascii_case(preset_label))
+            .map(|(_, state)| state.clone())
+            .ok_or(anyhow!(
+                "Fixture \"{}\" has no preset \"{}\"",
+                fixture_label,
+                preset_label
+            ))?;
+
+        debug!(
+            "Apply preset \"{}\" to fixture \"{}\"",
+            preset_label, &fixture.label
+        );
+        for m in fixture.config.active_mode.macros.iter_mut() {
+            let value_in_preset = preset.get(m.label());
+            match (m, value_in_preset) {
+                (FixtureMacro::Control(control_macro), Some(SceneValue::ControlValue(value))) => {
+                    control_macro.animation = None;
+                    control_macro.set_target_value(*value);
+                }
+                (FixtureMacro::Colour(colour_macro), Some(SceneValue::ColourValue(value))) => {
+                    colour_macro.animation = None;
+                    colour_macro.kelvin = None;
+                    colour_macro.current_value = *value;
+                }
+                (FixtureMacro::Wheel(wheel_macro), Some(SceneValue::Slot(slot_label))) => {
+                    if !wheel_macro.select_slot(slot_label) {
+                        warn!(
+                            "Wheel Macro {} has no slot {}",
+                            &wheel_macro.label, slot_label
+                        );
+                    }
+                }
+                (m, Some(value)) => {
+                    warn!(
+                        "Preset value {:?} does not suit macro {}; ignored",
+                        value,
+                        m.label()
+                    );
+                }
+                (_, None) => {}
+            }
+        }
+        self.apply_macros = true;
+        Ok(())
+    }
+
+    /// Switch to another Project file at runtime. If it cannot be loaded, the
+    /// current Project is kept. Otherwise the ArtNet interface is set up
+    /// again (the Project may have its own settings) and all channels go to
+    /// the new fixtures' home values.
+    pub fn load_project(&mut self, path: &str) -> anyhow::Result<()> {
+        let project = Project::load(path)?;
+        info!("Switch to Project \"{}\"", path);
+        self.last_saved_project = Project::to_json(&project).ok();
+        self.project = project;
+        self.current_project_path = Some(String::from(path));
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.undo_checkpoint = None;
+        self.selected_macro_group_index = 0;
+        self.last_scene = None;
+        self.cue_playback = None;
+        self.startup_fade = None;
+        self.clear_macro_feedback();
+        // Release any socket before binding a new one
+        self.artnet = None;
+        self.artnet = match get_artnet_interface(&self.settings, &self.project) {
+            Ok(artnet) => {
+                self.artnet_error = None;
+                Some(artnet)
+            }
+            Err(e) => {
+                error!("ArtNet output is disabled: {}", e);
+                self.artnet_error = Some(e.to_string());
+                None
+            }
+        };
+        self.update_channels_assigned();
+        self.apply_home_values();
+        Ok(())
+    }
+
+    /// Record an undo step if the Project has changed since the last one. This
+    /// is called whenever the GUI is idle, so that a whole edit (e.g. a
+    /// slider drag, or typing into a text box) becomes a single step.
+    pub fn record_undo_checkpoint(&mut self) {
+        let json = match Project::to_json(&self.project) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Failed to serialise Project for undo: {}", e);
+                return;
+            }
+        };
+        match self.undo_checkpoint.take() {
+            Some((previous_json, previous)) if previous_json == json => {
+                self.undo_checkpoint = Some((previous_json, previous));
+                return;
+            }
+            Some((_, previous)) => {
+                if self.undo_stack.len() >= UNDO_LIMIT {
+                    self.undo_stack.pop_front();
+                }
+                self.undo_stack.push_back(previous);
+                self.redo_stack.clear();
+            }
+            None => {}
+        }
+        self.undo_checkpoint = Some((json, self.project.clone()));
+    }
+
+    pub fn undo(&mut self) {
+        self.record_undo_checkpoint();
+        match self.undo_stack.pop_back() {
+            Some(previous) => {
+                info!("Undo Project edit");
+                let current = std::mem::replace(&mut self.project, previous);
+                self.after_undo_or_redo(&current);
+                self.redo_stack.push(current);
+            }
+            None => debug!("Nothing to undo"),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.record_undo_checkpoint();
+        match self.redo_stack.pop() {
+            Some(next) => {
+                info!("Redo Project edit");
+                let current = std::mem::replace(&mut self.project, next);
+                self.after_undo_or_redo(&current);
+                self.undo_stack.push_back(current);
+            }
+            None => debug!("Nothing to redo"),
+        }
+    }
+
+    /// After swapping in a Project snapshot: keep the live macro values (which
+    /// are not part of an edit) and take the snapshot as the new checkpoint
+    fn after_undo_or_redo(&mut self, replaced: &Project) {
+        for fixture in self.project.fixtures.iter_mut() {
+            if let Some(r) = replaced.fixtures.iter().find(|r| r.label == fixture.label) {
+                carry_over_macro_values(
+                    &mut fixture.config.active_mode.macros,
+                    &r.config.active_mode.macros,
+                );
+            }
+        }
+        self.undo_checkpoint = Project::to_json(&self.project)
+            .ok()
+            .map(|json| (json, self.project.clone()));
+        self.clear_macro_feedback();
+        self.update_channels_assigned();
+        self.apply_macros = true;
+    }
+
+    /// Call when fixtures are added or removed (or the whole Project
+    /// replaced): macro feedback starts afresh for the current fixtures
+    pub fn clear_macro_feedback(&mut self) {
+        if let Some(macro_feedback) = &mut self.macro_feedback {
+            macro_feedback.clear();
+        }
+    }
+
+    /// Replace the configuration of a single fixture in the project with a
+    /// definition loaded from disk, without reloading the whole project. Macro
+    /// values and MIDI indices are kept for any macros with matching labels.
+    pub fn reload_fixture_config(
+        &mut self,
+        fixture_index: usize,
+        path: &str,
+    ) -> anyhow::Result<()> {
+        let config = load_fixture_config(path)?;
+
+        let fixture = self
+            .project
+            .fixtures
+            .get_mut(fixture_index)
+            .ok_or(anyhow!("No fixture with index {}", fixture_index))?;
+
+        if !config.name.eq_ignore_ascii_case(&fixture.config_name) {
+            warn!(
+                "Fixture \"{}\" was \"{}\", will now be \"{}\"",
+                &fixture.label, &fixture.config_name, &config.name
+            );
+            fixture.config_name = String::from(&config.name);
+        }
+
+        let previous_macros = fixture.config.active_mode.macros.clone();
+        fixture.set_config(&config)?;
+
+        let macros = &mut fixture.config.active_mode.macros;
+        macros.sort_by_key(|m| String::from(m.label()));
+        carry_over_macro_values(macros, &previous_macros);
+
+        info!("Reloaded config for fixture \"{}\"", &fixture.label);
+        self.update_channels_assigned();
+        Ok(())
+    }
+
+    /// Import a fixture config from a GDTF file (`.gdtf`) or an Open Fixture
+    /// Library definition (`.json`), making it available to add to the Project
+    /// (replacing any known fixture with the same name). The config is also
+    /// saved as JSON in `IMPORTED_FIXTURES_DIR`, so that it is loaded again on
+    /// the next start (and can be edited by hand).
+    pub fn import_fixture_config(&mut self, path: &str) -> anyhow::Result<FixtureConfig> {
+        let config = if path.to_lowercase().ends_with(".gdtf") {
+            load_gdtf(path)?
+        } else {
+            load_ofl(path)?
+        };
+
+        if let Err(e) = save_imported_fixture_config(Path::new(IMPORTED_FIXTURES_DIR), &config) {
+            warn!(
+                "Could not save imported fixture \"{}\"; it will only be available until restart: {}",
+                &config.name, e
+            );
+        }
+
+        self.known_fixtures
+            .retain(|x| !x.name.eq_ignore_ascii_case(&config.name));
+        self.known_fixtures.push(config.clone());
+        Ok(config)
+    }
+
+    /// Switch a fixture to a different mode (index into the modes in its
+    /// config), then re-derive channel assignments and re-apply home values.
+    /// Macros with the same label in both modes keep their current values.
+    pub fn set_fixture_mode(
+        &mut self,
+        fixture_index: usize,
+        mode_index: usize,
+    ) -> anyhow::Result<()> {
+        let fixture = self
+            .project
+            .fixtures
+            .get_mut(fixture_index)
+            .ok_or(anyhow!("No fixture with index {}", fixture_index))?;
+
+        let previous_mode_index = fixture.mode_index;
+        let previous_macros = fixture.config.active_mode.macros.clone();
+        let config = fixture.config.clone();
+
+        fixture.mode_index = mode_index;
+        if let Err(e) = fixture.set_config(&config) {
+            fixture.mode_index = previous_mode_index;
+            return Err(e);
+        }
+
+        let macros = &mut fixture.config.active_mode.macros;
+        macros.sort_by_key(|m| String::from(m.label()));
+        carry_over_macro_values(macros, &previous_macros);
+
+        info!(
+            "Fixture \"{}\" now in mode \"{}\"",
+            &fixture.label, &fixture.config.active_mode.name
+        );
+        let label = String::from(&fixture.label);
+        self.update_channels_assigned();
+        self.apply_home_values();
+
+        // The new footprint may run into the next fixture(s)
+        for issue in self.project.validate() {
+            if issue.involves(&label) {
+                warn!("Patch problem after mode change; {}", issue);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `set_fixture_mode`, but by fixture label and mode name (not
+    /// case-sensitive), e.g. for remote messages. The label may use `*` as a
+    /// wildcard, to switch every matching fixture.
+    pub fn set_fixture_mode_by_name(
+        &mut self,
+        fixture_label: &str,
+        mode_name: &str,
+    ) -> anyhow::Result<()> {
+        let fixture_indexes: Vec<usize> = self
+            .project
+            .fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| label_matches(fixture_label, &f.label))
+            .map(|(i, _)| i)
+            .collect();
+        if fixture_indexes.is_empty() {
+            return Err(anyhow!("No fixture with label \"{}\"", fixture_label));
+        }
+        for fixture_index in fixture_indexes {
+            let fixture = &self.project.fixtures[fixture_index];
+            let mode_index = fixture
+                .config
+                .modes
+                .iter()
+                .position(|m| m.name.eq_ignore_ascii_case(mode_name))
+                .ok_or(anyhow!(
+                    "Fixture \"{}\" has no mode \"{}\"",
+                    &fixture.label,
+                    mode_name
+                ))?;
+            self.set_fixture_mode(fixture_index, mode_index)?;
+        }
+        Ok(())
+    }
+
+    /// Back to a clean slate: cancel all animations, clear all overrides,
+    /// return macros and channels to home, and send a fresh frame immediately.
+    /// Unlike freezing macros, none of the current state is preserved.
+    pub fn reset_all(&mut self) {
+        warn!("Reset all: animations, overrides, macros and channels");
+        self.startup_fade = None;
+        self.channel_overrides.clear();
+        for fixture in self.project.fixtures.iter_mut() {
+            fixture.reset_macros();
+        }
+        for scene in self.project.scenes.iter_mut() {
+            scene.last_active = false;
+        }
+        self.last_scene = None;
+        self.apply_home_values();
+        if let Some(artnet) = &mut self.artnet {
+            artnet.send_next_frame_now();
+        }
+    }
+
+    /// Summary of fixtures (with current macro values) and scenes, for remote
+    /// controllers
+    pub fn project_state(&self) -> RemoteProjectState {
+        RemoteProjectState {
+            fixtures: self
+                .project
+                .fixtures
+                .iter()
+                .map(|f| RemoteFixtureState {
+                    label: String::from(&f.label),
+                    config_name: String::from(&f.config_name),
+                    enabled: f.enabled,
+                    macros: f.current_state(),
+                })
+                .collect(),
+            scene_labels: self
+                .project
+                .scenes
+                .iter()
+                .map(|s| String::from(&s.label))
+                .collect(),
+        }
+    }
+
+    /// Set the (first) Colour Macro of every enabled fixture to the same colour,
+    /// immediately. Fixtures without any Colour Macro are skipped. Returns the
+    /// number of fixtures affected.
+    pub fn set_all_colour(&mut self, colour: Color32) -> usize {
+        let mut matched_count = 0;
+        for fixture in self.project.fixtures.iter_mut().filter(|f| f.enabled) {
+            if let Some(colour_macro) =
+                fixture
+                    .config
+                    .active_mode
+                    .macros
+                    .iter_mut()
+                    .find_map(|m| match m {
+                        FixtureMacro::Colour(colour_macro) => Some(colour_macro),
+                        _ => None,
+                    })
+            {
+                colour_macro.animation = None;
+                colour_macro.kelvin = None;
+                colour_macro.current_value = colour;
+                matched_count += 1;
+            }
+        }
+        debug!(
+            "Set all colour to {:?}; {} fixtures matched",
+            colour, matched_count
+        );
+        self.master_colour = colour;
+        matched_count
+    }
+
+    /// Fill a gradient across the Colour Macros of an ordered group of fixtures
+    /// (by label): the first gets `colour_a`, the last gets `colour_b`, and
+    /// those in between are interpolated in HSV. Fixtures which are unknown,
+    /// disabled or have no Colour Macro are left out. Returns the number of
+    /// fixtures affected.
+    pub fn apply_gradient(
+        &mut self,
+        group: &[String],
+        colour_a: Color32,
+        colour_b: Color32,
+    ) -> usize {
+        let targets: Vec<usize> = group
+            .iter()
+            .filter_map(|label| {
+                let index = self
+                    .project
+                    .fixtures
+                    .iter()
+                    .position(|f| f.label.eq_ignore_ascii_case(label));
+                if index.is_none() {
+                    warn!("Gradient: no fixture \"{}\"; skipped", label);
+                }
+                index
+            })
+            .filter(|i| {
+                let fixture = &self.project.fixtures[*i];
+                fixture.enabled
+                    && fixture
+                        .config
+                        .active_mode
+                        .macros
+                        .iter()
+                        .any(|m| matches!(m, FixtureMacro::Colour(_)))
+            })
+            .collect();
+        let last = targets.len().saturating_sub(1).max(1) as f32;
+        for (position, fixture_index) in targets.iter().enumerate() {
+            let colour = interpolate_hsv(colour_a, colour_b, position as f32 / last);
+            if let Some(FixtureMacro::Colour(colour_macro)) = self.project.fixtures[*fixture_index]
+                .config
+                .active_mode
+                .macros
+                .iter_mut()
+                .find(|m| matches!(m, FixtureMacro::Colour(_)))
+            {
+                colour_macro.animation = None;
+                colour_macro.kelvin = None;
+                colour_macro.current_value = colour;
+            }
+        }
+        debug!(
+            "Gradient {:?} => {:?} applied to {} fixtures",
+            colour_a,
+            colour_b,
+            targets.len()
+        );
+        targets.len()
+    }
+
+    /// Set a channel (numbered 1-512) directly, bypassing macros; the value
+    /// will persist until the override is cleared
+    pub fn set_channel_override(&mut self, channel: u16, value: u8) -> anyhow::Result<()> {
+        if !(1..=self.universe_size).contains(&channel) {
+            return Err(anyhow!(
+                "Channel {} out of range; must be 1-{}",
+                channel,
+                self.universe_size
+            ));
+        }
+        debug!("Override channel {} => {}", channel, value);
+        self.channel_overrides.insert(channel, value);
+        Ok(())
+    }
+
+    /// Write a single channel (numbered 1-512) or a full universe buffer
+    /// straight into the channels state. Unlike an override, this can be
+    /// changed again by anything else that sets channel values.
+    pub fn set_channels_direct(&mut self, m: RemoteChannelControlMessage) -> anyhow::Result<()> {
+        match m {
+            RemoteChannelControlMessage::Single(RemoteChannelMessage { channel, value }) => {
+                if !(1..=self.universe_size).contains(&channel) {
+                    return Err(anyhow!(
+                        "Channel {} out of range; must be 1-{}",
+                        channel,
+                        self.universe_size
+                    ));
+                }
+                trace!("Set channel {} => {}", channel, value);
+                if let Some(c) = self.channels_state.get_mut((channel - 1) as usize) {
+                    *c = value;
+                }
+            }
+            RemoteChannelControlMessage::Buffer(values) => {
+                if values.len() != self.universe_size as usize {
+                    return Err(anyhow!(
+                        "Buffer length {} does not match universe size {}",
+                        values.len(),
+                        self.universe_size
+                    ));
+                }
+                trace!("Set all channels from buffer");
+                self.channels_state = values;
+            }
+        }
+        Ok(())
+    }
+
+    /// Release an overridden channel, so that it outputs whatever is
+    /// underneath again, i.e. the value of the macro it belongs to (or the
+    /// channels state, if macros are frozen)
+    pub fn clear_channel_override(&mut self, channel: u16) {
+        debug!("Clear override for channel {}", channel);
+        if self.channel_overrides.remove(&channel).is_some() {
+            if let Some(artnet) = &mut self.artnet {
+                artnet.send_next_frame_now();
+            }
+        }
+    }
+
+    pub fn clear_channel_overrides(&mut self) {
+        debug!("Clear all channel overrides");
+        self.channel_overrides.clear();
+        if let Some(artnet) = &mut self.artnet {
+            artnet.send_next_frame_now();
+        }
+    }
+
+    /// Set every channel to zero, in every universe
+    pub fn zero_all(&mut self) {
+        zero(&mut self.channels_state);
+        for state in self.extra_universes.values_mut() {
+            zero(state);
+        }
+    }
+
+    /// Set every channel to a random value, in every universe
+    pub fn random_all(&mut self) {
+        random(&mut self.channels_state);
+        for state in self.extra_universes.values_mut() {
+            random(state);
+        }
+    }
+
     pub fn apply_home_values(&mut self) {
         debug!("Apply home values");
         debug!("Before: {:?}", self.channels_state);
 
-        self.channels_state = [0].repeat(CHANNELS_PER_UNIVERSE as usize); // init zeroes
+        self.channels_state = [0].repeat(self.universe_size as usize); // init zeroes
+        for state in self.extra_universes.values_mut() {
+            zero(state);
+        }
 
-        let fixtures_clone = self.project.fixtures.clone();
-        for fixture in fixtures_clone.iter() {
-            let current_mode = &fixture.config.active_mode;
-            for m in &current_mode.mappings {
-                if let Some(default_value) = m.home {
-                    let channel_index = m.channel + fixture.offset_channels - 1;
-                    self.channels_state[channel_index as usize] = default_value;
+        for index in 0..self.project.fixtures.len() {
+            self.apply_fixture_home_values(index);
+        }
+        debug!("After: {:?}", self.channels_state);
+    }
+
+    /// Apply home values for a single fixture only (if enabled), leaving all
+    /// other channels as they are
+    pub fn apply_fixture_home_values(&mut self, fixture_index: usize) {
+        if let Some(fixture) = self
+            .project
+            .fixtures
+            .get(fixture_index)
+            .filter(|f| f.enabled)
+        {
+            let state = if fixture.universe == DEFAULT_UNIVERSE {
+                &mut self.channels_state
+            } else {
+                match self.extra_universes.get_mut(&fixture.universe) {
+                    Some(state) => state,
+                    None => return, // channels not assigned (yet)
+                }
+            };
+            for m in &fixture.config.active_mode.mappings {
+                if let (Some(default_value), Some(c)) =
+                    (m.home, state.get_mut(fixture.channel_index(m.channel)))
+                {
+                    *c = default_value;
                 }
             }
         }
-        debug!("After: {:?}", self.channels_state);
+    }
+
+    /// Save the Project to its file, but only if anything has changed since
+    /// it was last saved or loaded
+    fn auto_save_project(&mut self) {
+        let Some(path) = &self.current_project_path else {
+            return;
+        };
+        match Project::to_json(&self.project) {
+            Ok(json) => {
+                if self.last_saved_project.as_ref() != Some(&json) {
+                    info!("Project has changed; auto-save...");
+                    match Project::save(path, &self.project) {
+                        Ok(()) => self.last_saved_project = Some(json),
+                        Err(e) => error!("Failed to auto-save Project: {}", e),
+                    }
+                }
+            }
+            Err(e) => error!("Failed to serialise Project for auto-save: {}", e),
+        }
     }
 
     pub fn reset_before_quit(&mut self) {
@@ -611,7 +2401,7 @@ impl Model {
             BehaviourOnExit::Zero => {
                 info!("Exit Behaviour: All fixtures Go Zero");
                 self.apply_macros = false;
-                zero(&mut self.channels_state);
+                self.zero_all();
                 self.update();
             }
         }
@@ -620,23 +2410,100 @@ impl Model {
     }
 }
 
-fn fixtures_list_contains(search_list: &Option<Vec<String>>, label_search_string: &str) -> bool {
-    if let Some(list) = search_list {
-        for label in list.iter() {
-            if label.eq_ignore_ascii_case(label_search_string) {
-                return true;
+/// Copy values (and MIDI knob indexes) from previous macros onto new macros
+/// with matching labels, e.g. after a fixture's config or mode has changed
+fn carry_over_macro_values(macros: &mut [FixtureMacro], previous_macros: &[FixtureMacro]) {
+    for m in macros.iter_mut() {
+        if let Some(previous) = previous_macros.iter().find(|p| p.label() == m.label()) {
+            match (m, previous) {
+                (FixtureMacro::Control(m), FixtureMacro::Control(p)) => {
+                    m.current_value = p.current_value;
+                    m.global_index = p.global_index;
+                }
+                (FixtureMacro::Colour(m), FixtureMacro::Colour(p)) => {
+                    m.current_value = p.current_value;
+                }
+                (FixtureMacro::Wheel(m), FixtureMacro::Wheel(p)) => {
+                    m.current_value = p.current_value;
+                }
+                _ => {}
             }
         }
-        false
+    }
+}
+
+/// Whether a fixture label is targeted by a list of fixture labels; no list
+/// means all fixtures. Each entry may itself be a comma-separated list, and
+/// may use `*` as a wildcard, e.g. "mover-*" or "mover-1,mover-3".
+fn fixtures_list_contains(search_list: &Option<Vec<String>>, label_search_string: &str) -> bool {
+    if let Some(list) = search_list {
+        list.iter()
+            .flat_map(|entry| entry.split(','))
+            .any(|pattern| label_matches(pattern.trim(), label_search_string))
     } else {
         true
     }
 }
 
+/// Case-insensitive match of a label against a pattern, where `*` matches
+/// any run of characters (including none)
+fn label_matches(pattern: &str, label: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let label = label.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = label.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty(); // no wildcard: exact match
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Scale a 7-bit MIDI value (0-127) to the full DMX range (0-255)
+fn midi_to_dmx(value: u8) -> u8 {
+    (value.min(127) as u16 * 255 / 127) as u8
+}
+
+/// Adjust a macro from a MIDI Control Change value (0-127)
+fn apply_midi_value(m: &mut FixtureMacro, value: u8) {
+    match m {
+        FixtureMacro::Control(control_macro) => {
+            let value = midi_to_dmx(value);
+            debug!("Adjust {} to {}", &control_macro.label, value);
+            control_macro.set_target_value(value);
+        }
+        FixtureMacro::Colour(colour_macro) => {
+            let value = midi_to_dmx(value);
+
+            let [r, g, b, a] = colour_macro.current_value.to_array();
+
+            colour_macro.current_value = Color32::from_rgba_premultiplied(r, g, b, value);
+
+            debug!("Color a {} => {}", a, colour_macro.current_value.a());
+        }
+        FixtureMacro::Wheel(wheel_macro) => {
+            let slot_index = value.min(127) as usize * wheel_macro.slots.len() / 128;
+            debug!("Select {} slot {}", &wheel_macro.label, slot_index);
+            wheel_macro.current_value = slot_index;
+        }
+    }
+}
+
 pub fn attempt_connection(model: &mut Model) {
     match model.tether_interface.connect(
         model.should_quit.clone(),
         model.settings.tether_host.as_deref(),
+        &model.settings.tether_role,
+        model.settings.tether_id.as_deref(),
     ) {
         Ok(_) => {
             model.tether_status = TetherStatus::Connected;
@@ -646,3 +2513,529 @@ pub fn attempt_connection(model: &mut Model) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serde_json::json;
+
+    use super::*;
+    use crate::output::{MockOutput, RecordedFrames};
+
+    const UNIVERSE_SIZE: usize = 32;
+
+    /// A Varytec HERO 340FX (brightness on channel 6, RGBW on 8-11) patched at
+    /// channel 1, and a Scene for it
+    const PROJECT_JSON: &str = r#"{
+        "fixtures": [
+            { "label": "Hero", "configName": "Varytec HERO 340FX", "offsetChannels": 0 }
+        ],
+        "scenes": [
+            {
+                "label": "Blue",
+                "state": {
+                    "Hero": {
+                        "brightness": { "ControlValue": 200 },
+                        "colour": { "ColourValue": [0, 0, 255, 255] }
+                    }
+                }
+            }
+        ]
+    }"#;
+
+    fn test_model() -> (Model, RecordedFrames) {
+        let cli = Cli::parse_from(["test", "--artnet.universeSize", "32"]);
+        let project = Project::from_json(PROJECT_JSON).unwrap();
+        let (output, frames) = MockOutput::new(8);
+        (Model::with_output(cli, project, Box::new(output)), frames)
+    }
+
+    /// Send a remote message as if from Tether, then update until a frame is
+    /// sent
+    fn send_and_update(model: &mut Model, frames: &RecordedFrames, m: RemoteControlMessage) {
+        model.tether_interface.message_sender().send(m).unwrap();
+        next_frame(model, frames);
+    }
+
+    /// Update until the next frame is sent, and return it
+    fn next_frame(model: &mut Model, frames: &RecordedFrames) -> Vec<u8> {
+        frames.lock().unwrap().clear();
+        while frames.lock().unwrap().is_empty() {
+            model.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        latest_frame(frames)
+    }
+
+    fn latest_frame(frames: &RecordedFrames) -> Vec<u8> {
+        let frames = frames.lock().unwrap();
+        let (universe, data) = frames.last().expect("no frame sent");
+        assert_eq!(*universe, DEFAULT_UNIVERSE);
+        data.clone()
+    }
+
+    fn macro_message(value: serde_json::Value) -> RemoteControlMessage {
+        RemoteControlMessage::MacroAnimation(serde_json::from_value(value).unwrap())
+    }
+
+    #[test]
+    fn starts_at_home_values_without_sending() {
+        let (model, frames) = test_model();
+        assert_eq!(model.channels_state.len(), UNIVERSE_SIZE);
+        assert_eq!(model.channels_state[2], 128); // tilt home
+        assert!(model.current_project_path.is_none());
+        assert!(model.handles.is_empty());
+        assert!(frames.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn small_universe_truncates_fixtures() {
+        let cli = Cli::parse_from(["test", "--artnet.universeSize", "8"]);
+        let project = Project::from_json(PROJECT_JSON).unwrap();
+        let (output, frames) = MockOutput::new(8);
+        let mut model = Model::with_output(cli, project, Box::new(output));
+        assert_eq!(model.channels_state.len(), 8);
+        // The 16-channel fixture takes every channel there is
+        assert_eq!(model.channels_assigned, vec![true; 8]);
+
+        send_and_update(
+            &mut model,
+            &frames,
+            RemoteControlMessage::SceneAnimation(RemoteSceneMessage {
+                scene_label: String::from("Blue"),
+                ms: None,
+                beats: None,
+                stagger_ms: None,
+                fixture_labels: None,
+            }),
+        );
+        let frame = latest_frame(&frames);
+        assert_eq!(frame.len(), 8);
+        assert_eq!(frame[5], 200);
+        assert_eq!(frame[7], 0); // red; the rest of RGBW is past the end
+        assert_eq!(model.channels_state.len(), 8);
+    }
+
+    #[test]
+    fn universe_out_of_range_is_not_output() {
+        let cli = Cli::parse_from(["test"]);
+        let project = Project::from_json(
+            r#"{
+                "fixtures": [
+                    { "label": "Hero", "configName": "Varytec HERO 340FX", "offsetChannels": 0, "universe": 40000 }
+                ],
+                "scenes": []
+            }"#,
+        )
+        .unwrap();
+        let (output, _frames) = MockOutput::new(8);
+        let model = Model::with_output(cli, project, Box::new(output));
+        assert!(model.extra_universes.is_empty());
+    }
+
+    /// Auto random/zero (ticked by hand here) cover every universe
+    #[test]
+    fn auto_test_modes_cover_all_universes() {
+        let cli = Cli::parse_from(["test", "--artnet.universeSize", "32"]);
+        let project = Project::from_json(
+            r#"{
+                "fixtures": [
+                    { "label": "Hero", "configName": "Varytec HERO 340FX", "offsetChannels": 0 },
+                    { "label": "Hero 2", "configName": "Varytec HERO 340FX", "offsetChannels": 0, "universe": 1 }
+                ],
+                "scenes": []
+            }"#,
+        )
+        .unwrap();
+        let (output, _frames) = MockOutput::new(8);
+        let mut model = Model::with_output(cli, project, Box::new(output));
+        model.apply_macros = false;
+        let (tick_tx, tick_rx) = std::sync::mpsc::channel();
+        model.auto_tick_rx = Some(tick_rx);
+        assert_eq!(model.extra_universes[&1][6], 255); // shutter home
+
+        model.settings.auto_zero = true;
+        tick_tx.send(()).unwrap();
+        model.update();
+        assert!(model.channels_state.iter().all(|c| *c == 0));
+        assert!(model.extra_universes[&1].iter().all(|c| *c == 0));
+
+        model.settings.auto_random = true;
+        tick_tx.send(()).unwrap();
+        model.update();
+        // 32 zero bytes from a random source is as good as impossible
+        assert!(model.channels_state.iter().any(|c| *c != 0));
+        assert!(model.extra_universes[&1].iter().any(|c| *c != 0));
+    }
+
+    #[test]
+    fn scene_message_is_output() {
+        let (mut model, frames) = test_model();
+        send_and_update(
+            &mut model,
+            &frames,
+            RemoteControlMessage::SceneAnimation(RemoteSceneMessage {
+                scene_label: String::from("blue"),
+                ms: None,
+                beats: None,
+                stagger_ms: None,
+                fixture_labels: None,
+            }),
+        );
+        let frame = latest_frame(&frames);
+        assert_eq!(frame.len(), UNIVERSE_SIZE);
+        assert_eq!(frame[5], 200); // brightness
+        assert_eq!(&frame[7..11], &[0, 0, 255, 0]); // RGBW
+        assert_eq!(model.last_scene.as_deref(), Some("Blue"));
+    }
+
+    #[test]
+    fn macro_message_is_output() {
+        let (mut model, frames) = test_model();
+        send_and_update(
+            &mut model,
+            &frames,
+            macro_message(json!({
+                "fixtureLabels": ["hero"],
+                "macroLabel": "brightness",
+                "value": { "ControlValue": 99 }
+            })),
+        );
+        assert_eq!(latest_frame(&frames)[5], 99);
+    }
+
+    fn brightness_message(value: u8) -> RemoteControlMessage {
+        macro_message(json!({
+            "macroLabel": "brightness",
+            "value": { "ControlValue": value }
+        }))
+    }
+
+    #[test]
+    fn override_is_output_but_not_kept_in_state() {
+        let (mut model, frames) = test_model();
+        send_and_update(&mut model, &frames, brightness_message(99));
+        model.set_channel_override(6, 10).unwrap();
+        assert_eq!(next_frame(&mut model, &frames)[5], 10);
+        assert_eq!(model.channels_state[5], 99);
+    }
+
+    #[test]
+    fn released_override_returns_to_macro_value() {
+        let (mut model, frames) = test_model();
+        send_and_update(&mut model, &frames, brightness_message(99));
+        model.set_channel_override(6, 10).unwrap();
+        // The macro keeps changing underneath the override...
+        send_and_update(&mut model, &frames, brightness_message(150));
+        assert_eq!(latest_frame(&frames)[5], 10);
+        // ...and its latest value is output once released
+        model.clear_channel_override(6);
+        assert_eq!(next_frame(&mut model, &frames)[5], 150);
+    }
+
+    #[test]
+    fn released_override_returns_to_frozen_state() {
+        let (mut model, frames) = test_model();
+        send_and_update(&mut model, &frames, brightness_message(99));
+        model.apply_macros = false;
+        model.set_channel_override(6, 10).unwrap();
+        model.set_channel_override(20, 20).unwrap(); // unpatched
+        let frame = next_frame(&mut model, &frames);
+        assert_eq!((frame[5], frame[19]), (10, 20));
+        model.clear_channel_overrides();
+        let frame = next_frame(&mut model, &frames);
+        assert_eq!((frame[5], frame[19]), (99, 0));
+    }
+
+    #[test]
+    fn startup_fade_keeps_channels_written_during_it() {
+        let (mut model, frames) = test_model();
+        model.apply_macros = false;
+        let home_values = model.channels_state.clone();
+        zero(&mut model.channels_state);
+        model.startup_fade = Some(StartupFade::new(Duration::from_secs(60), home_values));
+
+        let set_channel = |channel: u16, value: u8| {
+            RemoteControlMessage::Channel(RemoteChannelControlMessage::Single(
+                RemoteChannelMessage { channel, value },
+            ))
+        };
+        send_and_update(&mut model, &frames, set_channel(3, 200)); // tilt, home 128
+        send_and_update(&mut model, &frames, set_channel(21, 77)); // unpatched
+        for _ in 0..3 {
+            let frame = next_frame(&mut model, &frames);
+            assert_eq!((frame[2], frame[20]), (200, 77));
+        }
+        // Everything else is still fading in
+        assert!(model.startup_fade.is_some());
+        assert!(model.channels_state[6] < 255); // shutter
+    }
+
+    #[test]
+    fn fixture_mode_by_name_ignores_case() {
+        let (mut model, _frames) = test_model();
+        assert!(model
+            .set_fixture_mode_by_name("HERO", "16-Channel Mode")
+            .is_ok());
+        assert!(model
+            .set_fixture_mode_by_name("h*", "16-channel mode")
+            .is_ok());
+        assert!(model
+            .set_fixture_mode_by_name("Hero 2", "16-channel mode")
+            .is_err());
+        assert!(model
+            .set_fixture_mode_by_name("Hero", "8-channel mode")
+            .is_err());
+    }
+
+    #[test]
+    fn macro_feedback_starts_afresh_when_cleared() {
+        let (mut model, _frames) = test_model();
+        model.macro_feedback = Some(MacroFeedbackPublisher::new(Duration::ZERO));
+        let poll = |model: &mut Model| {
+            model
+                .macro_feedback
+                .as_mut()
+                .unwrap()
+                .poll(&model.project.fixtures)
+                .map(|values| values.len())
+        };
+        let all = poll(&mut model).expect("first poll publishes every value");
+        assert_eq!(poll(&mut model), None);
+        model.clear_macro_feedback();
+        assert_eq!(poll(&mut model), Some(all));
+    }
+
+    /// Rename the Scene as a Project edit, then record it as the GUI would
+    fn edit_scene_label(model: &mut Model, label: &str) {
+        model.project.scenes[0].label = String::from(label);
+        model.record_undo_checkpoint();
+    }
+
+    #[test]
+    fn each_edit_is_one_undo_step() {
+        let (mut model, _frames) = test_model();
+        model.record_undo_checkpoint();
+        edit_scene_label(&mut model, "A");
+        model.record_undo_checkpoint(); // unchanged, so no step
+        edit_scene_label(&mut model, "B");
+        assert_eq!(model.undo_stack.len(), 2);
+
+        model.undo();
+        assert_eq!(model.project.scenes[0].label, "A");
+        model.undo();
+        assert_eq!(model.project.scenes[0].label, "Blue");
+        model.undo(); // nothing left
+        assert_eq!(model.project.scenes[0].label, "Blue");
+        model.redo();
+        assert_eq!(model.project.scenes[0].label, "A");
+        assert_eq!((model.undo_stack.len(), model.redo_stack.len()), (1, 1));
+    }
+
+    #[test]
+    fn oldest_undo_step_is_dropped_at_limit() {
+        let (mut model, _frames) = test_model();
+        model.record_undo_checkpoint();
+        for i in 0..UNDO_LIMIT + 5 {
+            edit_scene_label(&mut model, &i.to_string());
+        }
+        assert_eq!(model.undo_stack.len(), UNDO_LIMIT);
+        for _ in 0..UNDO_LIMIT + 5 {
+            model.undo();
+        }
+        // "Blue" and the first few edits are gone
+        assert_eq!(model.project.scenes[0].label, "4");
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let (mut model, _frames) = test_model();
+        model.record_undo_checkpoint();
+        edit_scene_label(&mut model, "A");
+        edit_scene_label(&mut model, "B");
+        model.undo();
+        assert_eq!(model.redo_stack.len(), 1);
+
+        edit_scene_label(&mut model, "C");
+        assert!(model.redo_stack.is_empty());
+        model.redo();
+        assert_eq!(model.project.scenes[0].label, "C");
+        model.undo();
+        assert_eq!(model.project.scenes[0].label, "A");
+    }
+
+    #[test]
+    fn undo_keeps_live_macro_values() {
+        let (mut model, _frames) = test_model();
+        model.record_undo_checkpoint();
+        edit_scene_label(&mut model, "A");
+        let FixtureMacro::Control(brightness) = hero_macro(&mut model, "brightness") else {
+            panic!("brightness should be a Control Macro");
+        };
+        brightness.current_value = 150;
+        // Macro values are not edits
+        model.record_undo_checkpoint();
+        assert_eq!(model.undo_stack.len(), 1);
+
+        model.undo();
+        assert_eq!(model.project.scenes[0].label, "Blue");
+        let FixtureMacro::Control(brightness) = hero_macro(&mut model, "brightness") else {
+            panic!("brightness should be a Control Macro");
+        };
+        assert_eq!(brightness.current_value, 150);
+    }
+
+    fn hero_macro<'a>(model: &'a mut Model, label: &str) -> &'a mut FixtureMacro {
+        model.project.fixtures[0]
+            .config
+            .active_mode
+            .macros
+            .iter_mut()
+            .find(|m| m.label() == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn replacement_animation_starts_from_live_value() {
+        let (mut model, frames) = test_model();
+        let animate_to = |value: u8| {
+            serde_json::from_value::<RemoteMacroMessage>(json!({
+                "macroLabel": "brightness",
+                "value": { "ControlValue": value },
+                "ms": 400,
+                "easing": "Linear"
+            }))
+            .unwrap()
+        };
+        model.handle_macro_message(animate_to(255));
+        std::thread::sleep(Duration::from_millis(200));
+        // Replaced half way through, without any update in between (so the
+        // macro's current value has not moved on from 0 yet)
+        model.handle_macro_message(animate_to(0));
+
+        let FixtureMacro::Control(brightness) = hero_macro(&mut model, "brightness") else {
+            panic!("brightness should be a Control Macro");
+        };
+        let seeded = brightness.current_value;
+        assert!(
+            (64..=192).contains(&seeded),
+            "should start from around half way, not {}",
+            seeded
+        );
+        let start = (brightness.animation.as_mut().unwrap().get_value() * 255.0) as u8;
+        assert!(start.abs_diff(seeded) <= 16);
+
+        // Only the newest animation runs: straight down from there
+        model.apply_macros = true;
+        let mut previous = seeded;
+        loop {
+            let value = next_frame(&mut model, &frames)[5];
+            assert!(value <= previous, "went back up to {}", value);
+            previous = value;
+            let FixtureMacro::Control(brightness) = hero_macro(&mut model, "brightness") else {
+                unreachable!();
+            };
+            if brightness.animation.is_none() {
+                break;
+            }
+        }
+        assert_eq!(previous, 0);
+    }
+
+    #[test]
+    fn replacement_colour_animation_starts_from_live_value() {
+        let (mut model, _frames) = test_model();
+        let animate_to = |colour: [u8; 4], ms: Option<u64>| {
+            serde_json::from_value::<RemoteMacroMessage>(json!({
+                "macroLabel": "colour",
+                "value": { "ColourValue": colour },
+                "ms": ms,
+                "easing": "Linear"
+            }))
+            .unwrap()
+        };
+        model.handle_macro_message(animate_to([0, 0, 0, 255], None));
+        model.handle_macro_message(animate_to([255, 0, 0, 255], Some(400)));
+        std::thread::sleep(Duration::from_millis(200));
+        model.handle_macro_message(animate_to([0, 0, 255, 255], Some(400)));
+
+        let FixtureMacro::Colour(colour) = hero_macro(&mut model, "colour") else {
+            panic!("colour should be a Colour Macro");
+        };
+        let (_, start_colour, end_colour) = colour.animation.as_ref().unwrap();
+        assert!(
+            (64..=192).contains(&start_colour.r()),
+            "should start from around half way to red, not {:?}",
+            start_colour
+        );
+        assert_eq!(
+            *end_colour,
+            Color32::from_rgba_premultiplied(0, 0, 255, 255)
+        );
+    }
+
+    #[test]
+    fn animation_message_reaches_its_target() {
+        let (mut model, frames) = test_model();
+        send_and_update(
+            &mut model,
+            &frames,
+            macro_message(json!({
+                "macroLabel": "colour",
+                "value": { "ColourValue": [255, 0, 0, 255] },
+                "ms": 100
+            })),
+        );
+        let started = Instant::now();
+        while latest_frame(&frames)[7..11] != [255, 0, 0, 0] {
+            assert!(
+                started.elapsed() < Duration::from_secs(2),
+                "animation did not finish"
+            );
+            model.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn label_matches_exactly_ignoring_case() {
+        assert!(label_matches("Mover-1", "mover-1"));
+        assert!(!label_matches("mover", "mover-1"));
+        assert!(!label_matches("mover-1", "mover"));
+    }
+
+    #[test]
+    fn label_matches_wildcards() {
+        assert!(label_matches("mover-*", "mover-12")); // prefix
+        assert!(label_matches("mover-*", "mover-"));
+        assert!(!label_matches("mover-*", "spot-1"));
+        assert!(label_matches("*-left", "wash-left")); // suffix
+        assert!(!label_matches("*-left", "wash-right"));
+        assert!(label_matches("wash*left", "wash-stage-left")); // infix
+        assert!(!label_matches("wash*left", "wash-stage-right"));
+        assert!(label_matches("*stage*", "wash-stage-left"));
+        assert!(label_matches("a**b", "axb"));
+    }
+
+    #[test]
+    fn label_matches_empty() {
+        assert!(label_matches("", ""));
+        assert!(!label_matches("", "mover"));
+        assert!(label_matches("*", ""));
+        assert!(label_matches("*", "mover"));
+        assert!(!label_matches("mover", ""));
+    }
+
+    /// The text either side of a wildcard may not share characters
+    #[test]
+    fn label_matches_overlapping_parts() {
+        assert!(!label_matches("a*a", "a"));
+        assert!(label_matches("a*a", "aa"));
+        assert!(label_matches("a*a", "aba"));
+        assert!(!label_matches("ab*ba", "aba"));
+        assert!(label_matches("ab*ba", "abba"));
+        assert!(!label_matches("a*b*a", "ab"));
+        assert!(label_matches("a*b*a", "aba"));
+        assert!(!label_matches("*aa*aa", "aaa"));
+    }
+}
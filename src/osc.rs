@@ -0,0 +1,335 @@
+use std::{net::UdpSocket, sync::mpsc::Sender, thread::JoinHandle};
+
+use anyhow::anyhow;
+use log::{debug, error, info, trace};
+
+use crate::tether_interface::{RemoteControlMessage, RemoteMacroMessage, RemoteMacroValue};
+
+#[derive(Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+/// Read a null-terminated OSC string, padded to a multiple of 4 bytes;
+/// returns the string and the offset just after the padding
+fn read_string(buff: &[u8], offset: usize) -> anyhow::Result<(String, usize)> {
+    let rest = buff
+        .get(offset..)
+        .ok_or_else(|| anyhow!("OSC packet truncated"))?;
+    let length = rest
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| anyhow!("OSC string not terminated"))?;
+    let s = String::from_utf8(rest[..length].to_vec())?;
+    Ok((s, offset + (length + 4) / 4 * 4))
+}
+
+fn read_4_bytes(buff: &[u8], offset: usize) -> anyhow::Result<[u8; 4]> {
+    buff.get(offset..offset + 4)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow!("OSC argument truncated"))
+}
+
+/// Parse an OSC packet: a single message, or a bundle of (possibly nested)
+/// messages, which are returned in order. Timetags are ignored.
+pub fn parse_packet(buff: &[u8]) -> anyhow::Result<Vec<OscMessage>> {
+    if buff.starts_with(b"#bundle\0") {
+        if buff.len() < 16 {
+            return Err(anyhow!("OSC bundle truncated"));
+        }
+        let mut messages = Vec::new();
+        let mut offset = 16; // "#bundle\0" plus 8-byte timetag
+        while offset < buff.len() {
+            let size = u32::from_be_bytes(read_4_bytes(buff, offset)?) as usize;
+            let element = buff
+                .get(offset + 4..offset + 4 + size)
+                .ok_or_else(|| anyhow!("OSC bundle element truncated"))?;
+            messages.append(&mut parse_packet(element)?);
+            offset += 4 + size;
+        }
+        return Ok(messages);
+    }
+
+    let (address, offset) = read_string(buff, 0)?;
+    if !address.starts_with('/') {
+        return Err(anyhow!("Invalid OSC address \"{}\"", address));
+    }
+    let (type_tags, mut offset) = if offset < buff.len() {
+        read_string(buff, offset)?
+    } else {
+        (String::from(","), offset) // no arguments, old-style
+    };
+    if !type_tags.starts_with(',') {
+        return Err(anyhow!("Invalid OSC type tags \"{}\"", type_tags));
+    }
+    let mut args = Vec::new();
+    for tag in type_tags.chars().skip(1) {
+        let (arg, next) = match tag {
+            'i' => {
+                let bytes = read_4_bytes(buff, offset)?;
+                (OscArg::Int(i32::from_be_bytes(bytes)), offset + 4)
+            }
+            'f' => {
+                let bytes = read_4_bytes(buff, offset)?;
+                (OscArg::Float(f32::from_be_bytes(bytes)), offset + 4)
+            }
+            's' => {
+                let (s, next) = read_string(buff, offset)?;
+                (OscArg::String(s), next)
+            }
+            _ => return Err(anyhow!("Unsupported OSC argument type '{}'", tag)),
+        };
+        args.push(arg);
+        offset = next;
+    }
+    Ok(vec![OscMessage { address, args }])
+}
+
+/// Translate an OSC message into the same control message a Tether client
+/// would send. Supported:
+/// - `/macro/<fixture>/<macro label> <value>`: a float is 0-1, an int 0-255
+pub fn to_remote_message(m: &OscMessage) -> anyhow::Result<RemoteControlMessage> {
+    let parts: Vec<&str> = m.address.trim_start_matches('/').split('/').collect();
+    match parts.as_slice() {
+        ["macro", fixture_label, macro_label] => {
+            let value = match m.args.first() {
+                Some(OscArg::Float(f)) => (f.clamp(0., 1.) * 255.0).round() as u8,
+                Some(OscArg::Int(i)) => (*i).clamp(0, 255) as u8,
+                Some(OscArg::String(_)) | None => {
+                    return Err(anyhow!("OSC {} needs a number value", &m.address))
+                }
+            };
+            Ok(RemoteControlMessage::MacroAnimation(RemoteMacroMessage {
+                fixture_labels: Some(vec![String::from(*fixture_label)]),
+                macro_label: String::from(*macro_label),
+                value: RemoteMacroValue::ControlValue(value),
+                ms: None,
+                beats: None,
+                colour_space: None,
+                stagger_ms: None,
+                repeat: None,
+                easing: None,
+            }))
+        }
+        _ => Err(anyhow!("Unknown OSC address {}", &m.address)),
+    }
+}
+
+/// Listen for OSC on the given UDP port, passing translated messages on to
+/// the same channel as remote (Tether) control messages
+pub fn spawn_osc_listener(
+    port: u16,
+    tx: Sender<RemoteControlMessage>,
+) -> anyhow::Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))?;
+    info!("Listening for OSC on port {}", port);
+
+    let handle = std::thread::spawn(move || {
+        let mut buff = [0u8; 1536];
+        loop {
+            let (length, src) = match socket.recv_from(&mut buff) {
+                Ok(received) => received,
+                Err(e) => {
+                    error!("OSC listener failed: {}", e);
+                    break;
+                }
+            };
+            let messages = match parse_packet(&buff[..length]) {
+                Ok(messages) => messages,
+                Err(e) => {
+                    error!("Invalid OSC packet from {}: {}", src, e);
+                    continue;
+                }
+            };
+            for m in messages {
+                trace!("OSC from {}: {:?}", src, m);
+                match to_remote_message(&m) {
+                    Ok(remote_message) => {
+                        if tx.send(remote_message).is_err() {
+                            debug!("OSC listener ended");
+                            return;
+                        }
+                    }
+                    Err(e) => error!("Ignoring OSC message: {}", e),
+                }
+            }
+        }
+        debug!("OSC listener ended");
+    });
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An OSC string: null-terminated and padded to a multiple of 4 bytes
+    fn osc_string(s: &str) -> Vec<u8> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize((s.len() + 4) / 4 * 4, 0);
+        bytes
+    }
+
+    fn int_float_string_message() -> Vec<u8> {
+        let mut packet = osc_string("/test/args");
+        packet.extend(osc_string(",ifs"));
+        packet.extend(42i32.to_be_bytes());
+        packet.extend(0.5f32.to_be_bytes());
+        packet.extend(osc_string("hello"));
+        packet
+    }
+
+    fn macro_message(arg: OscArg) -> OscMessage {
+        OscMessage {
+            address: String::from("/macro/Mover 1/dimmer"),
+            args: vec![arg],
+        }
+    }
+
+    fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+        let mut packet = b"#bundle\0".to_vec();
+        packet.extend([0, 0, 0, 0, 0, 0, 0, 1]); // timetag: immediately
+        for element in elements {
+            packet.extend((element.len() as u32).to_be_bytes());
+            packet.extend(element);
+        }
+        packet
+    }
+
+    #[test]
+    fn message_with_int_float_and_string_args() {
+        assert_eq!(
+            parse_packet(&int_float_string_message()).unwrap(),
+            vec![OscMessage {
+                address: String::from("/test/args"),
+                args: vec![
+                    OscArg::Int(42),
+                    OscArg::Float(0.5),
+                    OscArg::String(String::from("hello"))
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn message_without_type_tags() {
+        let messages = parse_packet(&osc_string("/old/style")).unwrap();
+        assert_eq!(messages[0].address, "/old/style");
+        assert!(messages[0].args.is_empty());
+    }
+
+    #[test]
+    fn bundle_messages_in_order() {
+        let mut second = osc_string("/second");
+        second.extend(osc_string(",i"));
+        second.extend(7i32.to_be_bytes());
+        let nested = bundle(&[second]);
+        let messages = parse_packet(&bundle(&[int_float_string_message(), nested])).unwrap();
+        let addresses: Vec<&str> = messages.iter().map(|m| m.address.as_str()).collect();
+        assert_eq!(addresses, vec!["/test/args", "/second"]);
+        assert_eq!(messages[1].args, vec![OscArg::Int(7)]);
+    }
+
+    #[test]
+    fn unpadded_strings() {
+        // Without padding, a string at the very end of the packet is accepted
+        let mut packet = osc_string("/a/string");
+        packet.extend(osc_string(",s"));
+        packet.extend(b"end\0");
+        packet.extend(b"/x\0");
+        let messages = parse_packet(&packet[..packet.len() - 3]).unwrap();
+        assert_eq!(messages[0].args, vec![OscArg::String(String::from("end"))]);
+
+        let messages = parse_packet(b"/abcde\0").unwrap();
+        assert_eq!(messages[0].address, "/abcde");
+
+        // ...but anything after an unpadded string is misaligned
+        let mut packet = b"/abcd\0".to_vec();
+        packet.extend(osc_string(",i"));
+        packet.extend(1i32.to_be_bytes());
+        assert!(parse_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let message = int_float_string_message();
+        // The address on its own makes a complete message, as does everything
+        // but the padding after the final string (as long as its terminator
+        // is there)
+        let complete = [11, 12, message.len() - 2, message.len() - 1];
+        for length in 0..message.len() {
+            let result = parse_packet(&message[..length]);
+            assert_eq!(
+                result.is_ok(),
+                complete.contains(&length),
+                "length {}",
+                length
+            );
+        }
+
+        let packet = bundle(&[int_float_string_message()]);
+        for length in 0..packet.len() {
+            let result = parse_packet(&packet[..length]);
+            // An empty bundle (just the header and timetag) is valid
+            assert_eq!(result.is_ok(), length == 16, "length {}", length);
+        }
+    }
+
+    #[test]
+    fn unsupported_packets_are_errors() {
+        assert!(parse_packet(&osc_string("no/slash")).is_err());
+        let mut packet = osc_string("/blob");
+        packet.extend(osc_string(",b"));
+        packet.extend(0i32.to_be_bytes());
+        assert!(parse_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn macro_address() {
+        let expect_value = |arg: OscArg, expected: u8| {
+            let Ok(RemoteControlMessage::MacroAnimation(m)) =
+                to_remote_message(&macro_message(arg))
+            else {
+                panic!("should be a macro message");
+            };
+            assert_eq!(m.fixture_labels, Some(vec![String::from("Mover 1")]));
+            assert_eq!(m.macro_label, "dimmer");
+            assert!(m.ms.is_none());
+            let RemoteMacroValue::ControlValue(value) = m.value else {
+                panic!("should be a Control Value");
+            };
+            assert_eq!(value, expected);
+        };
+        expect_value(OscArg::Float(0.5), 128);
+        expect_value(OscArg::Float(2.), 255);
+        expect_value(OscArg::Int(100), 100);
+        expect_value(OscArg::Int(-5), 0);
+        expect_value(OscArg::Int(300), 255);
+
+        assert!(to_remote_message(&macro_message(OscArg::String(String::from("full")))).is_err());
+        let no_value = OscMessage {
+            address: String::from("/macro/Mover 1/dimmer"),
+            args: Vec::new(),
+        };
+        assert!(to_remote_message(&no_value).is_err());
+    }
+
+    #[test]
+    fn unknown_addresses_are_errors() {
+        for address in ["/macro/Mover 1", "/macro/a/b/c", "/scene/Blue", "/"] {
+            let m = OscMessage {
+                address: String::from(address),
+                args: vec![OscArg::Int(1)],
+            };
+            assert!(to_remote_message(&m).is_err(), "{}", address);
+        }
+    }
+}
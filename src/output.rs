@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+/// A way of getting DMX frames out of the controller, e.g. ArtNet or sACN.
+/// `ArtNetInterface` builds every frame (macros, limits, smoothing, rate
+/// limits etc.) and only hands over the finished channel data for each
+/// universe to its output.
+pub trait DmxOutput {
+    /// Send the channel data (without any start code) for a single universe
+    fn send_universe(&mut self, universe: u16, data: &[u8]);
+
+    /// Called after all universes in a frame have been sent, e.g. to send
+    /// any synchronisation packet
+    fn end_frame(&mut self, _universes: &[u16]) {}
+
+    /// False if nothing actually leaves the controller, i.e. a dry run
+    fn is_sending(&self) -> bool {
+        true
+    }
+
+    /// Short description for the GUI, e.g. "sACN multicast"
+    fn describe(&self) -> String;
+}
+
+/// Frames recorded by a `MockOutput`, oldest first: (universe, channel data)
+pub type RecordedFrames = Arc<Mutex<Vec<(u16, Vec<u8>)>>>;
+
+/// An output which sends nothing, but keeps the most recent frames it is
+/// given, e.g. for a dry run, or to check what the controller would output
+/// without a lighting network
+pub struct MockOutput {
+    frames: RecordedFrames,
+    /// How many frames (one per universe) are kept; older ones are dropped
+    max_frames: usize,
+    /// Complete frames (i.e. calls to `end_frame`) seen so far
+    frame_count: u64,
+}
+
+impl MockOutput {
+    /// The output, plus a handle to the frames it records (which remains
+    /// readable once the output has been boxed and handed over)
+    pub fn new(max_frames: usize) -> (Self, RecordedFrames) {
+        let frames: RecordedFrames = Arc::new(Mutex::new(Vec::new()));
+        (
+            MockOutput {
+                frames: frames.clone(),
+                max_frames,
+                frame_count: 0,
+            },
+            frames,
+        )
+    }
+}
+
+impl DmxOutput for MockOutput {
+    fn send_universe(&mut self, universe: u16, data: &[u8]) {
+        let mut frames = self.frames.lock().unwrap();
+        frames.push((universe, data.to_vec()));
+        let excess = frames.len().saturating_sub(self.max_frames);
+        frames.drain(..excess);
+    }
+
+    fn end_frame(&mut self, _universes: &[u16]) {
+        self.frame_count += 1;
+    }
+
+    fn is_sending(&self) -> bool {
+        false
+    }
+
+    fn describe(&self) -> String {
+        format!("{} frames recorded", self.frame_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_latest_frames() {
+        let (mut output, frames) = MockOutput::new(2);
+        output.send_universe(0, &[1, 2]);
+        output.send_universe(1, &[3, 4]);
+        output.send_universe(0, &[5, 6]);
+        assert_eq!(
+            *frames.lock().unwrap(),
+            vec![(1, vec![3, 4]), (0, vec![5, 6])]
+        );
+    }
+
+    #[test]
+    fn counts_complete_frames_not_universes() {
+        let (mut output, _frames) = MockOutput::new(8);
+        for _ in 0..3 {
+            output.send_universe(0, &[0]);
+            output.send_universe(1, &[0]);
+            output.end_frame(&[0, 1]);
+        }
+        assert_eq!(output.describe(), "3 frames recorded");
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::anyhow;
+use indexmap::IndexMap;
 use std::{
-    net::{Ipv4Addr, SocketAddr},
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
 };
 
@@ -8,8 +11,10 @@ use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    artnet::{ArtNetInterface, ArtNetMode},
-    settings::Cli,
+    artnet::{ArtNetInterface, ArtNetMode, ArtNetOutput},
+    output::DmxOutput,
+    sacn::SacnInterface,
+    settings::{Cli, OutputProtocol, DEFAULT_BROADCAST_SRC_PORT, DEFAULT_UNICAST_SRC_PORT},
 };
 
 use super::Project;
@@ -20,43 +25,133 @@ pub enum ArtNetConfigMode {
     Unicast(String, String),
 }
 
+/// Universe => destination address, as stored in a Project or routes file
+pub type ArtNetRoutes = IndexMap<u16, String>;
+
 pub fn get_artnet_interface(
     cli: &Cli,
     project: &Project,
 ) -> Result<ArtNetInterface, anyhow::Error> {
     debug!("get_artnet_interface");
+    let interface = if cli.artnet_dry_run {
+        warn!("CLI artnet dry run flag overrides any other ArtNet settings");
+        ArtNetInterface::new_dry_run(cli.artnet_update_frequency)
+    } else {
+        ArtNetInterface::new(get_output(cli, project)?, cli.artnet_update_frequency)
+    };
+    Ok(interface
+        .with_smoothing(cli.artnet_smoothing)
+        .with_universe_size(cli.universe_size)
+        .with_max_fps(cli.artnet_max_fps))
+}
+
+/// The sACN output, or the ArtNet output (with any routes) for the ArtNet
+/// mode given on the CLI or in the Project
+fn get_output(cli: &Cli, project: &Project) -> Result<Box<dyn DmxOutput>, anyhow::Error> {
+    if cli.protocol == OutputProtocol::Sacn {
+        Ok(Box::new(SacnInterface::new(cli.sacn_priority)?))
+    } else {
+        let output = ArtNetOutput::new(get_artnet_mode(cli, project)?)?
+            .with_routes(get_artnet_routes(cli, project))
+            .with_sync(cli.artnet_sync);
+        Ok(Box::new(output))
+    }
+}
+
+/// Parse the universe routing table, from the CLI-specified file if provided,
+/// otherwise from the Project. Any invalid entries are skipped.
+pub fn get_artnet_routes(cli: &Cli, project: &Project) -> BTreeMap<u16, SocketAddr> {
+    let routes = match &cli.artnet_routes_path {
+        Some(path) => match fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|d| serde_json::from_str::<ArtNetRoutes>(&d).map_err(anyhow::Error::from))
+        {
+            Ok(routes) => {
+                info!("Loaded ArtNet routes from \"{}\"", path);
+                routes
+            }
+            Err(e) => {
+                error!("Failed to load ArtNet routes from \"{}\": {}", path, e);
+                ArtNetRoutes::new()
+            }
+        },
+        None => project.artnet_routes.clone(),
+    };
+
+    let mut parsed = BTreeMap::new();
+    for (universe, address) in routes.iter() {
+        match parse_destination(address, cli.artnet_dst_port) {
+            Some(destination) => {
+                parsed.insert(*universe, destination);
+            }
+            None => error!(
+                "Invalid destination \"{}\" for universe {}; will use default destination",
+                address, universe
+            ),
+        }
+    }
+    parsed
+}
+
+/// Accept either "ip:port" or a plain IP, which gets the default port
+fn parse_destination(address: &str, default_port: u16) -> Option<SocketAddr> {
+    SocketAddr::from_str(address).ok().or_else(|| {
+        IpAddr::from_str(address)
+            .ok()
+            .map(|ip| SocketAddr::from((ip, default_port)))
+    })
+}
+
+/// Broadcast mode, using the ports given on the CLI (if any)
+pub fn broadcast_mode(cli: &Cli) -> ArtNetMode {
+    ArtNetMode::Broadcast(
+        cli.artnet_src_port.unwrap_or(DEFAULT_BROADCAST_SRC_PORT),
+        cli.artnet_dst_port,
+    )
+}
+
+/// Unicast mode between the given addresses, using the ports given on the CLI
+/// (if any)
+pub fn unicast_mode(cli: &Cli, interface: IpAddr, destination: IpAddr) -> ArtNetMode {
+    ArtNetMode::Unicast(
+        SocketAddr::from((
+            interface,
+            cli.artnet_src_port.unwrap_or(DEFAULT_UNICAST_SRC_PORT),
+        )),
+        SocketAddr::from((destination, cli.artnet_dst_port)),
+    )
+}
+
+fn get_artnet_mode(cli: &Cli, project: &Project) -> Result<ArtNetMode, anyhow::Error> {
     if cli.artnet_broadcast {
         warn!("CLI artnetBroadcast flag overrides any Project ArtNet settings");
-        ArtNetInterface::new(ArtNetMode::Broadcast, cli.artnet_update_frequency)
-    } else if cli.unicast_src.is_some() && cli.unicast_dst.is_some() {
+        Ok(broadcast_mode(cli))
+    } else if let (Some(unicast_src), Some(unicast_dst)) = (cli.unicast_src, cli.unicast_dst) {
         warn!("CLI unicastSrc + unicastDst options override any Project ArtNet settings");
-        ArtNetInterface::new(
-            ArtNetMode::Unicast(
-                SocketAddr::from((cli.unicast_src.unwrap(), 6453)),
-                SocketAddr::from((cli.unicast_dst.unwrap(), 6454)),
-            ),
-            cli.artnet_update_frequency,
-        )
+        Ok(unicast_mode(cli, unicast_src, unicast_dst))
     } else {
         debug!("No CLI overrides, attempt to use Project ArtNet config...");
         match &project.artnet_config {
             Some(artnet_mode) => {
                 info!("Using project ArtNet Config {:?}", artnet_mode);
                 match artnet_mode {
-                    ArtNetConfigMode::Broadcast => {
-                        ArtNetInterface::new(ArtNetMode::Broadcast, cli.artnet_update_frequency)
-                    }
+                    ArtNetConfigMode::Broadcast => Ok(broadcast_mode(cli)),
                     ArtNetConfigMode::Unicast(interface_ip, destination_ip) => {
-                        ArtNetInterface::new(
-                            ArtNetMode::Unicast(
-                                SocketAddr::from((Ipv4Addr::from_str(interface_ip).unwrap(), 6453)),
-                                SocketAddr::from((
-                                    Ipv4Addr::from_str(destination_ip).unwrap(),
-                                    6454,
-                                )),
-                            ),
-                            cli.artnet_update_frequency,
-                        )
+                        match (
+                            Ipv4Addr::from_str(interface_ip),
+                            Ipv4Addr::from_str(destination_ip),
+                        ) {
+                            (Ok(interface), Ok(destination)) => Ok(unicast_mode(
+                                cli,
+                                interface.into(),
+                                destination.into(),
+                            )),
+                            _ => Err(anyhow!(
+                                "Invalid unicast address(es) \"{}\" => \"{}\" in Project ArtNet config",
+                                interface_ip,
+                                destination_ip
+                            )),
+                        }
                     }
                 }
             }
@@ -70,10 +165,10 @@ pub fn get_artnet_interface(
     }
 }
 
-impl From<&ArtNetInterface> for ArtNetConfigMode {
-    fn from(value: &ArtNetInterface) -> Self {
-        match value.mode_in_use() {
-            ArtNetMode::Broadcast => ArtNetConfigMode::Broadcast,
+impl From<&ArtNetMode> for ArtNetConfigMode {
+    fn from(value: &ArtNetMode) -> Self {
+        match value {
+            ArtNetMode::Broadcast(..) => ArtNetConfigMode::Broadcast,
             ArtNetMode::Unicast(src, dst) => {
                 ArtNetConfigMode::Unicast(src.ip().to_string(), dst.ip().to_string())
             }
@@ -1,9 +1,18 @@
 use std::cmp::Ordering;
 
+use anyhow::anyhow;
 use egui::Color32;
+use indexmap::IndexMap;
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::animation::Animation;
+use crate::{
+    animation::{Animation, Slew},
+    colour::ColourSpace,
+    settings::DEFAULT_UNIVERSE,
+};
+
+use super::{SceneState, SceneValue};
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -14,13 +23,176 @@ pub struct FixtureInstance {
     /// The exact match for the fixture name as it appears in the fixture config JSON
     pub config_name: String,
     pub offset_channels: u16,
+    /// The universe this fixture is patched on; `offset_channels` is relative to
+    /// the start of this universe
+    #[serde(default)]
+    pub universe: u16,
     #[serde(default)]
     pub mode_index: usize,
+    /// A disabled fixture keeps its place (and addressing) in the Project, but
+    /// outputs nothing (all its channels stay at zero) and ignores macros/scenes
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Named presets of macro values for this fixture alone, e.g. "parked" or
+    /// "warmup": { "preset label": { "macro label": value } }
+    #[serde(default)]
+    pub presets: IndexMap<String, SceneState>,
+    /// Safety limits for channels (numbered from 1, as in the mappings), e.g.
+    /// to keep a moving head's pan/tilt away from scenery: { channel: [min, max] }.
+    /// Output is always clamped to these, whatever set the value.
+    #[serde(default)]
+    pub channel_limits: IndexMap<u16, [u8; 2]>,
+    /// Free text for operators, e.g. "this mover flickers on channel 3"
+    #[serde(default)]
+    pub notes: String,
+    /// Where this fixture is on the stage layout, from (0, 0) at the top left
+    /// to (1, 1) at the bottom right; None until it has been placed
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
     #[serde(skip)]
     /// The actual configuration, once loaded via the path
     pub config: FixtureConfig,
 }
 
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl FixtureInstance {
+    /// Apply the given configuration to this fixture, activating the selected
+    /// mode. Any problems with the mode definition are logged, but not fatal.
+    pub fn set_config(&mut self, config: &FixtureConfig) -> anyhow::Result<()> {
+        let mode = config.modes.get(self.mode_index).ok_or(anyhow!(
+            "Fixture config \"{}\" has no mode with index {}",
+            &config.name,
+            self.mode_index
+        ))?;
+        self.config = config.clone();
+        self.config.active_mode = mode.clone();
+
+        if let Err(errors) = self.config.active_mode.validate() {
+            for e in errors {
+                warn!(
+                    "Fixture \"{}\", mode \"{}\": {}",
+                    &self.label, &self.config.active_mode.name, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Index into the buffer for this fixture's universe, for the given
+    /// channel (numbered from 1) of the fixture. This may lie beyond the end
+    /// of the universe (e.g. a mode that overruns it, or a channel 0 in a bad
+    /// definition), so callers must use checked access, i.e. `get_mut`.
+    pub fn channel_index(&self, channel: u16) -> usize {
+        (usize::from(channel) + usize::from(self.offset_channels)).wrapping_sub(1)
+    }
+
+    /// The fine (low byte) channel paired with the given channel, if it is
+    /// mapped as 16-bit
+    pub fn fine_channel_for(&self, channel: u16) -> Option<u16> {
+        self.config
+            .active_mode
+            .mappings
+            .iter()
+            .find(|m| m.channel == channel)
+            .and_then(|m| m.fine_channel)
+    }
+
+    /// The number of channels occupied by this fixture, in its active mode
+    /// (none, if the selected mode doesn't exist)
+    pub fn footprint(&self) -> u16 {
+        if self.config.modes.get(self.mode_index).is_none() {
+            return 0;
+        }
+        self.config.active_mode.footprint()
+    }
+
+    /// The current values of all macros, e.g. for saving as a preset
+    pub fn current_state(&self) -> SceneState {
+        let mut state = SceneState::new();
+        for m in self.config.active_mode.macros.iter() {
+            let value = match m {
+                FixtureMacro::Control(control_macro) => {
+                    SceneValue::ControlValue(control_macro.current_value)
+                }
+                FixtureMacro::Colour(colour_macro) => {
+                    SceneValue::ColourValue(colour_macro.current_value)
+                }
+                FixtureMacro::Wheel(wheel_macro) => {
+                    SceneValue::Slot(String::from(wheel_macro.current_slot_label()))
+                }
+            };
+            state.insert(String::from(m.label()), value);
+        }
+        state
+    }
+
+    /// The value each macro returns to on reset (see `reset_macros`), in the
+    /// same form as a Scene
+    pub fn home_state(&self) -> SceneState {
+        let mappings = &self.config.active_mode.mappings;
+        let mut state = SceneState::new();
+        for m in self.config.active_mode.macros.iter() {
+            let value = match m {
+                FixtureMacro::Control(control_macro) => SceneValue::ControlValue(
+                    control_macro
+                        .channels
+                        .first()
+                        .and_then(|c| mappings.iter().find(|m| m.channel == *c))
+                        .and_then(|m| m.home)
+                        .unwrap_or(0),
+                ),
+                FixtureMacro::Colour(_) => SceneValue::ColourValue(default_rgb()),
+                FixtureMacro::Wheel(wheel_macro) => SceneValue::Slot(
+                    wheel_macro
+                        .slots
+                        .first()
+                        .map(|s| String::from(&s.label))
+                        .unwrap_or_default(),
+                ),
+            };
+            state.insert(String::from(m.label()), value);
+        }
+        state
+    }
+
+    /// Cancel any animations (or slews) and return every macro to its initial
+    /// value; for Control Macros, this is the home value of its first channel
+    pub fn reset_macros(&mut self) {
+        let mappings = &self.config.active_mode.mappings;
+        for m in self.config.active_mode.macros.iter_mut() {
+            match m {
+                FixtureMacro::Control(control_macro) => {
+                    control_macro.animation = None;
+                    control_macro.slew = None;
+                    control_macro.current_value = control_macro
+                        .channels
+                        .first()
+                        .and_then(|c| mappings.iter().find(|m| m.channel == *c))
+                        .and_then(|m| m.home)
+                        .unwrap_or(0);
+                }
+                FixtureMacro::Colour(colour_macro) => {
+                    colour_macro.animation = None;
+                    colour_macro.kelvin = None;
+                    colour_macro.current_value = default_rgb();
+                }
+                FixtureMacro::Wheel(wheel_macro) => {
+                    wheel_macro.current_value = 0;
+                }
+            }
+        }
+    }
+
+    /// The highest offset which still keeps the whole fixture within a
+    /// universe of the given size
+    pub fn max_offset(&self, universe_size: u16) -> u16 {
+        universe_size.saturating_sub(self.footprint())
+    }
+}
+
 impl PartialEq for FixtureInstance {
     fn eq(&self, other: &Self) -> bool {
         self.label == other.label
@@ -44,13 +216,23 @@ impl Ord for FixtureInstance {
 
 impl From<&FixtureConfig> for FixtureInstance {
     fn from(config: &FixtureConfig) -> Self {
-        FixtureInstance {
+        let mut fixture = FixtureInstance {
             label: format!("My {}", config.name),
             config_name: String::from(&config.name),
             offset_channels: 0,
+            universe: DEFAULT_UNIVERSE,
             mode_index: 0,
+            enabled: true,
+            presets: IndexMap::new(),
+            channel_limits: IndexMap::new(),
+            notes: String::new(),
+            position: None,
             config: config.clone(),
+        };
+        if let Err(e) = fixture.set_config(config) {
+            error!("New fixture has no usable mode: {}", e);
         }
+        fixture
     }
 }
 
@@ -69,15 +251,109 @@ pub struct FixtureConfig {
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct ControlMode {
     pub name: String,
+    /// The number of channels the fixture occupies in this mode, if declared;
+    /// not every channel needs to be mapped
+    #[serde(default)]
+    pub channel_count: Option<u16>,
     pub mappings: Vec<Mapping>,
     pub macros: Vec<FixtureMacro>,
 }
 
+impl ControlMode {
+    /// The number of channels used by this mode, i.e. the declared channel count
+    /// or otherwise the highest mapped channel
+    pub fn footprint(&self) -> u16 {
+        let highest_mapped = self.mappings.iter().map(|m| m.channel).max().unwrap_or(0);
+        self.channel_count
+            .unwrap_or(highest_mapped)
+            .max(highest_mapped)
+    }
+
+    /// Check that the mappings are internally consistent: no channel is mapped
+    /// twice, and every channel is within the declared channel count (or, if
+    /// no count is declared, there are no gaps between mapped channels)
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let mut channels: Vec<u16> = self.mappings.iter().map(|m| m.channel).collect();
+        channels.sort();
+
+        for pair in channels.windows(2) {
+            if pair[0] == pair[1] {
+                errors.push(format!("channel {} is mapped more than once", pair[0]));
+            }
+        }
+        channels.dedup();
+
+        for m in self.mappings.iter() {
+            if let Some(fine_channel) = m.fine_channel {
+                if !channels.contains(&fine_channel) {
+                    errors.push(format!(
+                        "fine channel {} (for channel {}) is not mapped",
+                        fine_channel, m.channel
+                    ));
+                }
+            }
+        }
+
+        if channels.first().is_some_and(|c| *c < 1) {
+            errors.push(String::from("channel numbers must start from 1"));
+        }
+
+        match self.channel_count {
+            Some(count) => {
+                for c in channels.iter().filter(|c| **c > count) {
+                    errors.push(format!(
+                        "channel {} is mapped, but mode declares only {} channels",
+                        c, count
+                    ));
+                }
+            }
+            None => {
+                for pair in channels.windows(2) {
+                    if pair[1] - pair[0] > 1 {
+                        errors.push(format!(
+                            "no mapping for channel(s) {}-{}",
+                            pair[0] + 1,
+                            pair[1] - 1
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// What a channel controls, where that matters to the controller, e.g.
+/// Intensity channels are scaled by the Grand Master
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelRole {
+    Intensity,
+    Colour,
+    Position,
+    #[default]
+    Other,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Mapping {
     pub channel: u16,
+    #[serde(default)]
+    pub role: ChannelRole,
+    /// For 16-bit channels: the (separately mapped) channel carrying the low
+    /// byte, while this one carries the high byte
+    #[serde(default)]
+    pub fine_channel: Option<u16>,
     pub label: String,
     pub notes: Option<String>,
     pub home: Option<u8>,
@@ -94,6 +370,121 @@ pub struct ChannelMacro {
     pub animation: Option<Animation>,
     #[serde(skip)]
     pub global_index: u8,
+    /// Optional maximum rate of change (DMX units per second) for direct
+    /// value changes; if not set, new values are applied immediately
+    #[serde(default)]
+    pub slew_rate: Option<f32>,
+    #[serde(skip)]
+    pub slew: Option<Slew>,
+    /// Optional output range: the logical value (0-255) is rescaled into
+    /// `output_min..=output_max` before being written to the channels
+    #[serde(default)]
+    pub output_min: Option<u8>,
+    #[serde(default)]
+    pub output_max: Option<u8>,
+    /// Additional channels which are each driven by the macro value with their
+    /// own scaling, e.g. raising one channel while lowering another
+    #[serde(default)]
+    pub influences: Vec<ChannelInfluence>,
+    /// Drive 16-bit (coarse/fine) channels, e.g. for smooth pan/tilt: the low
+    /// byte goes to the `fine_channel` of each channel's mapping
+    #[serde(default)]
+    pub is_16bit: bool,
+    /// Low byte of the value, if 16-bit; `current_value` is the high byte
+    #[serde(skip)]
+    pub fine_value: u8,
+}
+
+/// A channel driven by a Control Macro, as `offset + scale * value` (clamped
+/// to 0-255), where value is the macro's logical value 0-255
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelInfluence {
+    pub channel: u16,
+    pub scale: f32,
+    #[serde(default)]
+    pub offset: f32,
+}
+
+impl ChannelInfluence {
+    pub fn output_value(&self, macro_value: u8) -> u8 {
+        (self.offset + self.scale * macro_value as f32)
+            .round()
+            .clamp(0., 255.) as u8
+    }
+}
+
+impl ChannelMacro {
+    /// A plain macro for the given channels, e.g. when importing a fixture
+    /// definition from another format
+    pub fn new(label: &str, channels: Vec<u16>) -> Self {
+        ChannelMacro {
+            label: String::from(label),
+            channels,
+            current_value: 0,
+            animation: None,
+            global_index: 0,
+            slew_rate: None,
+            slew: None,
+            output_min: None,
+            output_max: None,
+            influences: Vec::new(),
+            is_16bit: false,
+            fine_value: 0,
+        }
+    }
+
+    /// The DMX value to actually output, after remapping the logical value into
+    /// the output range (if any)
+    pub fn output_value(&self) -> u8 {
+        if self.output_min.is_none() && self.output_max.is_none() {
+            return self.current_value;
+        }
+        let min = self.output_min.unwrap_or(0) as f32;
+        let max = self.output_max.unwrap_or(255) as f32;
+        (min + (max - min) * self.current_value as f32 / 255.0).round() as u8
+    }
+
+    /// The full value, for 16-bit macros (high byte from `current_value`)
+    pub fn value_16bit(&self) -> u16 {
+        u16::from_be_bytes([self.current_value, self.fine_value])
+    }
+
+    pub fn set_value_16bit(&mut self, value: u16) {
+        [self.current_value, self.fine_value] = value.to_be_bytes();
+    }
+
+    /// Like `output_value`, but with full 16-bit resolution; the output range
+    /// (if any) covers whole coarse steps
+    pub fn output_value_16bit(&self) -> u16 {
+        if self.output_min.is_none() && self.output_max.is_none() {
+            return self.value_16bit();
+        }
+        let min = u16::from_be_bytes([self.output_min.unwrap_or(0), 0]) as f32;
+        let max = u16::from_be_bytes([self.output_max.unwrap_or(255), 255]) as f32;
+        (min + (max - min) * self.value_16bit() as f32 / 65535.0).round() as u16
+    }
+
+    /// The value right now, sampling any animation in progress (which may have
+    /// moved on since `current_value` was last updated)
+    pub fn live_value(&mut self) -> u8 {
+        match &mut self.animation {
+            Some(animation) => (animation.get_value() * 255.0) as u8,
+            None => self.current_value,
+        }
+    }
+
+    /// Go to the given value, either immediately or (if a slew rate is set)
+    /// ramping towards it over the following updates
+    pub fn set_target_value(&mut self, value: u8) {
+        self.fine_value = 0;
+        match self.slew_rate {
+            Some(_) => self.slew = Some(Slew::new(self.current_value, value)),
+            None => {
+                self.slew = None;
+                self.current_value = value;
+            }
+        }
+    }
 }
 
 // Cloning an Animation is tricky, and we don't need it anyway
@@ -105,6 +496,13 @@ impl Clone for ChannelMacro {
             current_value: self.current_value,
             animation: None, // Just ignore
             global_index: self.global_index,
+            slew_rate: self.slew_rate,
+            slew: None,
+            output_min: self.output_min,
+            output_max: self.output_max,
+            influences: self.influences.clone(),
+            is_16bit: self.is_16bit,
+            fine_value: self.fine_value,
         }
     }
 }
@@ -144,6 +542,37 @@ pub struct ColourMacro {
     pub current_value: Color32,
     #[serde(skip)]
     pub animation: Option<(Animation, Color32, Color32)>,
+    /// How the animation (if any) interpolates between its start and end
+    #[serde(skip)]
+    pub colour_space: ColourSpace,
+    /// If set, the colour is being controlled as a colour temperature (Kelvin)
+    #[serde(skip)]
+    pub kelvin: Option<u16>,
+}
+
+impl ColourMacro {
+    pub fn new(label: &str, channels: ChannelList) -> Self {
+        ColourMacro {
+            label: String::from(label),
+            channels,
+            current_value: default_rgb(),
+            animation: None,
+            colour_space: ColourSpace::default(),
+            kelvin: None,
+        }
+    }
+
+    /// The colour right now, sampling any animation in progress (which may have
+    /// moved on since `current_value` was last updated)
+    pub fn live_value(&mut self) -> Color32 {
+        match &mut self.animation {
+            Some((animation, start_colour, end_colour)) => {
+                self.colour_space
+                    .interpolate(start_colour, end_colour, animation.get_value())
+            }
+            None => self.current_value,
+        }
+    }
 }
 
 impl Clone for ColourMacro {
@@ -153,15 +582,75 @@ impl Clone for ColourMacro {
             channels: self.channels.clone(),
             current_value: self.current_value,
             animation: None,
+            colour_space: ColourSpace::default(),
+            kelvin: self.kelvin,
         }
     }
 }
 
+/// Selects one of a number of named slots, e.g. on a gobo or colour wheel,
+/// where each slot corresponds to a DMX value or range of values
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WheelMacro {
+    pub label: String,
+    pub channels: Vec<u16>,
+    pub slots: Vec<RangeDescription>,
+    /// Index of the currently-selected slot
+    #[serde(skip)]
+    pub current_value: usize,
+}
+
+impl WheelMacro {
+    /// The DMX value to output for the currently-selected slot, i.e. the middle
+    /// of its range
+    pub fn dmx_value(&self) -> u8 {
+        match self.slots.get(self.current_value) {
+            Some(slot) => {
+                let [start, end] = slot.range;
+                ((start as u16 + end as u16) / 2) as u8
+            }
+            None => 0,
+        }
+    }
+
+    pub fn select_slot(&mut self, slot_label: &str) -> bool {
+        match self
+            .slots
+            .iter()
+            .position(|s| s.label.eq_ignore_ascii_case(slot_label))
+        {
+            Some(index) => {
+                self.current_value = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn current_slot_label(&self) -> &str {
+        self.slots
+            .get(self.current_value)
+            .map(|s| s.label.as_str())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum FixtureMacro {
     Control(ChannelMacro),
     Colour(ColourMacro),
+    Wheel(WheelMacro),
+}
+
+impl FixtureMacro {
+    pub fn label(&self) -> &str {
+        match self {
+            FixtureMacro::Control(m) => &m.label,
+            FixtureMacro::Colour(m) => &m.label,
+            FixtureMacro::Wheel(m) => &m.label,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -169,3 +658,79 @@ pub struct RangeDescription {
     pub range: [u8; 2],
     pub label: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::settings::CHANNELS_PER_UNIVERSE;
+
+    fn fixture_at(universe: u16, offset_channels: u16) -> FixtureInstance {
+        serde_json::from_value(json!({
+            "label": "test",
+            "configName": "test",
+            "universe": universe,
+            "offsetChannels": offset_channels
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn channel_index_at_start_of_universe() {
+        let fixture = fixture_at(0, 0);
+        assert_eq!(fixture.channel_index(1), 0);
+        assert_eq!(fixture.channel_index(16), 15);
+    }
+
+    #[test]
+    fn channel_index_with_offset_1() {
+        let fixture = fixture_at(0, 1);
+        assert_eq!(fixture.channel_index(1), 1);
+        assert_eq!(fixture.channel_index(2), 2);
+    }
+
+    #[test]
+    fn channel_index_is_relative_to_own_universe() {
+        for universe in [0, 1, 2, 32767] {
+            assert_eq!(fixture_at(universe, 1).channel_index(1), 1);
+        }
+    }
+
+    #[test]
+    fn channel_index_at_end_of_universe() {
+        // Starting at channel 512: only its first channel fits
+        let fixture = fixture_at(1, 511);
+        assert_eq!(fixture.channel_index(1), 511);
+        assert_eq!(fixture.channel_index(2), CHANNELS_PER_UNIVERSE as usize);
+    }
+
+    #[test]
+    fn channel_index_with_offset_512() {
+        // Entirely past the end of the universe
+        let fixture = fixture_at(1, 512);
+        assert_eq!(fixture.channel_index(1), CHANNELS_PER_UNIVERSE as usize);
+    }
+
+    #[test]
+    fn channel_index_crossing_universe_boundary() {
+        // Indexes run on past the end, rather than wrapping into the next
+        // universe, so that checked access skips them
+        let fixture = fixture_at(1, 510);
+        let indexes: Vec<usize> = (1..=4).map(|c| fixture.channel_index(c)).collect();
+        assert_eq!(indexes, vec![510, 511, 512, 513]);
+        let universe = [0u8; CHANNELS_PER_UNIVERSE as usize];
+        assert_eq!(
+            indexes
+                .iter()
+                .filter(|i| universe.get(**i).is_some())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn channel_index_of_channel_0_is_out_of_range() {
+        assert!(fixture_at(0, 0).channel_index(0) >= CHANNELS_PER_UNIVERSE as usize);
+    }
+}
@@ -0,0 +1,491 @@
+use std::{fs, io::Read};
+
+use anyhow::anyhow;
+use flate2::read::DeflateDecoder;
+use log::{debug, info, warn};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::fixture::{
+    ChannelMacro, ChannelRole, ControlMode, FixtureConfig, FixtureMacro, Mapping, RangeDescription,
+};
+
+/// Import a fixture from a GDTF file (a zip archive containing
+/// `description.xml`): each DMX mode becomes a ControlMode, with a mapping for
+/// every channel (coarse and fine, for 16-bit channels) and a Control Macro
+/// for each channel function's attribute
+pub fn load_gdtf(path: &str) -> anyhow::Result<FixtureConfig> {
+    let archive = fs::read(path)?;
+    gdtf_from_archive(&archive, path)
+}
+
+/// As `load_gdtf`, from the archive itself; the path is only for messages
+fn gdtf_from_archive(archive: &[u8], path: &str) -> anyhow::Result<FixtureConfig> {
+    let xml = String::from_utf8(read_zip_entry(archive, "description.xml")?)?;
+    config_from_description(&xml, path)
+}
+
+/// Parse the description, keeping only the modes which are usable
+fn config_from_description(xml: &str, path: &str) -> anyhow::Result<FixtureConfig> {
+    let mut config = parse_description(xml)?;
+
+    config.modes.retain(|mode| {
+        if mode.mappings.is_empty() {
+            warn!(
+                "Skipping GDTF mode \"{}\" of \"{}\": no DMX channels",
+                &mode.name, &config.name
+            );
+            return false;
+        }
+        match mode.validate() {
+            Ok(()) => true,
+            Err(errors) => {
+                warn!(
+                    "Skipping GDTF mode \"{}\" of \"{}\": {}",
+                    &mode.name,
+                    &config.name,
+                    errors.join("; ")
+                );
+                false
+            }
+        }
+    });
+    if config.modes.is_empty() {
+        return Err(anyhow!("GDTF file \"{}\" has no usable DMX modes", path));
+    }
+    info!(
+        "Imported fixture \"{}\" with {} modes from GDTF \"{}\"",
+        &config.name,
+        config.modes.len(),
+        path
+    );
+    Ok(config)
+}
+
+fn read_u16(buff: &[u8], offset: usize) -> anyhow::Result<u16> {
+    buff.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("Zip archive truncated"))
+}
+
+fn read_u32(buff: &[u8], offset: usize) -> anyhow::Result<u32> {
+    buff.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("Zip archive truncated"))
+}
+
+/// Extract a single (stored or deflated) file from a zip archive, found via
+/// the central directory. Zip64 and encryption are not supported.
+fn read_zip_entry(archive: &[u8], name: &str) -> anyhow::Result<Vec<u8>> {
+    // The end of central directory record is at least 22 bytes from the end,
+    // followed by an optional comment
+    let end_of_directory = (0..archive.len().saturating_sub(21))
+        .rev()
+        .find(|i| archive[*i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or_else(|| anyhow!("Not a zip archive"))?;
+    let entry_count = read_u16(archive, end_of_directory + 10)?;
+    let mut offset = read_u32(archive, end_of_directory + 16)? as usize;
+
+    for _ in 0..entry_count {
+        if read_u32(archive, offset)? != 0x02014b50 {
+            return Err(anyhow!("Invalid zip central directory"));
+        }
+        let method = read_u16(archive, offset + 10)?;
+        let compressed_size = read_u32(archive, offset + 20)? as usize;
+        let name_length = read_u16(archive, offset + 28)? as usize;
+        let extra_length = read_u16(archive, offset + 30)? as usize;
+        let comment_length = read_u16(archive, offset + 32)? as usize;
+        let local_header = read_u32(archive, offset + 42)? as usize;
+        let entry_name = archive
+            .get(offset + 46..offset + 46 + name_length)
+            .ok_or_else(|| anyhow!("Zip archive truncated"))?;
+        offset += 46 + name_length + extra_length + comment_length;
+
+        if entry_name != name.as_bytes() {
+            continue;
+        }
+
+        if read_u32(archive, local_header)? != 0x04034b50 {
+            return Err(anyhow!("Invalid zip header for \"{}\"", name));
+        }
+        let start = local_header
+            + 30
+            + read_u16(archive, local_header + 26)? as usize
+            + read_u16(archive, local_header + 28)? as usize;
+        let data = archive
+            .get(start..start + compressed_size)
+            .ok_or_else(|| anyhow!("Zip archive truncated"))?;
+        return match method {
+            0 => Ok(data.to_vec()),
+            8 => {
+                let mut contents = Vec::new();
+                DeflateDecoder::new(data).read_to_end(&mut contents)?;
+                Ok(contents)
+            }
+            _ => Err(anyhow!(
+                "Unsupported zip compression method {} for \"{}\"",
+                method,
+                name
+            )),
+        };
+    }
+
+    Err(anyhow!("No \"{}\" in zip archive", name))
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    match e.try_get_attribute(name)? {
+        Some(a) => Ok(Some(a.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// The coarse (highest) byte of a GDTF DMX value, which is written as
+/// "value/bytes", e.g. "32768/2" is 128; older files may leave out the bytes
+fn coarse_value(s: &str) -> Option<u8> {
+    let (value, bytes) = match s.split_once('/') {
+        Some((value, bytes)) => (value, bytes.parse::<u32>().ok()?),
+        None => (s, 1),
+    };
+    let value = value.parse::<u32>().ok()?;
+    let shift = 8 * bytes.clamp(1, 4).saturating_sub(1);
+    u8::try_from(value >> shift).ok()
+}
+
+/// Like `coarse_value`, but the next byte down (the fine byte of a 16-bit value)
+fn fine_value(s: &str) -> Option<u8> {
+    let (value, bytes) = s.split_once('/')?;
+    let bytes = bytes.parse::<u32>().ok()?;
+    if bytes < 2 {
+        return Some(0);
+    }
+    let value = value.parse::<u32>().ok()?;
+    Some((value >> (8 * bytes.clamp(2, 4).saturating_sub(2))) as u8)
+}
+
+fn role_for(attribute: &str) -> ChannelRole {
+    match attribute {
+        "Dimmer" => ChannelRole::Intensity,
+        "Pan" | "Tilt" => ChannelRole::Position,
+        a if a.starts_with("Color") => ChannelRole::Colour,
+        _ => ChannelRole::Other,
+    }
+}
+
+/// A DMXChannel, as it is read from the description
+#[derive(Default)]
+struct GdtfChannel {
+    /// Channel numbers (within the mode) for each byte, coarse first
+    offsets: Vec<u16>,
+    default: Option<String>,
+    geometry: String,
+    attribute: Option<String>,
+    /// Name and start value of each channel function
+    functions: Vec<(String, String)>,
+}
+
+impl GdtfChannel {
+    fn add_to_mode(self, mode: &mut ControlMode) {
+        let Some(coarse) = self.offsets.first().copied() else {
+            return; // virtual channel, no DMX
+        };
+        let attribute = self.attribute.unwrap_or_else(|| String::from("Unknown"));
+        let mut label = attribute.clone();
+        let is_taken = |label: &str| mode.macros.iter().any(|m| m.label().eq(label));
+        if is_taken(&label) {
+            label = format!("{} {}", &self.geometry, &attribute);
+        }
+        if is_taken(&label) {
+            label = format!("{} (channel {})", &label, coarse);
+        }
+
+        let role = role_for(&attribute);
+        let fine = self.offsets.get(1).copied();
+        let ranges: Vec<RangeDescription> = self
+            .functions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (name, from))| {
+                let start = coarse_value(from)?;
+                let end = match self.functions.get(i + 1) {
+                    Some((_, next)) => coarse_value(next)?.checked_sub(1)?,
+                    None => 255,
+                };
+                (end >= start).then(|| RangeDescription {
+                    range: [start, end],
+                    label: String::from(name),
+                })
+            })
+            .collect();
+
+        mode.mappings.push(Mapping {
+            channel: coarse,
+            role,
+            fine_channel: fine,
+            label: if fine.is_some() {
+                format!("{} coarse", &label)
+            } else {
+                String::from(&label)
+            },
+            notes: Some(format!("GDTF attribute {}", &attribute)),
+            home: self.default.as_deref().and_then(coarse_value),
+            ranges: (ranges.len() > 1).then_some(ranges),
+        });
+        for (i, channel) in self.offsets.iter().enumerate().skip(1) {
+            mode.mappings.push(Mapping {
+                channel: *channel,
+                role,
+                fine_channel: None,
+                label: if i == 1 {
+                    format!("{} fine", &label)
+                } else {
+                    format!("{} fine {}", &label, i)
+                },
+                notes: Some(format!("GDTF attribute {}", &attribute)),
+                home: if i == 1 {
+                    self.default.as_deref().and_then(fine_value)
+                } else {
+                    None
+                },
+                ranges: None,
+            });
+        }
+
+        let mut control_macro = ChannelMacro::new(&label, vec![coarse]);
+        control_macro.is_16bit = fine.is_some();
+        mode.macros.push(FixtureMacro::Control(control_macro));
+    }
+}
+
+/// Read the fixture type and its DMX modes from a GDTF `description.xml`.
+/// Only the first DMX break is used; channels on other breaks are skipped.
+fn parse_description(xml: &str) -> anyhow::Result<FixtureConfig> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut config = FixtureConfig::default();
+    let mut channel: Option<GdtfChannel> = None;
+
+    loop {
+        let (e, is_empty) = match reader.read_event()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                if e.name().as_ref() == b"DMXChannel" {
+                    if let (Some(c), Some(mode)) = (channel.take(), config.modes.last_mut()) {
+                        c.add_to_mode(mode);
+                    }
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        match e.name().as_ref() {
+            b"FixtureType" => {
+                let name = attribute(&e, "Name")?.unwrap_or_default();
+                config.name = match attribute(&e, "Manufacturer")? {
+                    Some(manufacturer) => format!("{} {}", manufacturer, name),
+                    None => name,
+                };
+            }
+            b"DMXMode" => config.modes.push(ControlMode {
+                name: attribute(&e, "Name")?.unwrap_or_default(),
+                channel_count: None,
+                mappings: Vec::new(),
+                macros: Vec::new(),
+            }),
+            b"DMXChannel" => {
+                let dmx_break = attribute(&e, "DMXBreak")?;
+                let offsets = attribute(&e, "Offset")?.unwrap_or_default();
+                let offsets: Vec<u16> = offsets
+                    .split(',')
+                    .filter_map(|o| o.trim().parse().ok())
+                    .collect();
+                let c = GdtfChannel {
+                    offsets,
+                    default: attribute(&e, "Default")?,
+                    geometry: attribute(&e, "Geometry")?.unwrap_or_default(),
+                    ..Default::default()
+                };
+                match dmx_break.as_deref() {
+                    None | Some("1") | Some("Overwrite") => {
+                        if is_empty {
+                            if let Some(mode) = config.modes.last_mut() {
+                                c.add_to_mode(mode);
+                            }
+                        } else {
+                            channel = Some(c);
+                        }
+                    }
+                    Some(other) => {
+                        debug!("Skip GDTF channel on DMX break {}", other);
+                        channel = None;
+                    }
+                }
+            }
+            b"LogicalChannel" => {
+                if let Some(c) = channel.as_mut().filter(|c| c.attribute.is_none()) {
+                    c.attribute = attribute(&e, "Attribute")?;
+                }
+            }
+            b"ChannelFunction" => {
+                if let Some(c) = channel.as_mut() {
+                    if c.attribute.is_none() {
+                        c.attribute = attribute(&e, "Attribute")?;
+                    }
+                    if c.default.is_none() {
+                        c.default = attribute(&e, "Default")?;
+                    }
+                    c.functions.push((
+                        attribute(&e, "Name")?.unwrap_or_default(),
+                        attribute(&e, "DMXFrom")?.unwrap_or_else(|| String::from("0")),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Declare the footprint, since not every channel (e.g. on other DMX
+    // breaks) is necessarily mapped
+    for mode in config.modes.iter_mut() {
+        mode.channel_count = mode.mappings.iter().map(|m| m.channel).max();
+    }
+
+    if config.name.is_empty() {
+        return Err(anyhow!("GDTF description has no FixtureType name"));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `description.xml` inside both test archives
+    const DESCRIPTION: &str = include_str!("../../tests/data/test_par_description.xml");
+    const STORED_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/test_par_stored.gdtf"
+    );
+    const DEFLATED_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/test_par_deflated.gdtf"
+    );
+    const NO_DESCRIPTION_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/no_description.gdtf"
+    );
+
+    fn mapping(mode: &ControlMode, channel: u16) -> &Mapping {
+        mode.mappings.iter().find(|m| m.channel == channel).unwrap()
+    }
+
+    #[test]
+    fn stored_and_deflated_entries() {
+        for path in [STORED_PATH, DEFLATED_PATH] {
+            let archive = fs::read(path).unwrap();
+            let xml = read_zip_entry(&archive, "description.xml").unwrap();
+            assert_eq!(String::from_utf8(xml).unwrap(), DESCRIPTION, "{}", path);
+        }
+    }
+
+    #[test]
+    fn load_test_par() {
+        let config = load_gdtf(DEFLATED_PATH).unwrap();
+        assert_eq!(config.name, "Acme Test Par");
+        // The mode with no channels is skipped
+        assert_eq!(config.modes.len(), 1);
+        let mode = &config.modes[0];
+        assert_eq!(mode.name, "Standard");
+        // Channels on DMX break 2 and virtual channels are not mapped
+        assert_eq!(mode.footprint(), 4);
+        assert_eq!(mode.mappings.len(), 4);
+
+        let dimmer = mapping(mode, 1);
+        assert_eq!(dimmer.label, "Dimmer coarse");
+        assert_eq!(dimmer.role, ChannelRole::Intensity);
+        assert_eq!(dimmer.fine_channel, Some(2));
+        assert_eq!(dimmer.home, Some(128)); // "32896/2"
+        let dimmer_fine = mapping(mode, 2);
+        assert_eq!(dimmer_fine.label, "Dimmer fine");
+        assert_eq!(dimmer_fine.role, ChannelRole::Intensity);
+        assert_eq!(dimmer_fine.home, Some(128));
+
+        assert_eq!(mapping(mode, 3).role, ChannelRole::Colour);
+
+        let shutter = mapping(mode, 4);
+        assert_eq!(shutter.home, Some(32)); // from the first channel function
+        let ranges: Vec<([u8; 2], &str)> = shutter
+            .ranges
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|r| (r.range, r.label.as_str()))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                ([0, 31], "Closed"),
+                ([32, 127], "Open"),
+                ([128, 255], "Strobe")
+            ]
+        );
+
+        let labels: Vec<&str> = mode.macros.iter().map(|m| m.label()).collect();
+        assert_eq!(labels, vec!["Dimmer", "ColorAdd_R", "Shutter1"]);
+        let FixtureMacro::Control(dimmer_macro) = &mode.macros[0] else {
+            panic!("Dimmer should be a Control Macro");
+        };
+        assert!(dimmer_macro.is_16bit);
+    }
+
+    #[test]
+    fn missing_description_is_an_error() {
+        let Err(error) = load_gdtf(NO_DESCRIPTION_PATH) else {
+            panic!("should fail without a description");
+        };
+        assert!(error.to_string().contains("description.xml"), "{}", error);
+    }
+
+    #[test]
+    fn truncated_archive_is_an_error() {
+        for path in [STORED_PATH, DEFLATED_PATH] {
+            let archive = fs::read(path).unwrap();
+            for length in 0..archive.len() {
+                assert!(
+                    gdtf_from_archive(&archive[..length], path).is_err(),
+                    "{} truncated to {} bytes",
+                    path,
+                    length
+                );
+                // Also missing the start, so offsets point past the data
+                assert!(gdtf_from_archive(&archive[archive.len() - length..], path).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn not_a_zip_is_an_error() {
+        assert!(gdtf_from_archive(DESCRIPTION.as_bytes(), "description.xml").is_err());
+        assert!(gdtf_from_archive(&[], "empty.gdtf").is_err());
+    }
+
+    #[test]
+    fn mode_without_channels_is_an_error() {
+        let xml = r#"<GDTF><FixtureType Name="Empty" Manufacturer="Acme">
+            <DMXModes><DMXMode Name="Nothing"><DMXChannels/></DMXMode></DMXModes>
+        </FixtureType></GDTF>"#;
+        assert!(config_from_description(xml, "empty.gdtf").is_err());
+    }
+
+    #[test]
+    fn invalid_description_is_an_error() {
+        assert!(config_from_description("<GDTF><FixtureType", "broken.gdtf").is_err());
+        // No FixtureType name
+        assert!(config_from_description("<GDTF></GDTF>", "unnamed.gdtf").is_err());
+    }
+}
@@ -7,6 +7,46 @@ pub struct MidiConfig {
     pub controller_start: u8,
     /// Which note count as the first, i.e. fixture index 0
     pub note_start: u8,
+    /// Controllers bound to specific macros via MIDI Learn; these take
+    /// precedence over the `controller_start` offset
+    #[serde(default)]
+    pub bindings: Vec<MidiBinding>,
+}
+
+/// A controller number bound to a single macro on a single fixture
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MidiBinding {
+    pub controller: u8,
+    pub fixture_label: String,
+    pub macro_label: String,
+}
+
+impl MidiConfig {
+    /// Bind the controller to the given macro, replacing any previous binding
+    /// for the same controller
+    pub fn bind(&mut self, controller: u8, fixture_label: &str, macro_label: &str) {
+        self.bindings.retain(|b| b.controller != controller);
+        self.bindings.push(MidiBinding {
+            controller,
+            fixture_label: String::from(fixture_label),
+            macro_label: String::from(macro_label),
+        });
+    }
+
+    pub fn unbind_macro(&mut self, fixture_label: &str, macro_label: &str) {
+        self.bindings
+            .retain(|b| !(b.fixture_label == fixture_label && b.macro_label == macro_label));
+    }
+
+    /// Controllers bound to the given macro, if any
+    pub fn controllers_for(&self, fixture_label: &str, macro_label: &str) -> Vec<u8> {
+        self.bindings
+            .iter()
+            .filter(|b| b.fixture_label == fixture_label && b.macro_label == macro_label)
+            .map(|b| b.controller)
+            .collect()
+    }
 }
 
 impl Default for MidiConfig {
@@ -14,6 +54,7 @@ impl Default for MidiConfig {
         MidiConfig {
             controller_start: 48,
             note_start: 49,
+            bindings: Vec::new(),
         }
     }
 }
@@ -1,44 +1,201 @@
-use std::fs;
+use std::{collections::BTreeMap, fmt, fs, path::Path};
 
+use anyhow::anyhow;
 use egui::Color32;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::project::fixture::{FixtureConfig, FixtureMacro};
+use crate::{
+    animation::Easing,
+    project::fixture::{FixtureConfig, FixtureMacro},
+    settings::{IMPORTED_FIXTURES_DIR, MAX_UNIVERSE},
+};
 
-use self::artnetconfig::ArtNetConfigMode;
+use self::artnetconfig::{ArtNetConfigMode, ArtNetRoutes};
 use self::fixture::FixtureInstance;
 use self::midiconfig::MidiConfig;
+use self::pixelmap::PixelMap;
+
+/// Version of the Project file format written by this build; older files are
+/// upgraded on load (see `migrate`)
+pub const PROJECT_VERSION: u32 = 2;
 
 pub mod artnetconfig;
 pub mod fixture;
+pub mod gdtf;
 pub mod midiconfig;
+pub mod ofl;
+pub mod pixelmap;
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Project {
+    /// Files from before versioning count as version 1
+    #[serde(default = "legacy_version")]
+    pub version: u32,
     pub fixtures: Vec<FixtureInstance>,
     pub scenes: Vec<Scene>,
     #[serde(default)]
     pub midi_config: MidiConfig,
     pub artnet_config: Option<ArtNetConfigMode>,
+    /// Universe => destination node address (IP, optionally with port), for
+    /// universes that should not go to the default ArtNet destination
+    #[serde(default)]
+    pub artnet_routes: ArtNetRoutes,
+    /// LED strips/matrices, each driven by a single effect
+    #[serde(default)]
+    pub pixel_maps: Vec<PixelMap>,
+    /// Named colours, for quickly setting Colour Macros by name
+    #[serde(default = "default_palette")]
+    pub palette: Palette,
+    /// Sequences of Scenes, for running a show back-to-back
+    #[serde(default)]
+    pub cue_lists: Vec<CueList>,
+    /// Named sets of fixtures, e.g. "backlights"; a group label can be used
+    /// wherever remote messages take a list of fixture labels
+    #[serde(default)]
+    pub groups: Vec<FixtureGroup>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureGroup {
+    pub label: String,
+    pub fixture_labels: Vec<String>,
+}
+
+/// An ordered list of Scenes, played one after another
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CueList {
+    pub label: String,
+    pub cues: Vec<Cue>,
+    /// Go back to the first cue after the last, instead of stopping
+    #[serde(default)]
+    pub looping: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Cue {
+    pub scene_label: String,
+    /// Transition into the Scene; 0 means immediately
+    pub fade_ms: u64,
+    /// Time to stay on the Scene after the fade, before the next cue; 0 means
+    /// wait for a manual GO
+    pub hold_ms: u64,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// Upgrade Project JSON from an older file format version to the current
+/// one, so that it can be parsed: each step fills in the fields that its
+/// version added, so that the result is a complete file of the current
+/// version (serde defaults are only a fallback).
+fn migrate(mut value: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(legacy_version() as u64) as u32;
+    if version > PROJECT_VERSION {
+        warn!(
+            "Project file version {} is newer than this build supports ({}); some settings may be ignored",
+            version, PROJECT_VERSION
+        );
+        return Ok(value);
+    }
+    let object = value
+        .as_object_mut()
+        .ok_or(anyhow!("Project file is not a JSON object"))?;
+    if version < 2 {
+        info!("Migrate Project file from version 1");
+        // Fields added in version 2, starting out empty (or, for the
+        // palette, with the default colours)
+        let added = [
+            ("palette", serde_json::to_value(default_palette())?),
+            ("groups", serde_json::Value::Array(Vec::new())),
+            ("cueLists", serde_json::Value::Array(Vec::new())),
+            ("pixelMaps", serde_json::Value::Array(Vec::new())),
+            (
+                "artnetRoutes",
+                serde_json::Value::Object(serde_json::Map::new()),
+            ),
+        ];
+        for (key, value) in added {
+            object.entry(key).or_insert(value);
+        }
+    }
+    object.insert(String::from("version"), PROJECT_VERSION.into());
+    Ok(value)
+}
+
+/// { "colour name": colour }
+pub type Palette = IndexMap<String, Color32>;
+
+pub fn default_palette() -> Palette {
+    IndexMap::from([
+        (String::from("red"), Color32::from_rgb(255, 0, 0)),
+        (String::from("amber"), Color32::from_rgb(255, 126, 0)),
+        (String::from("yellow"), Color32::from_rgb(255, 255, 0)),
+        (String::from("green"), Color32::from_rgb(0, 255, 0)),
+        (String::from("cyan"), Color32::from_rgb(0, 255, 255)),
+        (String::from("blue"), Color32::from_rgb(0, 0, 255)),
+        (String::from("magenta"), Color32::from_rgb(255, 0, 255)),
+        (String::from("white"), Color32::from_rgb(255, 255, 255)),
+    ])
+}
+
+/// Look up a colour in the palette by name (case-insensitive)
+pub fn palette_colour(palette: &Palette, name: &str) -> Option<Color32> {
+    palette
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, colour)| *colour)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum SceneValue {
     ControlValue(u8),
     ColourValue(Color32),
+    /// The label of the selected slot, for a Wheel Macro
+    Slot(String),
+}
+
+impl fmt::Display for SceneValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneValue::ControlValue(value) => write!(f, "{}", value),
+            SceneValue::ColourValue(colour) => {
+                let [r, g, b, a] = colour.to_array();
+                write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+            }
+            SceneValue::Slot(slot_label) => write!(f, "{}", slot_label),
+        }
+    }
 }
 
 /// { "macro label": value }
 pub type SceneState = IndexMap<String, SceneValue>;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Scene {
     pub label: String,
     /// { "fixture instance label": { "macro label": value } }
     pub state: IndexMap<String, SceneState>,
+    /// Easing curve used when transitioning into this Scene over time
+    #[serde(default)]
+    pub easing: Easing,
+    /// Labels of fixture instances that this Scene should never affect, even
+    /// if they were captured in the state
+    #[serde(default)]
+    pub excluded_fixtures: IndexSet<String>,
+    /// Free text for operators, e.g. "scene for the ballad"
+    #[serde(default)]
+    pub notes: String,
     #[serde(skip)]
     pub is_editing: bool,
     #[serde(skip)]
@@ -47,13 +204,181 @@ pub struct Scene {
     pub next_transition: f32,
 }
 
+/// A reference in a Scene which no longer matches the fixtures in the Project,
+/// e.g. after a fixture was renamed or its mode was changed. These parts of
+/// the Scene are skipped when it is applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneIssue {
+    MissingFixture {
+        scene_label: String,
+        fixture_label: String,
+    },
+    MissingMacro {
+        scene_label: String,
+        fixture_label: String,
+        macro_label: String,
+    },
+    /// The macro exists, but the value stored in the Scene is the wrong type
+    /// for it, e.g. a colour for a Control Macro
+    MismatchedValue {
+        scene_label: String,
+        fixture_label: String,
+        macro_label: String,
+    },
+}
+
+impl SceneIssue {
+    pub fn scene_label(&self) -> &str {
+        match self {
+            SceneIssue::MissingFixture { scene_label, .. }
+            | SceneIssue::MissingMacro { scene_label, .. }
+            | SceneIssue::MismatchedValue { scene_label, .. } => scene_label,
+        }
+    }
+}
+
+impl fmt::Display for SceneIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneIssue::MissingFixture {
+                scene_label,
+                fixture_label,
+            } => write!(
+                f,
+                "Scene \"{}\": no fixture \"{}\"",
+                scene_label, fixture_label
+            ),
+            SceneIssue::MissingMacro {
+                scene_label,
+                fixture_label,
+                macro_label,
+            } => write!(
+                f,
+                "Scene \"{}\": fixture \"{}\" has no macro \"{}\"",
+                scene_label, fixture_label, macro_label
+            ),
+            SceneIssue::MismatchedValue {
+                scene_label,
+                fixture_label,
+                macro_label,
+            } => write!(
+                f,
+                "Scene \"{}\": wrong value type for macro \"{}\" on fixture \"{}\"",
+                scene_label, macro_label, fixture_label
+            ),
+        }
+    }
+}
+
+/// A problem with where fixtures (or pixel maps) are patched
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchIssue {
+    /// Two fixtures patched onto the same DMX channel, so that both would
+    /// write to it (and the output flickers between them)
+    Conflict {
+        universe: u16,
+        /// Numbered from 1, as shown to operators
+        channel: usize,
+        fixture_a: String,
+        fixture_b: String,
+    },
+    /// A fixture or pixel map on a universe above `MAX_UNIVERSE`, which
+    /// cannot be output
+    UniverseOutOfRange { label: String, universe: u16 },
+}
+
+impl PatchIssue {
+    /// Whether the fixture (or pixel map) with this label is involved
+    pub fn involves(&self, label: &str) -> bool {
+        match self {
+            PatchIssue::Conflict {
+                fixture_a,
+                fixture_b,
+                ..
+            } => fixture_a == label || fixture_b == label,
+            PatchIssue::UniverseOutOfRange { label: l, .. } => l == label,
+        }
+    }
+}
+
+impl fmt::Display for PatchIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchIssue::Conflict {
+                universe,
+                channel,
+                fixture_a,
+                fixture_b,
+            } => write!(
+                f,
+                "fixtures \"{}\" and \"{}\" both use channel #{} (universe {})",
+                fixture_a, fixture_b, channel, universe
+            ),
+            PatchIssue::UniverseOutOfRange { label, universe } => write!(
+                f,
+                "\"{}\" is on universe {}, but universes only go up to {}; it will not be output",
+                label, universe, MAX_UNIVERSE
+            ),
+        }
+    }
+}
+
+/// A single macro value which differs between two Scenes; a value of None
+/// means that the macro (or the whole fixture) is not in that Scene
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneDifference {
+    pub fixture_label: String,
+    pub macro_label: String,
+    pub a_value: Option<SceneValue>,
+    pub b_value: Option<SceneValue>,
+}
+
+impl Scene {
+    /// List every macro value stored differently in this Scene (a) and the
+    /// other one (b), including macros stored in only one of them
+    pub fn diff(&self, other: &Scene) -> Vec<SceneDifference> {
+        let empty = SceneState::new();
+        let mut differences = Vec::new();
+        let fixture_labels = self
+            .state
+            .keys()
+            .chain(other.state.keys().filter(|k| !self.state.contains_key(*k)));
+        for fixture_label in fixture_labels {
+            let a_state = self.state.get(fixture_label).unwrap_or(&empty);
+            let b_state = other.state.get(fixture_label).unwrap_or(&empty);
+            let macro_labels = a_state
+                .keys()
+                .chain(b_state.keys().filter(|k| !a_state.contains_key(*k)));
+            for macro_label in macro_labels {
+                let a_value = a_state.get(macro_label);
+                let b_value = b_state.get(macro_label);
+                if a_value != b_value {
+                    differences.push(SceneDifference {
+                        fixture_label: String::from(fixture_label),
+                        macro_label: String::from(macro_label),
+                        a_value: a_value.cloned(),
+                        b_value: b_value.cloned(),
+                    });
+                }
+            }
+        }
+        differences
+    }
+}
+
 impl Project {
     pub fn new() -> Project {
         Project {
+            version: PROJECT_VERSION,
             fixtures: Vec::new(),
             scenes: Vec::new(),
             midi_config: MidiConfig::default(),
             artnet_config: None,
+            artnet_routes: ArtNetRoutes::new(),
+            pixel_maps: Vec::new(),
+            palette: default_palette(),
+            cue_lists: Vec::new(),
+            groups: Vec::new(),
         }
     }
 
@@ -61,114 +386,263 @@ impl Project {
         match fs::read_to_string(path) {
             Ok(d) => {
                 info!("Found project {}; parsing...", &path);
-                let mut project =
-                    serde_json::from_str::<Project>(&d).expect("failed to parse project file");
-                info!(
-                    "... loaded project with {} fixtures OK",
-                    project.fixtures.len()
+                Project::from_json(&d)
+            }
+            Err(e) => {
+                warn!("Failed to load Project from disk: {:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Parse (and if necessary migrate) Project JSON, attaching the fixture
+    /// configs that its fixtures refer to
+    pub fn from_json(json: &str) -> anyhow::Result<Project> {
+        let value = migrate(serde_json::from_str::<serde_json::Value>(json)?)?;
+        let mut project = serde_json::from_value::<Project>(value)
+            .map_err(|e| anyhow!("Project file could not be parsed: {}", e))?;
+        info!(
+            "... loaded project with {} fixtures OK",
+            project.fixtures.len()
+        );
+
+        // let all_fixtures_json = include_str!("../all_fixtures.json");
+        // let all_fixture_configs =
+        //     serde_json::from_str::<Vec<FixtureConfig>>(all_fixtures_json)
+        //         .expect("failed to parse all_fixtures JSON");
+
+        // debug!(
+        //     "Loaded {} fixtures from all_fixtures JSON",
+        //     all_fixture_configs.len(),
+        // );
+
+        let all_fixture_configs = load_all_fixture_configs();
+
+        let mut global_index = 0;
+
+        for fixture_ref in project.fixtures.iter_mut() {
+            if let Some(fixture_config) = all_fixture_configs
+                .iter()
+                .find(|x| x.name.eq_ignore_ascii_case(&fixture_ref.config_name))
+            {
+                if let Err(e) = fixture_ref.set_config(fixture_config) {
+                    error!(
+                        "Skipping fixture \"{}\" with invalid mode: {}",
+                        &fixture_ref.label, e
+                    );
+                    continue;
+                }
+
+                for m in fixture_ref.config.active_mode.macros.iter_mut() {
+                    match m {
+                        FixtureMacro::Control(control_macro) => {
+                            control_macro.global_index = global_index;
+                            global_index += 1;
+                        }
+                        FixtureMacro::Colour(_) | FixtureMacro::Wheel(_) => {
+                            // Ignore colour and wheel macros for now
+                        }
+                    }
+                }
+            } else {
+                error!(
+                    "Failed to match config name \"{}\" with any known fixtures",
+                    &fixture_ref.config_name
                 );
+            }
+        }
+
+        project.fixtures.sort_by_key(|x| x.label.clone());
 
-                // let all_fixtures_json = include_str!("../all_fixtures.json");
-                // let all_fixture_configs =
-                //     serde_json::from_str::<Vec<FixtureConfig>>(all_fixtures_json)
-                //         .expect("failed to parse all_fixtures JSON");
+        // Sort macros in Fixtures, alphabetically...
+        for fixture in project.fixtures.iter_mut() {
+            let mut mode_macros_ordered = fixture.config.active_mode.clone();
+            mode_macros_ordered
+                .macros
+                .sort_by_key(|m| String::from(m.label()));
+            fixture.config.active_mode = mode_macros_ordered;
+        }
 
-                // debug!(
-                //     "Loaded {} fixtures from all_fixtures JSON",
-                //     all_fixture_configs.len(),
-                // );
+        // Level 1: Scenes sorted by their labels
+        project.scenes.sort_by_key(|x| x.label.clone());
 
-                let all_fixture_configs = load_all_fixture_configs();
+        // Level 2: Fixture for each Scene sorted by label
+        for scene in project.scenes.iter_mut() {
+            scene.state.sort_keys();
+            for (_fixture_key, macro_key) in scene.state.iter_mut() {
+                macro_key.sort_keys();
+            }
+        }
 
-                let mut global_index = 0;
+        debug!("Final ordered scenes: {:?}", project.scenes);
 
-                for fixture_ref in project.fixtures.iter_mut() {
-                    if let Some(fixture_config) = all_fixture_configs
+        for issue in project.audit_scenes() {
+            warn!("Scene needs repair; {}", issue);
+        }
+
+        for issue in project.validate() {
+            warn!("Patch problem; {}", issue);
+        }
+
+        // Level 3: Sort each Macro entry within each Scene Fixture entry
+        // for scene in project.scenes.iter_mut() {
+        //     for fixture in scene.state.iter_mut() {}
+        // }
+        // for (_fixture_instance_key, macro_contents) in scene.state.iter_mut() {
+        //     let mut ordered_macros_vec = Vec::new();
+        //     // let ordered_
+        //     for (macro_key, macro_value) in macro_contents.clone() {
+        //         ordered_macros_vec.push((macro_key, macro_value));
+        //     }
+        //     ordered_macros_vec.sort_by_key(|(k, _v)| String::from(k));
+
+        //     macro_contents.clear();
+        //     for (i, (k, v)) in ordered_macros_vec.iter().enumerate() {
+        //         debug!("#{} Insert macro {}", i, k);
+        //         macro_contents.insert((*k).clone(), (*v).clone());
+        //     }
+        //     debug!("Fixtures")
+        // }
+
+        Ok(project)
+    }
+
+    /// Check every Scene against the fixtures (and their current modes) in the
+    /// Project, returning any references that would be skipped when applied
+    pub fn audit_scenes(&self) -> Vec<SceneIssue> {
+        let mut issues = Vec::new();
+        for scene in self.scenes.iter() {
+            for (fixture_label, fixture_state) in scene.state.iter() {
+                let fixture = match self
+                    .fixtures
+                    .iter()
+                    .find(|x| x.label.eq_ignore_ascii_case(fixture_label))
+                {
+                    Some(fixture) => fixture,
+                    None => {
+                        issues.push(SceneIssue::MissingFixture {
+                            scene_label: String::from(&scene.label),
+                            fixture_label: String::from(fixture_label),
+                        });
+                        continue;
+                    }
+                };
+                for (macro_label, value) in fixture_state.iter() {
+                    match fixture
+                        .config
+                        .active_mode
+                        .macros
                         .iter()
-                        .find(|x| x.name.eq_ignore_ascii_case(&fixture_ref.config_name))
+                        .find(|m| m.label().eq(macro_label))
                     {
-                        fixture_ref.config = fixture_config.clone();
-                        fixture_ref.config.active_mode =
-                            fixture_ref.config.modes[fixture_ref.mode_index].clone();
-
-                        for m in fixture_ref.config.active_mode.macros.iter_mut() {
-                            match m {
-                                FixtureMacro::Control(control_macro) => {
-                                    control_macro.global_index = global_index;
-                                    global_index += 1;
-                                }
-                                FixtureMacro::Colour(_colour_macro) => {
-                                    // Ignore colour macros for now
-                                }
+                        Some(m) => {
+                            let is_matching_type = matches!(
+                                (m, value),
+                                (FixtureMacro::Control(_), SceneValue::ControlValue(_))
+                                    | (FixtureMacro::Colour(_), SceneValue::ColourValue(_))
+                                    | (FixtureMacro::Wheel(_), SceneValue::Slot(_))
+                            );
+                            if !is_matching_type {
+                                issues.push(SceneIssue::MismatchedValue {
+                                    scene_label: String::from(&scene.label),
+                                    fixture_label: String::from(fixture_label),
+                                    macro_label: String::from(macro_label),
+                                });
                             }
                         }
-                    } else {
-                        error!(
-                            "Failed to match config name \"{}\" with any known fixtures",
-                            &fixture_ref.config_name
-                        );
+                        None => issues.push(SceneIssue::MissingMacro {
+                            scene_label: String::from(&scene.label),
+                            fixture_label: String::from(fixture_label),
+                            macro_label: String::from(macro_label),
+                        }),
                     }
                 }
+            }
+        }
+        issues
+    }
 
-                project.fixtures.sort_by_key(|x| x.label.clone());
-
-                // Sort macros in Fixtures, alphabetically...
-                for fixture in project.fixtures.iter_mut() {
-                    let mut mode_macros_ordered = fixture.config.active_mode.clone();
-                    mode_macros_ordered.macros.sort_by_key(|m| match m {
-                        fixture::FixtureMacro::Control(cm) => cm.label.clone(),
-                        fixture::FixtureMacro::Colour(cm) => cm.label.clone(),
-                    });
-                    fixture.config.active_mode = mode_macros_ordered;
+    /// Expand any group labels in a list of fixture labels into the labels of
+    /// the fixtures in that group; other labels (or patterns) are kept as they
+    /// are. Comma-separated entries are split first. No list (i.e. all
+    /// fixtures) stays as no list.
+    pub fn resolve_fixture_labels(&self, labels: &Option<Vec<String>>) -> Option<Vec<String>> {
+        labels.as_ref().map(|labels| {
+            let mut resolved = Vec::new();
+            for label in labels.iter().flat_map(|x| x.split(',')).map(str::trim) {
+                match self
+                    .groups
+                    .iter()
+                    .find(|g| g.label.eq_ignore_ascii_case(label))
+                {
+                    Some(group) => resolved.extend(group.fixture_labels.iter().cloned()),
+                    None => resolved.push(String::from(label)),
                 }
+            }
+            resolved
+        })
+    }
 
-                // Level 1: Scenes sorted by their labels
-                project.scenes.sort_by_key(|x| x.label.clone());
-
-                // Level 2: Fixture for each Scene sorted by label
-                for scene in project.scenes.iter_mut() {
-                    scene.state.sort_keys();
-                    for (_fixture_key, macro_key) in scene.state.iter_mut() {
-                        macro_key.sort_keys();
+    /// Check the patch for fixtures or pixel maps on universes that cannot be
+    /// output, and for channels used by more than one fixture, in each
+    /// fixture's current mode and offset. Each overlapping channel is reported
+    /// once per pair of fixtures.
+    pub fn validate(&self) -> Vec<PatchIssue> {
+        let mut conflicts = Vec::new();
+        for (label, universe) in self
+            .fixtures
+            .iter()
+            .map(|f| (&f.label, f.universe))
+            .chain(self.pixel_maps.iter().map(|m| (&m.label, m.universe)))
+        {
+            if universe > MAX_UNIVERSE {
+                conflicts.push(PatchIssue::UniverseOutOfRange {
+                    label: String::from(label),
+                    universe,
+                });
+            }
+        }
+        // { (universe, channel index): (fixture index, fixture label) }; by
+        // index, since labels need not be unique
+        let mut assigned: BTreeMap<(u16, usize), (usize, &str)> = BTreeMap::new();
+        for (fixture_index, fixture) in self.fixtures.iter().enumerate() {
+            if fixture.config.modes.get(fixture.mode_index).is_none() {
+                continue;
+            }
+            for m in fixture.config.active_mode.mappings.iter() {
+                let channel_index = fixture.channel_index(m.channel);
+                match assigned.get(&(fixture.universe, channel_index)) {
+                    Some((other_index, other_label)) if *other_index != fixture_index => conflicts
+                        .push(PatchIssue::Conflict {
+                            universe: fixture.universe,
+                            channel: channel_index.wrapping_add(1),
+                            fixture_a: String::from(*other_label),
+                            fixture_b: String::from(&fixture.label),
+                        }),
+                    Some(_) => {}
+                    None => {
+                        assigned.insert(
+                            (fixture.universe, channel_index),
+                            (fixture_index, &fixture.label),
+                        );
                     }
                 }
-
-                debug!("Final ordered scenes: {:?}", project.scenes);
-
-                // Level 3: Sort each Macro entry within each Scene Fixture entry
-                // for scene in project.scenes.iter_mut() {
-                //     for fixture in scene.state.iter_mut() {}
-                // }
-                // for (_fixture_instance_key, macro_contents) in scene.state.iter_mut() {
-                //     let mut ordered_macros_vec = Vec::new();
-                //     // let ordered_
-                //     for (macro_key, macro_value) in macro_contents.clone() {
-                //         ordered_macros_vec.push((macro_key, macro_value));
-                //     }
-                //     ordered_macros_vec.sort_by_key(|(k, _v)| String::from(k));
-
-                //     macro_contents.clear();
-                //     for (i, (k, v)) in ordered_macros_vec.iter().enumerate() {
-                //         debug!("#{} Insert macro {}", i, k);
-                //         macro_contents.insert((*k).clone(), (*v).clone());
-                //     }
-                //     debug!("Fixtures")
-                // }
-
-                Ok(project)
-            }
-            Err(e) => {
-                warn!("Failed to load Project from disk: {:?}", e);
-                Err(e.into())
             }
         }
+        conflicts
     }
 
-    pub fn save(path: &str, project: &Project) -> anyhow::Result<()> {
+    /// The JSON written by `save`, e.g. for checking whether there are any
+    /// unsaved changes
+    pub fn to_json(project: &Project) -> anyhow::Result<String> {
         let mut new_project = project.clone();
+        new_project.version = PROJECT_VERSION;
         new_project.fixtures.sort();
+        Ok(serde_json::to_string_pretty(&new_project)?)
+    }
 
-        let json = serde_json::to_string_pretty(&new_project)?;
+    pub fn save(path: &str, project: &Project) -> anyhow::Result<()> {
+        let json = Project::to_json(project)?;
         debug!("{}", json);
 
         fs::write(path, json)?;
@@ -185,18 +659,325 @@ impl Default for Project {
     }
 }
 
-/// Get the statically-defined DMX fixture configurations known to the system. This
-/// list is built at compile-time using the JSON definitions found in the `fixtures` folder;
-/// these are automatically concatenated into the file `all_fixtures.json` by the
-/// build script.
+/// Get the DMX fixture configurations known to the system: those built in
+/// at compile-time, plus any imported since (see `IMPORTED_FIXTURES_DIR`)
 pub fn load_all_fixture_configs() -> Vec<FixtureConfig> {
+    load_fixture_configs_with_imports(Path::new(IMPORTED_FIXTURES_DIR))
+}
+
+/// The built-in fixture configs, with those imported into the given folder
+/// added (replacing any built-in config with the same name)
+fn load_fixture_configs_with_imports(imported_dir: &Path) -> Vec<FixtureConfig> {
+    let mut all_fixture_configs = load_built_in_fixture_configs();
+    for imported in load_imported_fixture_configs(imported_dir) {
+        all_fixture_configs.retain(|x| !x.name.eq_ignore_ascii_case(&imported.name));
+        all_fixture_configs.push(imported);
+    }
+    all_fixture_configs
+}
+
+/// The statically-defined fixture configs. This list is built at compile-time
+/// using the JSON definitions found in the `fixtures` folder; these are
+/// automatically concatenated into the file `all_fixtures.json` by the build
+/// script.
+fn load_built_in_fixture_configs() -> Vec<FixtureConfig> {
     let all_fixtures_json = include_str!("../all_fixtures.json");
-    let all_fixture_configs = serde_json::from_str::<Vec<FixtureConfig>>(all_fixtures_json)
+    let mut all_fixture_configs = serde_json::from_str::<Vec<FixtureConfig>>(all_fixtures_json)
         .expect("failed to parse all_fixtures JSON");
 
+    all_fixture_configs.retain(|x| {
+        if x.modes.is_empty() {
+            warn!("Fixture config \"{}\" has no modes; skipped", &x.name);
+        }
+        !x.modes.is_empty()
+    });
+
     debug!(
         "Loaded {} fixtures from all_fixtures JSON",
         all_fixture_configs.len(),
     );
     all_fixture_configs
 }
+
+/// Every fixture config saved in the given folder (which need not exist yet);
+/// any file which fails to load is logged and skipped
+fn load_imported_fixture_configs(dir: &Path) -> Vec<FixtureConfig> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| match load_fixture_config(&path.to_string_lossy()) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                error!("Skipping imported fixture config {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Save an imported fixture config into the given folder (created if
+/// needed), named after the config, so that it is loaded again from then on
+pub fn save_imported_fixture_config(
+    dir: &Path,
+    fixture_config: &FixtureConfig,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    let file_name: String = fixture_config
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    save_fixture_config(
+        &dir.join(format!("{}.json", file_name)).to_string_lossy(),
+        fixture_config,
+    )
+}
+
+/// Load a single DMX fixture configuration from a JSON file on disk, e.g. when
+/// authoring a new fixture definition in the `fixtures` folder
+pub fn load_fixture_config(path: &str) -> anyhow::Result<FixtureConfig> {
+    let json = fs::read_to_string(path)?;
+    let fixture_config = serde_json::from_str::<FixtureConfig>(&json)?;
+    if fixture_config.modes.is_empty() {
+        return Err(anyhow!(
+            "Fixture config \"{}\" has no modes",
+            &fixture_config.name
+        ));
+    }
+    info!(
+        "Loaded fixture config \"{}\" with {} modes from \"{}\"",
+        &fixture_config.name,
+        fixture_config.modes.len(),
+        path
+    );
+    Ok(fixture_config)
+}
+
+/// Write a DMX fixture configuration as JSON, e.g. into the `fixtures` folder
+/// after importing it from another format, so that it is built in next time
+pub fn save_fixture_config(path: &str, fixture_config: &FixtureConfig) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(fixture_config)?;
+    fs::write(path, json)?;
+    info!(
+        "Saved fixture config \"{}\" to \"{}\"",
+        &fixture_config.name, path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_PROJECT_PATH: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/v1.project.json");
+
+    /// An empty folder of imported fixtures, unique to the test
+    fn imported_dir(test_name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "tether-artnet-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn built_in(name: &str) -> FixtureConfig {
+        load_built_in_fixture_configs()
+            .into_iter()
+            .find(|c| c.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn imported_fixture_is_loaded_again() {
+        let dir = imported_dir("imported_fixture_is_loaded_again");
+        let built_in_count = load_built_in_fixture_configs().len();
+        assert_eq!(
+            load_fixture_configs_with_imports(&dir).len(),
+            built_in_count
+        );
+
+        let mut config = built_in("Varytec HERO 340FX");
+        config.name = String::from("Imported / Mover");
+        save_imported_fixture_config(&dir, &config).unwrap();
+        assert!(dir.join("Imported___Mover.json").exists());
+
+        let all = load_fixture_configs_with_imports(&dir);
+        assert_eq!(all.len(), built_in_count + 1);
+        let imported = all.iter().find(|c| c.name == "Imported / Mover").unwrap();
+        assert_eq!(imported.modes.len(), config.modes.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imported_fixture_replaces_built_in_with_same_name() {
+        let dir = imported_dir("imported_fixture_replaces_built_in");
+        let mut config = built_in("Varytec HERO 340FX");
+        config.modes[0].name = String::from("Imported mode");
+        save_imported_fixture_config(&dir, &config).unwrap();
+
+        let all = load_fixture_configs_with_imports(&dir);
+        assert_eq!(all.len(), load_built_in_fixture_configs().len());
+        let matching: Vec<&FixtureConfig> = all
+            .iter()
+            .filter(|c| c.name == "Varytec HERO 340FX")
+            .collect();
+        assert_eq!(matching.len(), 1);
+        assert_eq!(matching[0].modes[0].name, "Imported mode");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_imported_fixture_is_skipped() {
+        let dir = imported_dir("invalid_imported_fixture_is_skipped");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("broken.json"), "{ not json").unwrap();
+        assert_eq!(
+            load_fixture_configs_with_imports(&dir).len(),
+            load_built_in_fixture_configs().len()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_v1_adds_v2_fields() {
+        let v1 = serde_json::from_str(&fs::read_to_string(V1_PROJECT_PATH).unwrap()).unwrap();
+        let migrated = migrate(v1).unwrap();
+        assert_eq!(migrated["version"], PROJECT_VERSION);
+        for key in ["palette", "groups", "cueLists", "pixelMaps", "artnetRoutes"] {
+            assert!(
+                migrated.get(key).is_some(),
+                "no \"{}\" after migration",
+                key
+            );
+        }
+        assert_eq!(
+            migrated["palette"],
+            serde_json::to_value(default_palette()).unwrap()
+        );
+    }
+
+    #[test]
+    fn load_v1_project() {
+        let project = Project::load(V1_PROJECT_PATH).unwrap();
+        assert_eq!(project.version, PROJECT_VERSION);
+        assert_eq!(project.fixtures.len(), 2);
+        assert!(project
+            .fixtures
+            .iter()
+            .all(|f| f.config.active_mode.mappings.len() == 16));
+        assert_eq!(project.scenes.len(), 1);
+        assert!(project.audit_scenes().is_empty());
+        assert_eq!(
+            project.artnet_config,
+            Some(ArtNetConfigMode::Unicast(
+                String::from("127.0.0.1"),
+                String::from("127.0.0.1")
+            ))
+        );
+        assert_eq!(project.palette, default_palette());
+        assert!(project.groups.is_empty());
+        assert!(project.cue_lists.is_empty());
+        assert!(project.pixel_maps.is_empty());
+        assert!(project.artnet_routes.is_empty());
+        assert!(project.validate().is_empty());
+    }
+
+    #[test]
+    fn v2_project_is_left_as_is() {
+        let project = Project::load(V1_PROJECT_PATH).unwrap();
+        let json = Project::to_json(&project).unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(migrate(value.clone()).unwrap(), value);
+    }
+
+    #[test]
+    fn universes_out_of_range_are_reported() {
+        let project = Project::from_json(
+            r#"{
+                "fixtures": [
+                    { "label": "ok", "configName": "Varytec HERO 340FX", "offsetChannels": 0, "universe": 32767 },
+                    { "label": "too high", "configName": "Varytec HERO 340FX", "offsetChannels": 0, "universe": 40000 }
+                ],
+                "scenes": [],
+                "pixelMaps": [
+                    { "label": "strip", "universe": 65535, "offsetChannels": 0, "width": 10 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let issues = project.validate();
+        assert_eq!(
+            issues,
+            vec![
+                PatchIssue::UniverseOutOfRange {
+                    label: String::from("too high"),
+                    universe: 40000
+                },
+                PatchIssue::UniverseOutOfRange {
+                    label: String::from("strip"),
+                    universe: 65535
+                }
+            ]
+        );
+        assert!(issues[0].involves("too high"));
+        assert!(!issues[0].involves("ok"));
+    }
+
+    #[test]
+    fn overlapping_fixtures_are_reported() {
+        let project = Project::from_json(
+            r#"{
+                "fixtures": [
+                    { "label": "a", "configName": "Varytec HERO 340FX", "offsetChannels": 0 },
+                    { "label": "b", "configName": "Varytec HERO 340FX", "offsetChannels": 15 }
+                ],
+                "scenes": []
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            project.validate(),
+            vec![PatchIssue::Conflict {
+                universe: 0,
+                channel: 16,
+                fixture_a: String::from("a"),
+                fixture_b: String::from("b")
+            }]
+        );
+    }
+
+    #[test]
+    fn overlapping_fixtures_with_the_same_label_are_reported() {
+        let project = Project::from_json(
+            r#"{
+                "fixtures": [
+                    { "label": "a", "configName": "Varytec HERO 340FX", "offsetChannels": 0 },
+                    { "label": "a", "configName": "Varytec HERO 340FX", "offsetChannels": 14 }
+                ],
+                "scenes": []
+            }"#,
+        )
+        .unwrap();
+        let conflicts = project.validate();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0],
+            PatchIssue::Conflict {
+                universe: 0,
+                channel: 15,
+                fixture_a: String::from("a"),
+                fixture_b: String::from("a")
+            }
+        );
+    }
+}
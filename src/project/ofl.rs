@@ -0,0 +1,465 @@
+use std::fs;
+
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::fixture::{
+    CMYChannels, ChannelList, ChannelMacro, ChannelRole, ColourMacro, ControlMode, FixtureConfig,
+    FixtureMacro, Mapping, RGBWChannels, RangeDescription, WheelMacro,
+};
+
+/// The parts of an Open Fixture Library (OFL) fixture definition that we use;
+/// see https://github.com/OpenLightingProject/open-fixture-library/blob/master/docs/fixture-format.md
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OflFixture {
+    name: String,
+    #[serde(default)]
+    links: IndexMap<String, Vec<String>>,
+    #[serde(default)]
+    available_channels: IndexMap<String, OflChannel>,
+    modes: Vec<OflMode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OflChannel {
+    #[serde(default)]
+    fine_channel_aliases: Vec<String>,
+    /// "8bit", "16bit", etc.; otherwise values are at the finest resolution
+    /// the channel has
+    dmx_value_resolution: Option<String>,
+    /// A DMX value, or a percentage string such as "50%"
+    default_value: Option<Value>,
+    capability: Option<OflCapability>,
+    #[serde(default)]
+    capabilities: Vec<OflCapability>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OflCapability {
+    #[serde(rename = "type")]
+    kind: String,
+    dmx_range: Option<[u32; 2]>,
+    comment: Option<String>,
+    /// For ColorIntensity, e.g. "Red"
+    color: Option<String>,
+    /// For ColorPreset, WheelSlot, ShutterStrobe, Effect, etc.
+    #[serde(alias = "shutterEffect", alias = "effectName", alias = "effectPreset")]
+    name: Option<String>,
+    /// For WheelSlot
+    slot_number: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct OflMode {
+    name: String,
+    /// Channel keys (or fine channel aliases); null for an unused channel
+    channels: Vec<Value>,
+}
+
+impl OflChannel {
+    fn capabilities(&self) -> Vec<&OflCapability> {
+        match &self.capability {
+            Some(c) => vec![c],
+            None => self.capabilities.iter().collect(),
+        }
+    }
+
+    /// How many bytes the DMX values in this channel's definition are given in
+    fn value_bytes(&self) -> u32 {
+        match self.dmx_value_resolution.as_deref() {
+            Some("8bit") => 1,
+            Some("16bit") => 2,
+            Some("24bit") => 3,
+            _ => 1 + self.fine_channel_aliases.len() as u32,
+        }
+    }
+
+    /// Reduce a value from the definition to the coarse (8-bit) channel
+    fn coarse_value(&self, value: u32) -> u8 {
+        let shift = 8 * self.value_bytes().clamp(1, 4).saturating_sub(1);
+        (value >> shift).min(255) as u8
+    }
+
+    fn home(&self) -> Option<u8> {
+        match self.default_value.as_ref()? {
+            Value::Number(n) => Some(self.coarse_value(n.as_u64()? as u32)),
+            Value::String(s) => {
+                let percent = s.strip_suffix('%')?.trim().parse::<f32>().ok()?;
+                Some((percent.clamp(0., 100.) * 2.55).round() as u8)
+            }
+            _ => None,
+        }
+    }
+
+    fn role(&self) -> ChannelRole {
+        match self.capabilities().first().map(|c| c.kind.as_str()) {
+            Some("Intensity") => ChannelRole::Intensity,
+            Some("ColorIntensity" | "ColorPreset" | "ColorTemperature") => ChannelRole::Colour,
+            Some("Pan" | "Tilt" | "PanContinuous" | "TiltContinuous") => ChannelRole::Position,
+            _ => ChannelRole::Other,
+        }
+    }
+
+    /// Labelled DMX ranges, one per capability, if there is more than one
+    fn ranges(&self) -> Option<Vec<RangeDescription>> {
+        let ranges: Vec<RangeDescription> = self
+            .capabilities()
+            .iter()
+            .filter_map(|c| {
+                let [start, end] = c.dmx_range?;
+                let label = c
+                    .comment
+                    .clone()
+                    .or_else(|| c.name.as_ref().map(|n| format!("{} {}", &c.kind, n)))
+                    .or_else(|| c.color.as_ref().map(|n| format!("{} {}", &c.kind, n)))
+                    .or_else(|| c.slot_number.map(|n| format!("Slot {}", n)))
+                    .unwrap_or_else(|| String::from(&c.kind));
+                Some(RangeDescription {
+                    range: [self.coarse_value(start), self.coarse_value(end)],
+                    label,
+                })
+            })
+            .collect();
+        (ranges.len() > 1).then_some(ranges)
+    }
+
+    /// A channel made up only of named slots (e.g. a colour or gobo wheel)
+    fn is_wheel(&self) -> bool {
+        let capabilities = self.capabilities();
+        capabilities.len() > 1
+            && capabilities
+                .iter()
+                .all(|c| matches!(c.kind.as_str(), "WheelSlot" | "ColorPreset" | "NoFunction"))
+    }
+}
+
+/// Import a fixture from an Open Fixture Library JSON definition: each mode
+/// becomes a ControlMode, with a mapping for every channel, a Colour Macro
+/// for colour mixing channels, a Wheel Macro for wheel (slot) channels and a
+/// Control Macro for everything else
+pub fn load_ofl(path: &str) -> anyhow::Result<FixtureConfig> {
+    let json = fs::read_to_string(path)?;
+    ofl_from_json(&json, path)
+}
+
+/// As `load_ofl`, from the JSON itself; the path is only for messages
+fn ofl_from_json(json: &str, path: &str) -> anyhow::Result<FixtureConfig> {
+    let fixture = serde_json::from_str::<OflFixture>(json)
+        .map_err(|e| anyhow!("OFL fixture could not be parsed: {}", e))?;
+
+    let reference = ["manual", "productPage", "other"]
+        .iter()
+        .find_map(|key| fixture.links.get(*key).and_then(|links| links.first()))
+        .cloned()
+        .unwrap_or_default();
+
+    let mut modes = Vec::new();
+    for ofl_mode in fixture.modes.iter() {
+        let mode = convert_mode(&fixture, ofl_mode);
+        match mode.validate() {
+            Ok(()) => modes.push(mode),
+            Err(errors) => warn!(
+                "Skipping OFL mode \"{}\" of \"{}\": {}",
+                &mode.name,
+                &fixture.name,
+                errors.join("; ")
+            ),
+        }
+    }
+    if modes.is_empty() {
+        return Err(anyhow!("OFL fixture \"{}\" has no usable modes", path));
+    }
+
+    info!(
+        "Imported fixture \"{}\" with {} modes from OFL \"{}\"",
+        &fixture.name,
+        modes.len(),
+        path
+    );
+    Ok(FixtureConfig {
+        name: fixture.name,
+        reference,
+        active_mode: ControlMode::default(),
+        modes,
+    })
+}
+
+fn convert_mode(fixture: &OflFixture, ofl_mode: &OflMode) -> ControlMode {
+    // { channel key or fine alias: channel number in this mode }
+    let mut numbers: IndexMap<&str, u16> = IndexMap::new();
+    for (i, entry) in ofl_mode.channels.iter().enumerate() {
+        match entry {
+            Value::String(key) => {
+                numbers.insert(key.as_str(), i as u16 + 1);
+            }
+            Value::Null => {}
+            other => warn!(
+                "Unsupported channel {} in OFL mode \"{}\" (matrix channels are not supported)",
+                other, &ofl_mode.name
+            ),
+        }
+    }
+
+    let mut mappings = Vec::new();
+    let mut macros = Vec::new();
+    let mut rgbw = RGBWChannels {
+        red: Vec::new(),
+        green: Vec::new(),
+        blue: Vec::new(),
+        white: Vec::new(),
+    };
+    let mut cmy = CMYChannels {
+        cyan: Vec::new(),
+        magenta: Vec::new(),
+        yellow: Vec::new(),
+        white: Vec::new(),
+    };
+
+    for (key, number) in numbers.iter() {
+        let Some(channel) = fixture.available_channels.get(*key) else {
+            if !fixture
+                .available_channels
+                .values()
+                .any(|c| c.fine_channel_aliases.iter().any(|a| a == key))
+            {
+                warn!(
+                    "OFL mode \"{}\" uses unknown channel \"{}\"",
+                    &ofl_mode.name, key
+                );
+            }
+            continue; // fine channels are mapped along with their coarse channel
+        };
+
+        let fine_channel = channel
+            .fine_channel_aliases
+            .first()
+            .and_then(|alias| numbers.get(alias.as_str()).copied());
+        mappings.push(Mapping {
+            channel: *number,
+            role: channel.role(),
+            fine_channel,
+            label: String::from(*key),
+            notes: channel
+                .capabilities()
+                .first()
+                .and_then(|c| c.comment.clone()),
+            home: channel.home(),
+            ranges: channel.ranges(),
+        });
+        for alias in channel.fine_channel_aliases.iter() {
+            if let Some(fine_number) = numbers.get(alias.as_str()) {
+                mappings.push(Mapping {
+                    channel: *fine_number,
+                    role: channel.role(),
+                    fine_channel: None,
+                    label: String::from(alias),
+                    notes: None,
+                    home: None,
+                    ranges: None,
+                });
+            }
+        }
+
+        let capabilities = channel.capabilities();
+        let colour_intensity = match capabilities.as_slice() {
+            [c] if c.kind == "ColorIntensity" => c.color.as_deref(),
+            _ => None,
+        };
+        let colour_channels = match colour_intensity {
+            Some("Red") => Some(&mut rgbw.red),
+            Some("Green") => Some(&mut rgbw.green),
+            Some("Blue") => Some(&mut rgbw.blue),
+            Some("White") => Some(&mut rgbw.white),
+            Some("Cyan") => Some(&mut cmy.cyan),
+            Some("Magenta") => Some(&mut cmy.magenta),
+            Some("Yellow") => Some(&mut cmy.yellow),
+            _ => None,
+        };
+        if let Some(colour_channels) = colour_channels {
+            colour_channels.push(*number);
+        } else if channel.is_wheel() {
+            macros.push(FixtureMacro::Wheel(WheelMacro {
+                label: String::from(*key),
+                channels: vec![*number],
+                slots: channel.ranges().unwrap_or_default(),
+                current_value: 0,
+            }));
+        } else {
+            let mut control_macro = ChannelMacro::new(key, vec![*number]);
+            control_macro.is_16bit = fine_channel.is_some();
+            macros.push(FixtureMacro::Control(control_macro));
+        }
+    }
+
+    // Colour mixing channels become one Colour Macro; any which don't make up
+    // a full RGB or CMY set get their own Control Macros instead
+    let is_additive = !(rgbw.red.is_empty() || rgbw.green.is_empty() || rgbw.blue.is_empty());
+    let is_subtractive = !(cmy.cyan.is_empty() || cmy.magenta.is_empty() || cmy.yellow.is_empty());
+    let mut leftover: Vec<u16> = Vec::new();
+    if is_additive {
+        leftover.append(&mut cmy.cyan);
+        leftover.append(&mut cmy.magenta);
+        leftover.append(&mut cmy.yellow);
+        macros.push(FixtureMacro::Colour(ColourMacro::new(
+            "colour",
+            ChannelList::Additive(rgbw),
+        )));
+    } else if is_subtractive {
+        leftover.append(&mut rgbw.red);
+        leftover.append(&mut rgbw.green);
+        leftover.append(&mut rgbw.blue);
+        cmy.white = rgbw.white;
+        macros.push(FixtureMacro::Colour(ColourMacro::new(
+            "colour",
+            ChannelList::Subtractive(cmy),
+        )));
+    } else {
+        for channels in [rgbw.red, rgbw.green, rgbw.blue, rgbw.white] {
+            leftover.extend(channels);
+        }
+        for channels in [cmy.cyan, cmy.magenta, cmy.yellow] {
+            leftover.extend(channels);
+        }
+    }
+    for number in leftover {
+        if let Some(m) = mappings.iter().find(|m| m.channel == number) {
+            macros.push(FixtureMacro::Control(ChannelMacro::new(
+                &m.label,
+                vec![number],
+            )));
+        }
+    }
+
+    ControlMode {
+        name: String::from(&ofl_mode.name),
+        channel_count: Some(ofl_mode.channels.len() as u16),
+        mappings,
+        macros,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFL_SPOT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/ofl_spot.json");
+
+    fn mode<'a>(config: &'a FixtureConfig, name: &str) -> &'a ControlMode {
+        config.modes.iter().find(|m| m.name == name).unwrap()
+    }
+
+    fn mapping(mode: &ControlMode, channel: u16) -> &Mapping {
+        mode.mappings.iter().find(|m| m.channel == channel).unwrap()
+    }
+
+    fn find_macro<'a>(mode: &'a ControlMode, label: &str) -> &'a FixtureMacro {
+        mode.macros.iter().find(|m| m.label() == label).unwrap()
+    }
+
+    #[test]
+    fn load_ofl_spot() {
+        let config = load_ofl(OFL_SPOT_PATH).unwrap();
+        assert_eq!(config.name, "Test Spot");
+        assert_eq!(config.reference, "https://example.com/test-spot-manual.pdf");
+        assert_eq!(config.modes.len(), 2);
+    }
+
+    #[test]
+    fn basic_mode_without_fine_channels() {
+        let config = load_ofl(OFL_SPOT_PATH).unwrap();
+        let basic = mode(&config, "Basic");
+        assert_eq!(basic.footprint(), 8);
+        assert_eq!(basic.mappings.len(), 8);
+
+        let pan = mapping(basic, 1);
+        assert_eq!(pan.label, "Pan");
+        assert_eq!(pan.role, ChannelRole::Position);
+        assert_eq!(pan.fine_channel, None);
+        // Default given at 16-bit resolution, reduced to the coarse channel
+        assert_eq!(pan.home, Some(128));
+
+        let dimmer = mapping(basic, 3);
+        assert_eq!(dimmer.role, ChannelRole::Intensity);
+        assert_eq!(dimmer.fine_channel, None);
+        let FixtureMacro::Control(dimmer_macro) = find_macro(basic, "Dimmer") else {
+            panic!("Dimmer should be a Control Macro");
+        };
+        assert!(!dimmer_macro.is_16bit);
+    }
+
+    #[test]
+    fn extended_mode_maps_fine_channels() {
+        let config = load_ofl(OFL_SPOT_PATH).unwrap();
+        let extended = mode(&config, "Extended");
+        // The trailing unused (null) channel still counts towards the footprint
+        assert_eq!(extended.footprint(), 12);
+        assert_eq!(extended.mappings.len(), 11);
+        assert!(extended.mappings.iter().all(|m| m.channel != 12));
+
+        for (coarse, fine, label) in [
+            (1, 2, "Pan fine"),
+            (3, 4, "Tilt fine"),
+            (5, 6, "Dimmer fine"),
+        ] {
+            assert_eq!(mapping(extended, coarse).fine_channel, Some(fine));
+            let fine_mapping = mapping(extended, fine);
+            assert_eq!(fine_mapping.label, label);
+            assert_eq!(fine_mapping.fine_channel, None);
+        }
+        // Tilt's default is explicitly 8-bit
+        assert_eq!(mapping(extended, 3).home, Some(128));
+
+        assert_eq!(mapping(extended, 5).role, ChannelRole::Intensity);
+        assert_eq!(mapping(extended, 6).role, ChannelRole::Intensity);
+        let FixtureMacro::Control(dimmer_macro) = find_macro(extended, "Dimmer") else {
+            panic!("Dimmer should be a Control Macro");
+        };
+        assert!(dimmer_macro.is_16bit);
+        assert_eq!(dimmer_macro.channels, vec![5]);
+    }
+
+    #[test]
+    fn colour_wheel_and_ranges() {
+        let config = load_ofl(OFL_SPOT_PATH).unwrap();
+        let extended = mode(&config, "Extended");
+
+        let FixtureMacro::Colour(colour) = find_macro(extended, "colour") else {
+            panic!("colour should be a Colour Macro");
+        };
+        let ChannelList::Additive(rgbw) = &colour.channels else {
+            panic!("colour should be additive");
+        };
+        assert_eq!(
+            (&rgbw.red, &rgbw.green, &rgbw.blue),
+            (&vec![7], &vec![8], &vec![9])
+        );
+        assert!(rgbw.white.is_empty());
+        assert_eq!(mapping(extended, 7).role, ChannelRole::Colour);
+
+        let FixtureMacro::Wheel(wheel) = find_macro(extended, "Color Wheel") else {
+            panic!("Color Wheel should be a Wheel Macro");
+        };
+        assert_eq!(wheel.channels, vec![10]);
+        let slots: Vec<&str> = wheel.slots.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(slots, vec!["Open", "Red", "Blue"]);
+
+        let shutter = mapping(extended, 11);
+        assert_eq!(shutter.home, Some(255)); // "100%"
+        let ranges = shutter.ranges.as_ref().unwrap();
+        assert_eq!(ranges[0].range, [0, 9]);
+        assert_eq!(ranges[0].label, "ShutterStrobe Closed");
+        assert_eq!(ranges[1].range, [10, 255]);
+    }
+
+    #[test]
+    fn invalid_ofl_is_an_error() {
+        assert!(ofl_from_json("{ not json", "broken.json").is_err());
+        assert!(ofl_from_json(r#"{ "name": "No Modes", "modes": [] }"#, "empty.json").is_err());
+    }
+}
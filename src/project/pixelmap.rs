@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::animate_colour;
+
+/// Every pixel is a consecutive group of Red, Green and Blue channels
+pub const CHANNELS_PER_PIXEL: usize = 3;
+
+/// An addressable LED strip (a single row) or matrix (several rows), patched
+/// as consecutive RGB pixels and driven by one effect for all of them, rather
+/// than being set up as many separate fixtures
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PixelMap {
+    pub label: String,
+    #[serde(default)]
+    pub universe: u16,
+    /// Channels before the first pixel, as for a fixture; 0 => channel 1
+    pub offset_channels: u16,
+    /// Number of pixels in each row
+    pub width: u16,
+    /// Number of rows; rows follow on from each other, without gaps
+    #[serde(default = "single_row")]
+    pub height: u16,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub effect: PixelEffect,
+}
+
+fn single_row() -> u16 {
+    1
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PixelEffect {
+    #[default]
+    Off,
+    /// Every pixel the same colour
+    Solid(Color32),
+    /// From the first colour at the start of each row to the second colour
+    /// at the end
+    Gradient(Color32, Color32),
+    /// A block of `length` pixels moving along each row (wrapping around) at
+    /// `speed` pixels per second, over a background colour
+    #[serde(rename_all = "camelCase")]
+    Chase {
+        colour: Color32,
+        background: Color32,
+        length: u16,
+        speed: f32,
+    },
+}
+
+impl PixelEffect {
+    pub const NAMES: [&'static str; 4] = ["Off", "Solid", "Gradient", "Chase"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PixelEffect::Off => "Off",
+            PixelEffect::Solid(_) => "Solid",
+            PixelEffect::Gradient(_, _) => "Gradient",
+            PixelEffect::Chase { .. } => "Chase",
+        }
+    }
+
+    /// A new effect of the named type, with some reasonable defaults
+    pub fn from_name(name: &str) -> PixelEffect {
+        match name {
+            "Solid" => PixelEffect::Solid(Color32::WHITE),
+            "Gradient" => PixelEffect::Gradient(Color32::RED, Color32::BLUE),
+            "Chase" => PixelEffect::Chase {
+                colour: Color32::WHITE,
+                background: Color32::BLACK,
+                length: 1,
+                speed: 10.,
+            },
+            _ => PixelEffect::Off,
+        }
+    }
+}
+
+impl PixelMap {
+    pub fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Indexes (from 0, within the universe) of all channels taken by this map
+    pub fn channel_indexes(&self) -> Range<usize> {
+        let start = self.offset_channels as usize;
+        start..start + self.pixel_count() * CHANNELS_PER_PIXEL
+    }
+
+    /// The colour of the pixel at position `x` in its row, given the time
+    /// (in seconds, from any fixed point) for moving effects
+    pub fn colour_at(&self, x: u16, seconds: f64) -> Color32 {
+        match self.effect {
+            PixelEffect::Off => Color32::BLACK,
+            PixelEffect::Solid(colour) => colour,
+            PixelEffect::Gradient(start, end) => {
+                let progress = if self.width > 1 {
+                    x as f32 / (self.width - 1) as f32
+                } else {
+                    0.
+                };
+                animate_colour(&start, &end, progress)
+            }
+            PixelEffect::Chase {
+                colour,
+                background,
+                length,
+                speed,
+            } => {
+                let width = self.width.max(1) as f64;
+                let position = (seconds * speed as f64).rem_euclid(width);
+                let distance = (x as f64 - position.floor()).rem_euclid(width);
+                if distance < length as f64 {
+                    colour
+                } else {
+                    background
+                }
+            }
+        }
+    }
+
+    /// Write the current effect into the given channels (for the whole
+    /// universe), ignoring any pixels beyond the end
+    pub fn write(&self, channels: &mut [u8], seconds: f64) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel_index = y as usize * self.width as usize + x as usize;
+                let start = self.offset_channels as usize + pixel_index * CHANNELS_PER_PIXEL;
+                if let Some(pixel) = channels.get_mut(start..start + CHANNELS_PER_PIXEL) {
+                    let colour = self.colour_at(x, seconds);
+                    pixel.copy_from_slice(&[colour.r(), colour.g(), colour.b()]);
+                }
+            }
+        }
+    }
+}
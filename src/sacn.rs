@@ -0,0 +1,183 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::{Ipv4Addr, UdpSocket},
+};
+
+use log::{info, trace, warn};
+use rand::Rng;
+
+use crate::output::DmxOutput;
+
+pub const SACN_PORT: u16 = 5568;
+
+/// sACN universes run from 1 to this
+pub const MAX_SACN_UNIVERSE: u16 = 63999;
+
+const SOURCE_NAME: &str = "Tether ArtNet Controller";
+
+/// ACN packet identifier, which starts every E1.31 packet
+const ACN_PACKET_IDENTIFIER: [u8; 12] = *b"ASC-E1.17\0\0\0";
+
+const VECTOR_ROOT_E131_DATA: u32 = 0x00000004;
+const VECTOR_E131_DATA_PACKET: u32 = 0x00000002;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+
+/// Streaming ACN (ANSI E1.31) output, multicast to the standard group for
+/// each universe. sACN universes are numbered from 1, so each universe here
+/// is sent as the sACN universe one higher, e.g. universe 0 => sACN universe 1.
+pub struct SacnInterface {
+    socket: UdpSocket,
+    priority: u8,
+    /// Component Identifier: unique to this source, for the lifetime of the
+    /// process
+    cid: [u8; 16],
+    /// sACN universe => sequence number of the packet sent last
+    sequences: BTreeMap<u16, u8>,
+    /// Universes which have no sACN equivalent, so that each is only logged
+    /// once
+    skipped_universes: BTreeSet<u16>,
+}
+
+impl SacnInterface {
+    pub fn new(priority: u8) -> Result<Self, anyhow::Error> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        let priority = if priority > 200 {
+            warn!("sACN priority {} out of range; will use 200", priority);
+            200
+        } else {
+            priority
+        };
+        info!("sACN multicast output, priority {}", priority);
+        Ok(SacnInterface {
+            socket,
+            priority,
+            cid: rand::thread_rng().gen(),
+            sequences: BTreeMap::new(),
+            skipped_universes: BTreeSet::new(),
+        })
+    }
+}
+
+impl DmxOutput for SacnInterface {
+    fn send_universe(&mut self, universe: u16, data: &[u8]) {
+        let Some(sacn_universe) = sacn_universe_for(universe) else {
+            if self.skipped_universes.insert(universe) {
+                warn!(
+                    "Universe {} is beyond the highest sACN universe ({}); not sent",
+                    universe, MAX_SACN_UNIVERSE
+                );
+            }
+            return;
+        };
+        let sequence = self
+            .sequences
+            .entry(sacn_universe)
+            .and_modify(|s| *s = s.wrapping_add(1))
+            .or_insert(0);
+        let buff = build_e131_packet(&self.cid, self.priority, *sequence, sacn_universe, data);
+        let destination = (multicast_address(sacn_universe), SACN_PORT);
+        trace!("Send sACN universe {} to {:?}", sacn_universe, destination);
+        if let Err(e) = self.socket.send_to(&buff, destination) {
+            warn!("Failed to send sACN universe {}: {}", sacn_universe, e);
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("sACN multicast (priority {})", self.priority)
+    }
+}
+
+/// The sACN universe that the given universe is sent as (one higher), if it
+/// is within the sACN range
+pub fn sacn_universe_for(universe: u16) -> Option<u16> {
+    universe
+        .checked_add(1)
+        .filter(|sacn_universe| *sacn_universe <= MAX_SACN_UNIVERSE)
+}
+
+/// The multicast group for an sACN universe (1-63999): 239.255.{high}.{low}
+pub fn multicast_address(sacn_universe: u16) -> Ipv4Addr {
+    let [high, low] = sacn_universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, high, low)
+}
+
+/// Build a complete E1.31 data packet (root, framing and DMP layers) for the
+/// given sACN universe, with DMX start code zero followed by `data`
+pub fn build_e131_packet(
+    cid: &[u8; 16],
+    priority: u8,
+    sequence: u8,
+    sacn_universe: u16,
+    data: &[u8],
+) -> Vec<u8> {
+    let length = 126 + data.len();
+    // Each layer starts with its own length (from that point to the end of
+    // the packet) in the low 12 bits, with flags 0x7 in the high 4 bits
+    let flags_and_length = |from: usize| (0x7000 | (length - from) as u16).to_be_bytes();
+
+    let mut buff = Vec::with_capacity(length);
+    // ---- Root layer
+    buff.extend_from_slice(&0x0010u16.to_be_bytes()); // preamble size
+    buff.extend_from_slice(&0x0000u16.to_be_bytes()); // postamble size
+    buff.extend_from_slice(&ACN_PACKET_IDENTIFIER);
+    buff.extend_from_slice(&flags_and_length(16));
+    buff.extend_from_slice(&VECTOR_ROOT_E131_DATA.to_be_bytes());
+    buff.extend_from_slice(cid);
+    // ---- Framing layer
+    buff.extend_from_slice(&flags_and_length(38));
+    buff.extend_from_slice(&VECTOR_E131_DATA_PACKET.to_be_bytes());
+    let mut source_name = [0u8; 64];
+    let name_length = SOURCE_NAME.len().min(63); // always null-terminated
+    source_name[..name_length].copy_from_slice(&SOURCE_NAME.as_bytes()[..name_length]);
+    buff.extend_from_slice(&source_name);
+    buff.push(priority);
+    buff.extend_from_slice(&0u16.to_be_bytes()); // synchronisation address (none)
+    buff.push(sequence);
+    buff.push(0); // options
+    buff.extend_from_slice(&sacn_universe.to_be_bytes());
+    // ---- DMP layer
+    buff.extend_from_slice(&flags_and_length(115));
+    buff.push(VECTOR_DMP_SET_PROPERTY);
+    buff.push(0xa1); // address type & data type
+    buff.extend_from_slice(&0x0000u16.to_be_bytes()); // first property address
+    buff.extend_from_slice(&0x0001u16.to_be_bytes()); // address increment
+    buff.extend_from_slice(&(data.len() as u16 + 1).to_be_bytes()); // including start code
+    buff.push(0); // DMX start code
+    buff.extend_from_slice(data);
+    buff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn universes_are_numbered_from_1() {
+        assert_eq!(sacn_universe_for(0), Some(1));
+        assert_eq!(
+            sacn_universe_for(MAX_SACN_UNIVERSE - 1),
+            Some(MAX_SACN_UNIVERSE)
+        );
+    }
+
+    #[test]
+    fn universes_beyond_sacn_range() {
+        assert_eq!(sacn_universe_for(MAX_SACN_UNIVERSE), None);
+        assert_eq!(sacn_universe_for(u16::MAX), None);
+    }
+
+    #[test]
+    fn universe_beyond_sacn_range_is_skipped() {
+        let mut sacn = SacnInterface::new(100).unwrap();
+        sacn.send_universe(u16::MAX, &[0; 512]);
+        sacn.send_universe(u16::MAX, &[0; 512]);
+        assert!(sacn.sequences.is_empty());
+        assert_eq!(sacn.skipped_universes.len(), 1);
+    }
+
+    #[test]
+    fn multicast_group_from_universe() {
+        assert_eq!(multicast_address(1), Ipv4Addr::new(239, 255, 0, 1));
+        assert_eq!(multicast_address(63999), Ipv4Addr::new(239, 255, 249, 255));
+    }
+}
@@ -1,12 +1,67 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 pub const UNICAST_SRC_STRING: &str = "127.0.0.1";
 pub const UNICAST_DST_STRING: &str = "127.0.0.1";
 
 pub const DEFAULT_ARTNET_HERTZ: u64 = 44;
 
+/// The standard ArtNet port, which nodes listen on
+pub const ARTNET_PORT: u16 = 6454;
+/// Local ports to send from, unless specified; never the standard port, which
+/// is left free for listening (discovery, merge input)
+pub const DEFAULT_UNICAST_SRC_PORT: u16 = 6453;
+pub const DEFAULT_BROADCAST_SRC_PORT: u16 = 6455;
+
+/// Default (and most common) sACN priority; receivers take the source with
+/// the highest priority, from 0 to 200
+pub const DEFAULT_SACN_PRIORITY: u8 = 100;
+
+pub const DEFAULT_TETHER_ROLE: &str = "ArtnetController";
+
+pub const DEFAULT_STATUS_INTERVAL_MS: u64 = 5000;
+/// Minimum time between macro value feedback messages, in milliseconds
+pub const MACRO_FEEDBACK_INTERVAL_MS: u64 = 50;
+
+/// The maximum (and default) number of channels in a universe
 pub const CHANNELS_PER_UNIVERSE: u16 = 512;
 
+pub const STATE_FILE_PATH: &str = "./state.json";
+
+/// Fixture definitions imported (from GDTF or OFL) while running are saved
+/// here, and loaded at startup along with the built-in ones
+pub const IMPORTED_FIXTURES_DIR: &str = "./imported_fixtures";
+
+/// How many Project edits can be undone
+pub const UNDO_LIMIT: usize = 50;
+
+/// The universe shown (and directly editable) in the GUI, and the one that
+/// channel overrides and direct channel messages apply to
+pub const DEFAULT_UNIVERSE: u16 = 0;
+
+/// The highest universe that can be output: ArtNet port addresses are 15-bit
+/// (sACN universes, numbered from 1, would allow up to 63999)
+pub const MAX_UNIVERSE: u16 = 32767;
+
+/// How DMX frames are sent to the lighting network
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputProtocol {
+    Artnet,
+    /// Streaming ACN (E1.31), multicast
+    Sacn,
+}
+
+/// How ArtNet input (e.g. from a backup console) is merged into the output
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum MergeMode {
+    /// No input is listened for
+    Off,
+    /// Highest Takes Precedence: each channel outputs the higher value
+    Htp,
+    /// Latest Takes Precedence: each channel outputs whichever source changed
+    /// it most recently
+    Ltp,
+}
+
 #[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = "Tether Artnet Controller")]
 pub struct Cli {
@@ -33,10 +88,94 @@ pub struct Cli {
     #[arg(long = "artnet.destination")]
     pub unicast_dst: Option<std::net::IpAddr>,
 
-    /// Update frequency, in Hertz, for sending ArtNet data (gets converted to ms)
-    #[arg(long = "artnet.freq", default_value_t=DEFAULT_ARTNET_HERTZ)]
+    /// Local UDP port to send ArtNet from; by default 6453 (unicast) or 6455
+    /// (broadcast)
+    #[arg(long = "artnet.srcPort")]
+    pub artnet_src_port: Option<u16>,
+
+    /// UDP port that destination nodes listen on, for both unicast and
+    /// broadcast (and routes given without a port)
+    #[arg(long = "artnet.dstPort", default_value_t = ARTNET_PORT)]
+    pub artnet_dst_port: u16,
+
+    /// Path to a JSON file routing universes to destination nodes, e.g.
+    /// `{ "0": "10.0.0.10", "1": "10.0.0.11:6454" }`; overrides any routes
+    /// in the Project
+    #[arg(long = "artnet.routes")]
+    pub artnet_routes_path: Option<String>,
+
+    /// Flag to send an ArtSync packet after each frame, so that nodes output
+    /// all universes simultaneously
+    #[arg(long = "artnet.sync")]
+    pub artnet_sync: bool,
+
+    /// Low-pass filter on the final output: the fraction of the difference
+    /// between the previous and new value of each channel that remains after
+    /// each frame, from 0 (off) up to (but not including) 1
+    #[arg(long = "artnet.smoothing", default_value_t = 0.)]
+    pub artnet_smoothing: f32,
+
+    /// Flag to never open a socket or send any ArtNet packets; frames are
+    /// logged (at debug level) instead
+    #[arg(long = "artnet.dryRun")]
+    pub artnet_dry_run: bool,
+
+    /// Number of channels per universe, up to 512; some nodes expect shorter
+    /// frames
+    #[arg(long = "artnet.universeSize", default_value_t = CHANNELS_PER_UNIVERSE)]
+    pub universe_size: u16,
+
+    /// Update frequency, in Hertz, for sending ArtNet data and stepping
+    /// animations (gets converted to ms). Frames are sent at this rate even
+    /// if nothing has changed, which also keeps nodes from timing out.
+    #[arg(long = "artnet.freq", default_value_t=DEFAULT_ARTNET_HERTZ, value_parser = clap::value_parser!(u64).range(1..))]
     pub artnet_update_frequency: u64,
 
+    /// Upper limit on frames sent per second; changes are then sent as soon
+    /// as allowed, while unchanged frames are only repeated at artnet.freq
+    #[arg(long = "artnet.maxFps", value_parser = clap::value_parser!(u64).range(1..))]
+    pub artnet_max_fps: Option<u64>,
+
+    /// Listen for ArtNet input (e.g. from an upstream console) on the default
+    /// universe, and merge it into the output; on its own, the flag means
+    /// Highest Takes Precedence
+    #[arg(long = "artnet.mergeInput", value_enum, default_value_t = MergeMode::Off, num_args = 0..=1, default_missing_value = "htp")]
+    pub artnet_merge_input: MergeMode,
+
+    /// Output protocol; with sACN, the ArtNet broadcast/unicast settings are
+    /// ignored
+    #[arg(long = "protocol", value_enum, default_value_t = OutputProtocol::Artnet)]
+    pub protocol: OutputProtocol,
+
+    /// sACN source priority, from 0 to 200
+    #[arg(long = "sacn.priority", default_value_t = DEFAULT_SACN_PRIORITY, value_parser = clap::value_parser!(u8).range(0..=200))]
+    pub sacn_priority: u8,
+
+    /// Listen for OSC control messages on this UDP port, e.g.
+    /// `/macro/<fixture>/<macro label> <value>`; disabled if not set
+    #[arg(long = "osc.port")]
+    pub osc_port: Option<u16>,
+
+    /// How long to wait for replies when discovering ArtNet nodes (from the
+    /// GUI), in milliseconds
+    #[arg(long = "artnet.discoveryMs", default_value_t = 2000)]
+    pub artnet_discovery_ms: u64,
+
+    /// Fade from zero to home values over this many milliseconds on startup;
+    /// zero (the default) applies home values immediately
+    #[arg(long = "startup.fadeMs", default_value_t = 0)]
+    pub startup_fade_ms: u64,
+
+    /// Save the Project file (if one is loaded) every this many seconds, if it
+    /// has changed since last saved or loaded; 0 (the default) to disable
+    #[arg(long = "project.autoSaveSecs", default_value_t = 0)]
+    pub auto_save_secs: u64,
+
+    /// Flag to save runtime state (macros active, selected macro group, last
+    /// Scene) whenever it changes, and restore it on startup
+    #[arg(long = "state.restore")]
+    pub restore_state: bool,
+
     // TODO: split tasks/commands such as "auto" into separate Clap Command
     #[arg(long = "auto.zero")]
     pub auto_zero: bool,
@@ -51,4 +190,23 @@ pub struct Cli {
     /// Host/IP for Tether MQTT Broker
     #[arg(long = "tether.host")]
     pub tether_host: Option<String>,
+
+    /// Role for this Tether Agent, i.e. the first part of all topics
+    #[arg(long = "tether.role", default_value_t = String::from(DEFAULT_TETHER_ROLE))]
+    pub tether_role: String,
+
+    /// ID (group) for this Tether Agent, e.g. to distinguish multiple
+    /// controllers using the same broker; defaults to "any"
+    #[arg(long = "tether.id")]
+    pub tether_id: Option<String>,
+
+    /// How often to publish a status (heartbeat) message on the "status"
+    /// Output Plug, in milliseconds; set to 0 to disable
+    #[arg(long = "tether.statusInterval", default_value_t = DEFAULT_STATUS_INTERVAL_MS)]
+    pub status_interval_ms: u64,
+
+    /// Publish macro values on the "macroValues" Output Plug whenever they
+    /// change (throttled), e.g. for motorised fader feedback
+    #[arg(long = "tether.publishState")]
+    pub publish_state: bool,
 }
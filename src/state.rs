@@ -0,0 +1,31 @@
+use std::fs;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+/// Runtime state which is not part of the Project, but which can be saved
+/// whenever it changes and restored on the next launch (with `--state.restore`)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistentState {
+    pub apply_macros: bool,
+    pub selected_macro_group_index: usize,
+    /// The label of the Scene applied most recently, if any
+    pub last_scene: Option<String>,
+}
+
+impl PersistentState {
+    pub fn load(path: &str) -> anyhow::Result<PersistentState> {
+        let json = fs::read_to_string(path)?;
+        let state = serde_json::from_str::<PersistentState>(&json)?;
+        info!("Loaded state from \"{}\": {:?}", path, state);
+        Ok(state)
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        debug!("Saved state to \"{}\"", path);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use indexmap::IndexMap;
+
+use crate::{
+    artnet::ArtNetInterface,
+    project::{fixture::FixtureInstance, SceneState},
+    tether_interface::RemoteMacroFeedback,
+};
+
+/// Heartbeat published periodically on Tether, so that a dashboard can show
+/// which controllers are alive and what they are doing
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusMessage {
+    pub agent_id: String,
+    pub uptime_secs: u64,
+    /// The label of the Scene applied most recently, if any
+    pub current_scene: Option<String>,
+    /// ArtNet frames actually sent per second, averaged since the previous
+    /// status message
+    pub output_fps: f32,
+    pub artnet_connected: bool,
+    pub artnet_dry_run: bool,
+}
+
+/// Keeps track of frames sent and decides when the next status message is
+/// due; driven by the update loop, so no extra thread is required
+pub struct StatusPublisher {
+    interval: Duration,
+    started: Instant,
+    last_published: Instant,
+    frames_since_last: u32,
+}
+
+impl StatusPublisher {
+    pub fn new(interval: Duration) -> Self {
+        let now = Instant::now();
+        StatusPublisher {
+            interval,
+            started: now,
+            last_published: now,
+            frames_since_last: 0,
+        }
+    }
+
+    /// Call once for every ArtNet frame actually sent
+    pub fn count_frame(&mut self) {
+        self.frames_since_last += 1;
+    }
+
+    /// Returns a status message if one is due, otherwise None
+    pub fn poll(
+        &mut self,
+        agent_id: &str,
+        current_scene: Option<&str>,
+        artnet: Option<&ArtNetInterface>,
+    ) -> Option<StatusMessage> {
+        let elapsed = self.last_published.elapsed();
+        if elapsed < self.interval {
+            return None;
+        }
+        let output_fps = self.frames_since_last as f32 / elapsed.as_secs_f32();
+        self.last_published = Instant::now();
+        self.frames_since_last = 0;
+        Some(StatusMessage {
+            agent_id: String::from(agent_id),
+            uptime_secs: self.started.elapsed().as_secs(),
+            current_scene: current_scene.map(String::from),
+            output_fps,
+            artnet_connected: artnet.is_some(),
+            artnet_dry_run: artnet.map(|a| a.is_dry_run()).unwrap_or(false),
+        })
+    }
+}
+
+/// Tracks the macro values last published, so that only changes are sent,
+/// no more often than the given interval
+pub struct MacroFeedbackPublisher {
+    interval: Duration,
+    last_published: Instant,
+    last_values: IndexMap<String, SceneState>,
+}
+
+impl MacroFeedbackPublisher {
+    pub fn new(interval: Duration) -> Self {
+        MacroFeedbackPublisher {
+            interval,
+            last_published: Instant::now(),
+            last_values: IndexMap::new(),
+        }
+    }
+
+    /// Returns the macro values which changed since last time, if any, and
+    /// if the interval has passed; otherwise None
+    pub fn poll(&mut self, fixtures: &[FixtureInstance]) -> Option<Vec<RemoteMacroFeedback>> {
+        if self.last_published.elapsed() < self.interval {
+            return None;
+        }
+        let mut changes = Vec::new();
+        for fixture in fixtures.iter() {
+            let state = fixture.current_state();
+            let previous = self.last_values.get(&fixture.label);
+            for (macro_label, value) in state.iter() {
+                if previous.and_then(|p| p.get(macro_label)) != Some(value) {
+                    changes.push(RemoteMacroFeedback {
+                        fixture_label: String::from(&fixture.label),
+                        macro_label: String::from(macro_label),
+                        value: value.clone(),
+                    });
+                }
+            }
+            self.last_values.insert(String::from(&fixture.label), state);
+        }
+        if changes.is_empty() {
+            return None;
+        }
+        self.last_published = Instant::now();
+        Some(changes)
+    }
+
+    /// Forget the values published so far, so that every fixture's values
+    /// are published afresh next time, and fixtures which no longer exist
+    /// are not kept around
+    pub fn clear(&mut self) {
+        self.last_values.clear();
+    }
+}
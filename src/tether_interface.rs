@@ -5,15 +5,22 @@ use std::{
         Arc, Mutex,
     },
     thread::{sleep, spawn},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::anyhow;
 use egui::Color32;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use tether_agent::{PlugOptionsBuilder, TetherAgentOptionsBuilder};
 
+use crate::{
+    animation::{Easing, RepeatMode},
+    colour::ColourSpace,
+    project::{SceneState, SceneValue},
+    status::StatusMessage,
+};
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TetherNotePayload {
     pub channel: u8,
@@ -37,62 +44,395 @@ pub struct TetherKnobPayload {
 #[derive(Serialize, Deserialize, Debug)]
 pub enum RemoteMacroValue {
     ControlValue(u8),
+    /// Relative adjustment to a Control Macro, e.g. from an endless encoder;
+    /// the result is clamped to 0-255
+    ControlDelta(i16),
     ColourValue(Color32),
+    /// Name of a colour in the Project palette, applied to a Colour Macro
+    ColourName(String),
+    /// Colour temperature in Kelvin, applied to a Colour Macro
+    Kelvin(u16),
+    /// Slot label, applied to a Wheel Macro
+    Slot(String),
+}
+
+impl RemoteMacroValue {
+    /// The variant name, as sent in messages, e.g. for errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RemoteMacroValue::ControlValue(_) => "ControlValue",
+            RemoteMacroValue::ControlDelta(_) => "ControlDelta",
+            RemoteMacroValue::ColourValue(_) => "ColourValue",
+            RemoteMacroValue::ColourName(_) => "ColourName",
+            RemoteMacroValue::Kelvin(_) => "Kelvin",
+            RemoteMacroValue::Slot(_) => "Slot",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoteMacroMessage {
-    /// If no fixtures specified, assume all
+    /// If no fixtures specified, assume all; group labels are also accepted
     pub fixture_labels: Option<Vec<String>>,
+    /// A single component of a Colour Macro can be targeted (with a Control
+    /// Value) by adding a suffix, e.g. "Colour.r"
     pub macro_label: String,
     /// Start value will be "whatever the current value is";
     /// so `target_value` is the End value
     pub value: RemoteMacroValue,
     /// Animation duration in ms
     pub ms: Option<u64>,
+    /// Animation duration in beats, used instead of `ms` if a tempo is set
+    pub beats: Option<f32>,
+    /// For Colour Macro animations: RGB (the default) or HSV
+    #[serde(default)]
+    pub colour_space: Option<ColourSpace>,
+    /// Delay the animation for each successive matching fixture by this many
+    /// ms, for a chase/wave effect; only applies when animated (`ms` or `beats`)
+    #[serde(default)]
+    pub stagger_ms: Option<u64>,
+    /// Loop or ping-pong the animation until stopped, instead of running once
+    #[serde(default)]
+    pub repeat: Option<RepeatMode>,
+    /// Easing curve for the animation; SineInOut if not specified
+    #[serde(default)]
+    pub easing: Option<Easing>,
+}
+
+/// Stop any animation (e.g. a looping one) on the matching macros, leaving
+/// them at whatever value they had reached
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStopAnimationMessage {
+    /// If no fixtures specified, assume all; group labels are also accepted
+    pub fixture_labels: Option<Vec<String>>,
+    pub macro_label: String,
+}
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteMacroBatchMessage {
+    pub fixture_label: String,
+    /// Pairs of macro label and value, all applied on the same frame
+    pub values: Vec<(String, RemoteMacroValue)>,
+    /// Animation duration in ms, applied to all values
+    pub ms: Option<u64>,
 }
+
 #[derive(Debug)]
 pub enum TetherMidiMessage {
     /// Already-encoded payload
-    // Raw(Vec<u8>),
+    Raw(Vec<u8>),
     NoteOn(TetherNotePayload),
-    // NoteOff(TetherNotePayload),
+    NoteOff(TetherNotePayload),
     ControlChange(TetherControlChangePayload),
     Knob(TetherKnobPayload),
 }
 
+impl TetherMidiMessage {
+    /// Decode a raw MIDI message into a Note or Control Change event, if it is
+    /// one of those; anything else (SysEx, clock, etc.) gives None.
+    pub fn from_raw(bytes: &[u8]) -> Option<TetherMidiMessage> {
+        let [status, data1, data2, ..] = *bytes else {
+            return None;
+        };
+        let channel = status & 0x0F;
+        let (data1, data2) = (data1 & 0x7F, data2 & 0x7F);
+        let note = TetherNotePayload {
+            channel,
+            note: data1,
+            velocity: data2,
+        };
+        match status & 0xF0 {
+            // NoteOn with zero velocity is a NoteOff, by convention
+            0x90 if data2 > 0 => Some(TetherMidiMessage::NoteOn(note)),
+            0x80 | 0x90 => Some(TetherMidiMessage::NoteOff(note)),
+            0xB0 => Some(TetherMidiMessage::ControlChange(
+                TetherControlChangePayload {
+                    channel,
+                    controller: data1,
+                    value: data2,
+                },
+            )),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoteSceneMessage {
     pub scene_label: String,
     pub ms: Option<u64>,
-    /// If no fixtures specified, assume all
+    /// Transition duration in beats, used instead of `ms` if a tempo is set
+    pub beats: Option<f32>,
+    /// Delay the animation for each successive fixture by this many ms
+    pub stagger_ms: Option<u64>,
+    /// If no fixtures specified, assume all; group labels are also accepted
     pub fixture_labels: Option<Vec<String>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CueListCommand {
+    /// Play from the first cue
+    Start,
+    Stop,
+    Next,
+    Previous,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteCueListMessage {
+    pub cue_list_label: String,
+    pub command: CueListCommand,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteReleaseSceneMessage {
+    pub scene_label: String,
+    /// Release (fade out) duration in ms; if none, return home immediately
+    pub ms: Option<u64>,
+    /// Release duration in beats, used instead of `ms` if a tempo is set
+    pub beats: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePresetMessage {
+    pub fixture_label: String,
+    pub preset_label: String,
+}
+
+/// Switch a fixture to another of its modes, by name (not case-sensitive);
+/// the fixture label may use `*` as a wildcard, to switch several at once
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFixtureModeMessage {
+    pub fixture_label: String,
+    pub mode_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoteChannelMessage {
+    /// Channel number, in the range 1-512
+    pub channel: u16,
+    pub value: u8,
+}
+
+/// Direct (raw DMX) channel control, bypassing macros
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RemoteChannelControlMessage {
+    /// A single channel, e.g. `{ "channel": 1, "value": 255 }`
+    Single(RemoteChannelMessage),
+    /// Values for every channel in the universe, in order; length must
+    /// match the universe size
+    Buffer(Vec<u8>),
+}
+
+/// Read-only summary of the Project, for external controllers which need to
+/// know which fixtures, macros and scenes exist
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteProjectState {
+    pub fixtures: Vec<RemoteFixtureState>,
+    pub scene_labels: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFixtureState {
+    pub label: String,
+    pub config_name: String,
+    pub enabled: bool,
+    /// Current values of all macros in the active mode
+    pub macros: SceneState,
+}
+
+/// A single macro value which has changed, published so that remote
+/// controllers can keep their own UI (e.g. motorised faders) in sync
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteMacroFeedback {
+    pub fixture_label: String,
+    pub macro_label: String,
+    pub value: SceneValue,
+}
+
+/// Messages queued by the Model, to be published by the Tether thread
+pub enum TetherOutgoingMessage {
+    Status(StatusMessage),
+    ProjectState(RemoteProjectState),
+    MacroValues(Vec<RemoteMacroFeedback>),
+}
+
 pub enum RemoteControlMessage {
     Midi(TetherMidiMessage),
     MacroAnimation(RemoteMacroMessage),
+    MacroBatch(RemoteMacroBatchMessage),
     SceneAnimation(RemoteSceneMessage),
+    /// Fade the fixtures in a Scene back to home values
+    ReleaseScene(RemoteReleaseSceneMessage),
+    /// Start, stop or step through a Cue List
+    CueList(RemoteCueListMessage),
+    StopAnimation(RemoteStopAnimationMessage),
+    /// Enable or disable ("freeze") macros being applied to the output
+    SetApplyMacros(bool),
+    /// Output all zeros (or stop doing so), without changing any macros
+    Blackout(bool),
+    /// Scale all Intensity channels (0-255), without changing any macros
+    GrandMaster(u8),
+    /// Override a single channel directly, bypassing macros
+    SetChannel(RemoteChannelMessage),
+    /// Cancel everything and return to home values; see `Model::reset_all`
+    Reset,
+    /// Apply a named preset to a single fixture
+    Preset(RemotePresetMessage),
+    /// Switch a fixture's mode, changing its channel footprint
+    FixtureMode(RemoteFixtureModeMessage),
+    /// Write directly into the channels state, bypassing (and freezing) macros
+    Channel(RemoteChannelControlMessage),
+    /// Set every fixture with a Colour Macro to the same colour
+    MasterColour(Color32),
+    /// Publish a `RemoteProjectState` in response; changes nothing
+    ProjectStateRequest,
+    /// Switch to the Project file at the given path
+    LoadProject(String),
+    /// Set the tempo in BPM, used for durations given in beats; zero (or less)
+    /// clears it
+    SetTempo(f32),
 }
 
+/// Single topic for all message types, dispatched according to the `type`
+/// field in the payload; see `parse_typed_message`
+const TYPED_CONTROL_TOPIC: &str = "artnet/+/control";
+
+#[derive(Deserialize)]
+struct TypedMessageHeader {
+    r#type: String,
+}
+
+/// For message types whose payload is a single value, e.g. `applyMacros`
+#[derive(Deserialize)]
+struct TypedValueMessage<T> {
+    value: T,
+}
+
+/// Parse a message received on the typed control topic. The payload must be
+/// a map with a `type` field, named the same as the equivalent dedicated plug
+/// (e.g. "scenes"), plus the fields for that message type.
+fn parse_typed_message(payload: &[u8]) -> anyhow::Result<RemoteControlMessage> {
+    let header = rmp_serde::from_slice::<TypedMessageHeader>(payload)
+        .map_err(|e| anyhow!("Missing or invalid \"type\" field: {}", e))?;
+    let m = match header.r#type.as_str() {
+        "notesOn" => {
+            RemoteControlMessage::Midi(TetherMidiMessage::NoteOn(rmp_serde::from_slice(payload)?))
+        }
+        "controlChange" => RemoteControlMessage::Midi(TetherMidiMessage::ControlChange(
+            rmp_serde::from_slice(payload)?,
+        )),
+        "notesOff" => {
+            RemoteControlMessage::Midi(TetherMidiMessage::NoteOff(rmp_serde::from_slice(payload)?))
+        }
+        "raw" => RemoteControlMessage::Midi(TetherMidiMessage::Raw(
+            rmp_serde::from_slice::<TypedValueMessage<Vec<u8>>>(payload)?.value,
+        )),
+        "knobs" => {
+            RemoteControlMessage::Midi(TetherMidiMessage::Knob(rmp_serde::from_slice(payload)?))
+        }
+        "macros" => RemoteControlMessage::MacroAnimation(rmp_serde::from_slice(payload)?),
+        "stopAnimations" => RemoteControlMessage::StopAnimation(rmp_serde::from_slice(payload)?),
+        "macroBatches" => RemoteControlMessage::MacroBatch(rmp_serde::from_slice(payload)?),
+        "scenes" => RemoteControlMessage::SceneAnimation(rmp_serde::from_slice(payload)?),
+        "releaseScenes" => RemoteControlMessage::ReleaseScene(rmp_serde::from_slice(payload)?),
+        "cueLists" => RemoteControlMessage::CueList(rmp_serde::from_slice(payload)?),
+        "applyMacros" => RemoteControlMessage::SetApplyMacros(
+            rmp_serde::from_slice::<TypedValueMessage<bool>>(payload)?.value,
+        ),
+        "blackout" => RemoteControlMessage::Blackout(
+            rmp_serde::from_slice::<TypedValueMessage<bool>>(payload)?.value,
+        ),
+        "grandMaster" => RemoteControlMessage::GrandMaster(
+            rmp_serde::from_slice::<TypedValueMessage<u8>>(payload)?.value,
+        ),
+        "channelOverrides" => RemoteControlMessage::SetChannel(rmp_serde::from_slice(payload)?),
+        "reset" => RemoteControlMessage::Reset,
+        "stateRequest" => RemoteControlMessage::ProjectStateRequest,
+        "loadProject" => RemoteControlMessage::LoadProject(
+            rmp_serde::from_slice::<TypedValueMessage<String>>(payload)?.value,
+        ),
+        "tempo" => RemoteControlMessage::SetTempo(
+            rmp_serde::from_slice::<TypedValueMessage<f32>>(payload)?.value,
+        ),
+        "presets" => RemoteControlMessage::Preset(rmp_serde::from_slice(payload)?),
+        "fixtureModes" => RemoteControlMessage::FixtureMode(rmp_serde::from_slice(payload)?),
+        "channelControl" => RemoteControlMessage::Channel(RemoteChannelControlMessage::Single(
+            rmp_serde::from_slice(payload)?,
+        )),
+        "masterColour" => RemoteControlMessage::MasterColour(
+            rmp_serde::from_slice::<TypedValueMessage<Color32>>(payload)?.value,
+        ),
+        unknown => return Err(anyhow!("Unknown message type \"{}\"", unknown)),
+    };
+    Ok(m)
+}
+
+/// How often to try reconnecting, if the connection to the broker is lost
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(3);
+
 pub struct TetherInterface {
     pub message_rx: Receiver<RemoteControlMessage>,
+    /// Reports changes in connection state from the Tether thread: false when
+    /// the connection is lost (and reconnection attempts begin), true when
+    /// reconnected
+    pub connection_rx: Receiver<bool>,
     pub quit_channel: (Sender<()>, Receiver<()>),
     // ---
     message_tx: Sender<RemoteControlMessage>,
+    connection_tx: Sender<bool>,
+    /// Only available once connected
+    outgoing_tx: Option<Sender<TetherOutgoingMessage>>,
+    /// The ID (group) the Tether Agent actually connected with
+    agent_id: Option<String>,
 }
 
 impl TetherInterface {
     pub fn new() -> Self {
         let (message_tx, message_rx) = sync::mpsc::channel();
         let (quit_tx, quit_rx) = sync::mpsc::channel();
+        let (connection_tx, connection_rx) = sync::mpsc::channel();
 
         TetherInterface {
             message_tx,
             message_rx,
+            connection_tx,
+            connection_rx,
             quit_channel: (quit_tx, quit_rx),
+            outgoing_tx: None,
+            agent_id: None,
+        }
+    }
+
+    /// The ID the Tether Agent connected with (which defaults to "any" if
+    /// none was given); None until connected
+    pub fn agent_id(&self) -> Option<&str> {
+        self.agent_id.as_deref()
+    }
+
+    /// For other inputs (e.g. OSC) to feed control messages into the same
+    /// channel as Tether
+    pub fn message_sender(&self) -> Sender<RemoteControlMessage> {
+        self.message_tx.clone()
+    }
+
+    /// Queue a message to be published by the Tether thread; ignored if not
+    /// connected
+    pub fn publish(&self, m: TetherOutgoingMessage) {
+        if let Some(outgoing_tx) = &self.outgoing_tx {
+            outgoing_tx.send(m).ok();
         }
     }
 
@@ -100,13 +440,18 @@ impl TetherInterface {
         &mut self,
         should_quit: Arc<Mutex<bool>>,
         tether_host: Option<&str>,
+        tether_role: &str,
+        tether_id: Option<&str>,
     ) -> Result<(), anyhow::Error> {
         info!("Attempt to connect Tether Agent...");
 
-        if let Ok(tether_agent) = TetherAgentOptionsBuilder::new("ArtnetController")
+        if let Ok(tether_agent) = TetherAgentOptionsBuilder::new(tether_role)
+            .id(tether_id)
             .host(tether_host)
             .build()
         {
+            self.agent_id = Some(String::from(tether_agent.id()));
+
             let input_midi_cc = PlugOptionsBuilder::create_input("controlChange")
                 .build(&tether_agent)
                 .expect("failed to create Input Plug");
@@ -115,6 +460,14 @@ impl TetherInterface {
                 .build(&tether_agent)
                 .expect("failed to create Input Plug");
 
+            let input_midi_notes_off = PlugOptionsBuilder::create_input("notesOff")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_midi_raw = PlugOptionsBuilder::create_input("raw")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
             let input_midi_kobs = PlugOptionsBuilder::create_input("knobs")
                 .build(&tether_agent)
                 .expect("failed to create Input Plug");
@@ -123,25 +476,175 @@ impl TetherInterface {
                 .build(&tether_agent)
                 .expect("failed to create Input Plug");
 
+            let input_macro_batches = PlugOptionsBuilder::create_input("macroBatches")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
             let input_scenes = PlugOptionsBuilder::create_input("scenes")
                 .build(&tether_agent)
                 .expect("failed to create Input Plug");
 
+            let input_release_scenes = PlugOptionsBuilder::create_input("releaseScenes")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_blackout = PlugOptionsBuilder::create_input("blackout")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_grand_master = PlugOptionsBuilder::create_input("grandMaster")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_stop_animations = PlugOptionsBuilder::create_input("stopAnimations")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_cue_lists = PlugOptionsBuilder::create_input("cueLists")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_apply_macros = PlugOptionsBuilder::create_input("applyMacros")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_channel_overrides = PlugOptionsBuilder::create_input("channelOverrides")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_reset = PlugOptionsBuilder::create_input("reset")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_presets = PlugOptionsBuilder::create_input("presets")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_channel_control = PlugOptionsBuilder::create_input("channelControl")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_typed_control = PlugOptionsBuilder::create_input("control")
+                .topic(Some(TYPED_CONTROL_TOPIC))
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_master_colour = PlugOptionsBuilder::create_input("masterColour")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let output_status = PlugOptionsBuilder::create_output("status")
+                .build(&tether_agent)
+                .expect("failed to create Output Plug");
+
+            let input_load_project = PlugOptionsBuilder::create_input("loadProject")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_fixture_modes = PlugOptionsBuilder::create_input("fixtureModes")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_tempo = PlugOptionsBuilder::create_input("tempo")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let input_state_requests = PlugOptionsBuilder::create_input("stateRequest")
+                .build(&tether_agent)
+                .expect("failed to create Input Plug");
+
+            let output_state = PlugOptionsBuilder::create_output("state")
+                .build(&tether_agent)
+                .expect("failed to create Output Plug");
+
+            let output_macro_values = PlugOptionsBuilder::create_output("macroValues")
+                .build(&tether_agent)
+                .expect("failed to create Output Plug");
+
+            let (outgoing_tx, outgoing_rx) = sync::mpsc::channel::<TetherOutgoingMessage>();
+            self.outgoing_tx = Some(outgoing_tx);
+
             let tx = self.message_tx.clone();
+            let connection_tx = self.connection_tx.clone();
 
             spawn(move || {
+                let all_inputs = [
+                    &input_midi_cc,
+                    &input_midi_notes,
+                    &input_midi_notes_off,
+                    &input_midi_raw,
+                    &input_midi_kobs,
+                    &input_macros,
+                    &input_macro_batches,
+                    &input_scenes,
+                    &input_release_scenes,
+                    &input_cue_lists,
+                    &input_stop_animations,
+                    &input_apply_macros,
+                    &input_blackout,
+                    &input_grand_master,
+                    &input_channel_overrides,
+                    &input_reset,
+                    &input_presets,
+                    &input_channel_control,
+                    &input_typed_control,
+                    &input_master_colour,
+                    &input_state_requests,
+                    &input_tempo,
+                    &input_load_project,
+                    &input_fixture_modes,
+                ];
+                let mut is_connected = true;
+                let mut last_reconnect_attempt: Option<Instant> = None;
+
                 while !*should_quit.lock().unwrap() {
+                    if !tether_agent.is_connected() {
+                        if is_connected {
+                            warn!("Tether connection lost; will attempt to reconnect...");
+                            is_connected = false;
+                            connection_tx.send(false).ok();
+                        }
+                        if last_reconnect_attempt
+                            .map(|t| t.elapsed() >= RECONNECT_INTERVAL)
+                            .unwrap_or(true)
+                        {
+                            last_reconnect_attempt = Some(Instant::now());
+                            match tether_agent.connect() {
+                                Ok(()) => {
+                                    for plug in all_inputs.iter() {
+                                        if let Err(e) =
+                                            tether_agent.client().subscribe(plug.topic(), 1)
+                                        {
+                                            error!(
+                                                "Failed to resubscribe \"{}\": {}",
+                                                plug.name(),
+                                                e
+                                            );
+                                        }
+                                    }
+                                    info!("...Tether reconnected and resubscribed OK");
+                                    is_connected = true;
+                                    connection_tx.send(true).ok();
+                                }
+                                Err(e) => warn!("...Tether reconnect failed: {}", e),
+                            }
+                        }
+                        sleep(Duration::from_millis(100));
+                        continue;
+                    }
                     while let Some((topic, message)) = tether_agent.check_messages() {
                         if input_midi_cc.matches(&topic) {
-                            // debug!("MIDI CC");
-                            // let m = rmp_serde::from_slice::<TetherControlChangePayload>(
-                            //     message.payload(),
-                            // )
-                            // .unwrap();
-                            // tx.send(RemoteControlMessage::Midi(
-                            //     TetherMidiMessage::ControlChange(m),
-                            // ))
-                            // .expect("failed to send from Tether Interface thread")
+                            debug!("MIDI CC");
+                            match rmp_serde::from_slice::<TetherControlChangePayload>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Midi(
+                                        TetherMidiMessage::ControlChange(m),
+                                    ))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Control Change message: {}", e),
+                            }
                         }
                         if input_midi_notes.matches(&topic) {
                             debug!("MIDI Note");
@@ -150,6 +653,24 @@ impl TetherInterface {
                             tx.send(RemoteControlMessage::Midi(TetherMidiMessage::NoteOn(m)))
                                 .expect("failed to send from Tether Interface thread")
                         }
+                        if input_midi_notes_off.matches(&topic) {
+                            debug!("MIDI Note Off");
+                            match rmp_serde::from_slice::<TetherNotePayload>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Midi(TetherMidiMessage::NoteOff(m)))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Note Off message: {}", e),
+                            }
+                        }
+                        if input_midi_raw.matches(&topic) {
+                            debug!("MIDI Raw");
+                            match rmp_serde::from_slice::<Vec<u8>>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Midi(TetherMidiMessage::Raw(m)))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Raw MIDI message: {}", e),
+                            }
+                        }
                         if input_macros.matches(&topic) {
                             debug!("Macro (direct) control message");
                             let m = rmp_serde::from_slice::<RemoteMacroMessage>(message.payload())
@@ -157,6 +678,17 @@ impl TetherInterface {
                             tx.send(RemoteControlMessage::MacroAnimation(m))
                                 .expect("failed to send from Tether Interface thread");
                         }
+                        if input_macro_batches.matches(&topic) {
+                            debug!("Macro batch control message");
+                            match rmp_serde::from_slice::<RemoteMacroBatchMessage>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::MacroBatch(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Macro Batch message: {}", e),
+                            }
+                        }
                         if input_scenes.matches(&topic) {
                             debug!("Remote Scene message");
                             let m = rmp_serde::from_slice::<RemoteSceneMessage>(message.payload())
@@ -164,6 +696,37 @@ impl TetherInterface {
                             tx.send(RemoteControlMessage::SceneAnimation(m))
                                 .expect("failed to send from Tether Interface thread");
                         }
+                        if input_release_scenes.matches(&topic) {
+                            debug!("Remote Release Scene message");
+                            match rmp_serde::from_slice::<RemoteReleaseSceneMessage>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::ReleaseScene(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Release Scene message: {}", e),
+                            }
+                        }
+                        if input_stop_animations.matches(&topic) {
+                            debug!("Remote Stop Animation message");
+                            match rmp_serde::from_slice::<RemoteStopAnimationMessage>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::StopAnimation(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Stop Animation message: {}", e),
+                            }
+                        }
+                        if input_cue_lists.matches(&topic) {
+                            debug!("Remote Cue List message");
+                            match rmp_serde::from_slice::<RemoteCueListMessage>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::CueList(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Cue List message: {}", e),
+                            }
+                        }
                         if input_midi_kobs.matches(&topic) {
                             debug!("Remote Knobs (MIDI) message");
                             let m = rmp_serde::from_slice::<TetherKnobPayload>(message.payload())
@@ -171,6 +734,137 @@ impl TetherInterface {
                             tx.send(RemoteControlMessage::Midi(TetherMidiMessage::Knob(m)))
                                 .expect("failed to send from Tether Interface thread");
                         }
+                        if input_apply_macros.matches(&topic) {
+                            debug!("Remote Apply Macros message");
+                            match rmp_serde::from_slice::<bool>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::SetApplyMacros(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Apply Macros message: {}", e),
+                            }
+                        }
+                        if input_blackout.matches(&topic) {
+                            debug!("Remote Blackout message");
+                            match rmp_serde::from_slice::<bool>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Blackout(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Blackout message: {}", e),
+                            }
+                        }
+                        if input_grand_master.matches(&topic) {
+                            debug!("Remote Grand Master message");
+                            match rmp_serde::from_slice::<u8>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::GrandMaster(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Grand Master message: {}", e),
+                            }
+                        }
+                        if input_channel_overrides.matches(&topic) {
+                            debug!("Remote Channel Override message");
+                            match rmp_serde::from_slice::<RemoteChannelMessage>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::SetChannel(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Channel Override message: {}", e),
+                            }
+                        }
+                        if input_reset.matches(&topic) {
+                            // Payload (if any) is ignored
+                            debug!("Remote Reset message");
+                            tx.send(RemoteControlMessage::Reset)
+                                .expect("failed to send from Tether Interface thread");
+                        }
+                        if input_presets.matches(&topic) {
+                            debug!("Remote Preset message");
+                            match rmp_serde::from_slice::<RemotePresetMessage>(message.payload()) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Preset(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Preset message: {}", e),
+                            }
+                        }
+                        if input_channel_control.matches(&topic) {
+                            debug!("Remote Channel Control message");
+                            match rmp_serde::from_slice::<RemoteChannelControlMessage>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::Channel(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Channel Control message: {}", e),
+                            }
+                        }
+                        if input_master_colour.matches(&topic) {
+                            debug!("Remote Master Colour message");
+                            match rmp_serde::from_slice::<Color32>(message.payload()) {
+                                Ok(colour) => tx
+                                    .send(RemoteControlMessage::MasterColour(colour))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Master Colour message: {}", e),
+                            }
+                        }
+                        if input_load_project.matches(&topic) {
+                            debug!("Remote Load Project message");
+                            match rmp_serde::from_slice::<String>(message.payload()) {
+                                Ok(path) => tx
+                                    .send(RemoteControlMessage::LoadProject(path))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Load Project message: {}", e),
+                            }
+                        }
+                        if input_fixture_modes.matches(&topic) {
+                            debug!("Remote Fixture Mode message");
+                            match rmp_serde::from_slice::<RemoteFixtureModeMessage>(
+                                message.payload(),
+                            ) {
+                                Ok(m) => tx
+                                    .send(RemoteControlMessage::FixtureMode(m))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Fixture Mode message: {}", e),
+                            }
+                        }
+                        if input_tempo.matches(&topic) {
+                            debug!("Remote Tempo message");
+                            match rmp_serde::from_slice::<f32>(message.payload()) {
+                                Ok(bpm) => tx
+                                    .send(RemoteControlMessage::SetTempo(bpm))
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid Tempo message: {}", e),
+                            }
+                        }
+                        if input_state_requests.matches(&topic) {
+                            // Payload (if any) is ignored
+                            debug!("Remote State Request message");
+                            tx.send(RemoteControlMessage::ProjectStateRequest)
+                                .expect("failed to send from Tether Interface thread");
+                        }
+                        if input_typed_control.matches(&topic) {
+                            debug!("Remote typed control message");
+                            match parse_typed_message(message.payload()) {
+                                Ok(m) => tx
+                                    .send(m)
+                                    .expect("failed to send from Tether Interface thread"),
+                                Err(e) => error!("Invalid typed control message: {}", e),
+                            }
+                        }
+                    }
+                    while let Ok(m) = outgoing_rx.try_recv() {
+                        let result = match m {
+                            TetherOutgoingMessage::Status(status) => {
+                                tether_agent.encode_and_publish(&output_status, status)
+                            }
+                            TetherOutgoingMessage::ProjectState(state) => {
+                                tether_agent.encode_and_publish(&output_state, state)
+                            }
+                            TetherOutgoingMessage::MacroValues(values) => {
+                                tether_agent.encode_and_publish(&output_macro_values, values)
+                            }
+                        };
+                        if let Err(e) = result {
+                            error!("Failed to publish message: {}", e);
+                        }
                     }
                     sleep(Duration::from_millis(1));
                 }
@@ -0,0 +1,52 @@
+use egui::{DragValue, Grid, Ui};
+use log::error;
+
+use crate::model::Model;
+
+pub fn render_channel_overrides(model: &mut Model, ui: &mut Ui) {
+    ui.heading("Channel Overrides");
+
+    ui.horizontal(|ui| {
+        let (channel, value) = &mut model.new_channel_override;
+        ui.label("Channel");
+        ui.add(
+            DragValue::new(channel)
+                .clamp_range(1..=model.universe_size)
+                .speed(1),
+        );
+        ui.label("Value");
+        ui.add(DragValue::new(value).clamp_range(0..=255).speed(1));
+        if ui.button("Override").clicked() {
+            let (channel, value) = model.new_channel_override;
+            if let Err(e) = model.set_channel_override(channel, value) {
+                error!("Failed to set channel override: {}", e);
+            }
+        }
+    });
+
+    if model.channel_overrides.is_empty() {
+        ui.small("No channels overridden");
+    } else {
+        let mut clear_channel = None;
+        Grid::new("channel_overrides")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (channel, value) in model.channel_overrides.iter() {
+                    ui.label(format!("Channel #{}", channel));
+                    ui.label(value.to_string());
+                    if ui.button("🗑").clicked() {
+                        clear_channel = Some(*channel);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(channel) = clear_channel {
+            model.clear_channel_override(channel);
+        }
+        if ui.button("Clear all").clicked() {
+            model.clear_channel_overrides();
+        }
+    }
+
+    ui.separator();
+}
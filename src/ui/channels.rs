@@ -0,0 +1,111 @@
+use egui::{Color32, RichText, ScrollArea, Slider, Ui};
+use log::error;
+
+use crate::{model::Model, settings::DEFAULT_UNIVERSE};
+
+const FADERS_PER_ROW: usize = 16;
+const FADER_WIDTH: f32 = 44.;
+
+/// One fader per channel of the default universe, for poking channels
+/// directly (e.g. testing a dimmer pack). Moving a fader overrides that
+/// channel, so that macros don't immediately overwrite it; click the channel
+/// number to release it again, back to its macro (or Scene) value.
+pub fn render_channels(model: &mut Model, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.heading(format!("Channels (universe {})", DEFAULT_UNIVERSE));
+        ui.label(RichText::new("■ patched").color(Color32::GREEN));
+        ui.label(RichText::new("■ unpatched").color(Color32::GRAY));
+        ui.label(RichText::new("■ overridden").color(Color32::LIGHT_RED));
+        if !model.channel_overrides.is_empty() && ui.button("Release all").clicked() {
+            model.clear_channel_overrides();
+        }
+    });
+    ui.separator();
+
+    // Which fixture (if any) each channel is patched to, for hover text
+    let mut owners: Vec<Option<&str>> = vec![None; model.channels_state.len()];
+    for fixture in model
+        .project
+        .fixtures
+        .iter()
+        .filter(|f| f.universe == DEFAULT_UNIVERSE)
+    {
+        for m in fixture.config.active_mode.mappings.iter() {
+            if let Some(owner) = owners.get_mut(fixture.channel_index(m.channel)) {
+                *owner = Some(&fixture.label);
+            }
+        }
+    }
+
+    let channel_count = model.universe_size.min(model.channels_state.len() as u16) as usize;
+    let mut set_override: Option<(u16, u8)> = None;
+    let mut release: Option<u16> = None;
+
+    ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            ui.spacing_mut().slider_width = 120.;
+            for (row, row_owners) in owners[..channel_count].chunks(FADERS_PER_ROW).enumerate() {
+                ui.horizontal(|ui| {
+                    for (n, owner) in row_owners.iter().enumerate() {
+                        let i = row * FADERS_PER_ROW + n;
+                        let channel = i as u16 + 1;
+                        let is_overridden = model.channel_overrides.contains_key(&channel);
+                        let colour = if is_overridden {
+                            Color32::LIGHT_RED
+                        } else if model.channels_assigned.get(i).copied().unwrap_or(false) {
+                            Color32::GREEN
+                        } else {
+                            Color32::GRAY
+                        };
+                        ui.vertical_centered(|ui| {
+                            ui.set_width(FADER_WIDTH);
+                            let number = ui
+                                .selectable_label(
+                                    is_overridden,
+                                    RichText::new(channel.to_string()).color(colour),
+                                )
+                                .on_hover_text(match (*owner, is_overridden) {
+                                    (Some(owner), true) => {
+                                        format!("{}; overridden, click to release", owner)
+                                    }
+                                    (Some(owner), false) => String::from(owner),
+                                    (None, true) => String::from("Overridden, click to release"),
+                                    (None, false) => String::from("Unpatched"),
+                                });
+                            if number.clicked() && is_overridden {
+                                release = Some(channel);
+                            }
+                            // Overrides only apply to the output, on top of the state
+                            let mut value = model
+                                .channel_overrides
+                                .get(&channel)
+                                .copied()
+                                .unwrap_or(model.channels_state[i]);
+                            if ui
+                                .add(
+                                    Slider::new(&mut value, 0..=255)
+                                        .vertical()
+                                        .show_value(false),
+                                )
+                                .changed()
+                            {
+                                set_override = Some((channel, value));
+                            }
+                            ui.small(value.to_string());
+                        });
+                    }
+                });
+                ui.add_space(8.);
+            }
+        });
+
+    if let Some((channel, value)) = set_override {
+        if let Err(e) = model.set_channel_override(channel, value) {
+            error!("Failed to set channel override: {}", e);
+        }
+    }
+    if let Some(channel) = release {
+        model.clear_channel_override(channel);
+    }
+}
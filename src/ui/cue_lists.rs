@@ -0,0 +1,147 @@
+use egui::{Color32, DragValue, Grid, RichText, Ui};
+
+use crate::{
+    model::Model,
+    project::{Cue, CueList},
+};
+
+enum CueAction {
+    Go(usize),
+    Previous,
+    Stop,
+    JumpTo(usize, usize),
+    DeleteCueList(usize),
+}
+
+pub fn render_cue_lists(model: &mut Model, ui: &mut Ui) {
+    let mut action: Option<CueAction> = None;
+
+    let scene_labels: Vec<String> = model
+        .project
+        .scenes
+        .iter()
+        .map(|s| String::from(&s.label))
+        .collect();
+    let playing = model
+        .cue_playback
+        .as_ref()
+        .map(|p| (p.cue_list_index, p.cue_index));
+
+    for (list_index, cue_list) in model.project.cue_lists.iter_mut().enumerate() {
+        let is_playing = playing.is_some_and(|(l, _)| l == list_index);
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut cue_list.label);
+                ui.checkbox(&mut cue_list.looping, "Loop");
+                if ui
+                    .button(RichText::new("GO").strong().color(Color32::LIGHT_GREEN))
+                    .on_hover_text("Start, or go to the next cue if already playing")
+                    .clicked()
+                {
+                    action = Some(CueAction::Go(list_index));
+                }
+                if is_playing {
+                    if ui.button("◀").on_hover_text("Previous cue").clicked() {
+                        action = Some(CueAction::Previous);
+                    }
+                    if ui.button("⏹").on_hover_text("Stop").clicked() {
+                        action = Some(CueAction::Stop);
+                    }
+                }
+                if ui.button("🗑").clicked() {
+                    action = Some(CueAction::DeleteCueList(list_index));
+                }
+            });
+
+            let mut delete_cue: Option<usize> = None;
+            Grid::new(format!("cue-list-{}", list_index))
+                .num_columns(5)
+                .show(ui, |ui| {
+                    for (cue_index, cue) in cue_list.cues.iter_mut().enumerate() {
+                        let is_current = playing == Some((list_index, cue_index));
+                        let number = RichText::new(format!("{}", cue_index + 1));
+                        if ui
+                            .selectable_label(is_current, number)
+                            .on_hover_text("Jump to this cue")
+                            .clicked()
+                        {
+                            action = Some(CueAction::JumpTo(list_index, cue_index));
+                        }
+                        egui::ComboBox::from_id_source(format!(
+                            "cue-list-{}-{}",
+                            list_index, cue_index
+                        ))
+                        .selected_text(&cue.scene_label)
+                        .show_ui(ui, |ui| {
+                            for label in scene_labels.iter() {
+                                ui.selectable_value(&mut cue.scene_label, label.clone(), label);
+                            }
+                        });
+                        ui.add(
+                            DragValue::new(&mut cue.fade_ms)
+                                .speed(10)
+                                .prefix("fade ")
+                                .suffix("ms"),
+                        );
+                        ui.add(
+                            DragValue::new(&mut cue.hold_ms)
+                                .speed(10)
+                                .prefix("hold ")
+                                .suffix("ms"),
+                        )
+                        .on_hover_text("0 = wait for GO");
+                        if ui.button("🗑").clicked() {
+                            delete_cue = Some(cue_index);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(cue_index) = delete_cue {
+                cue_list.cues.remove(cue_index);
+            }
+
+            if let Some(first_scene) = scene_labels.first() {
+                if ui.button("+ Add Cue").clicked() {
+                    let scene_label = model
+                        .last_scene
+                        .clone()
+                        .unwrap_or_else(|| first_scene.clone());
+                    cue_list.cues.push(Cue {
+                        scene_label,
+                        fade_ms: 1000,
+                        hold_ms: 0,
+                    });
+                }
+            }
+        });
+    }
+
+    if ui.button("+ Add Cue List").clicked() {
+        let label = format!("Cue List {}", model.project.cue_lists.len());
+        model.project.cue_lists.push(CueList {
+            label,
+            cues: Vec::new(),
+            looping: false,
+        });
+    }
+
+    match action {
+        Some(CueAction::Go(list_index)) => {
+            if playing.is_some_and(|(l, _)| l == list_index) {
+                model.next_cue();
+            } else {
+                model.start_cue_list(list_index);
+            }
+        }
+        Some(CueAction::Previous) => model.previous_cue(),
+        Some(CueAction::Stop) => model.stop_cue_list(),
+        Some(CueAction::JumpTo(list_index, cue_index)) => model.go_to_cue(list_index, cue_index),
+        Some(CueAction::DeleteCueList(list_index)) => {
+            if playing.is_some_and(|(l, _)| l == list_index) {
+                model.stop_cue_list();
+            }
+            model.project.cue_lists.remove(list_index);
+        }
+        None => {}
+    }
+}
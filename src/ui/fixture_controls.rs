@@ -1,11 +1,20 @@
-use egui::{DragValue, Grid, ScrollArea, Slider, Ui};
-use log::debug;
+use egui::{Color32, DragValue, Grid, RichText, ScrollArea, Slider, Ui};
+use log::{debug, error};
 
-use crate::model::Model;
+use crate::{
+    model::Model,
+    project::{
+        fixture::{FixtureInstance, FixtureMacro},
+        FixtureGroup, SceneValue,
+    },
+    settings::{DEFAULT_UNIVERSE, MAX_UNIVERSE},
+    tether_interface::{RemoteMacroMessage, RemoteMacroValue},
+};
 
 pub fn render_fixture_controls(model: &mut Model, ui: &mut Ui) {
     ui.heading("Fixtures");
     ui.separator();
+    let mut fixture_added = false;
     ScrollArea::vertical()
         .auto_shrink([false, false])
         .show(ui, |ui| {
@@ -14,11 +23,36 @@ pub fn render_fixture_controls(model: &mut Model, ui: &mut Ui) {
                     if let Some(new_fixture) = &mut model.new_fixture_to_add {
                         // -------- Edit some options and add to project (or cancel)
                         ui.text_edit_singleline(&mut new_fixture.label);
+                        ui.horizontal(|ui| {
+                            ui.label("Mode:");
+                            let mut selected_mode_index = new_fixture.mode_index;
+                            egui::ComboBox::from_id_source("new_fixture_mode")
+                                .selected_text(&new_fixture.config.active_mode.name)
+                                .show_ui(ui, |ui| {
+                                    for (mode_index, mode) in
+                                        new_fixture.config.modes.iter().enumerate()
+                                    {
+                                        ui.selectable_value(
+                                            &mut selected_mode_index,
+                                            mode_index,
+                                            &mode.name,
+                                        );
+                                    }
+                                });
+                            if selected_mode_index != new_fixture.mode_index {
+                                new_fixture.mode_index = selected_mode_index;
+                                let config = new_fixture.config.clone();
+                                if let Err(e) = new_fixture.set_config(&config) {
+                                    error!("Failed to change fixture mode: {}", e);
+                                }
+                            }
+                        });
                         ui.horizontal(|ui| {
                             ui.label("Offset channels:");
+                            let max_offset = new_fixture.max_offset(model.universe_size);
                             ui.add(
                                 DragValue::new(&mut new_fixture.offset_channels)
-                                    .clamp_range(0..=255)
+                                    .clamp_range(0..=max_offset)
                                     .speed(1),
                             );
                         });
@@ -28,6 +62,7 @@ pub fn render_fixture_controls(model: &mut Model, ui: &mut Ui) {
                                 debug!("Adding new fixture to project");
                                 model.project.fixtures.insert(0, new_fixture.clone());
                                 model.adding_new_fixture = false;
+                                fixture_added = true;
                             }
                             if ui.button("Cancel 🗙").clicked() {
                                 model.adding_new_fixture = false;
@@ -48,6 +83,27 @@ pub fn render_fixture_controls(model: &mut Model, ui: &mut Ui) {
                                 }
                             });
                         }
+                        if ui
+                            .button("Import…")
+                            .on_hover_text(
+                                "Add a fixture type from a GDTF file or an Open Fixture Library JSON file",
+                            )
+                            .clicked()
+                        {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("GDTF or OFL", &["gdtf", "json"])
+                                .pick_file()
+                            {
+                                match model.import_fixture_config(&path.display().to_string()) {
+                                    Ok(config) => model.new_fixture_to_add = Some((&config).into()),
+                                    Err(e) => error!(
+                                        "Failed to import fixture from \"{}\": {}",
+                                        path.display(),
+                                        e
+                                    ),
+                                }
+                            }
+                        }
                         if ui.button("Cancel 🗙").clicked() {
                             model.adding_new_fixture = false;
                             model.new_fixture_to_add = None;
@@ -60,47 +116,233 @@ pub fn render_fixture_controls(model: &mut Model, ui: &mut Ui) {
             }
             if !model.project.fixtures.is_empty() {
                 ui.separator();
+                render_groups(model, ui);
+                ui.separator();
+                if !model.selected_fixtures.is_empty() {
+                    render_bulk_edit(model, ui);
+                    ui.separator();
+                }
                 fixture_controls_in_project(model, ui);
             }
         });
+    if fixture_added {
+        model.clear_macro_feedback();
+        model.update_channels_assigned();
+    }
 }
 
 fn fixture_controls_in_project(model: &mut Model, ui: &mut Ui) {
     let mut remove_index = None;
+    let mut reload_index = None;
+    let mut change_mode: Option<(usize, usize)> = None;
+    let mut offsets_changed = false;
+    let mut enabled_changed_index = None;
+    let mut apply_preset: Option<(String, String)> = None;
+    let mut save_preset_index = None;
+    let mut clicked_index = None;
+    let mut colour_changed = false;
+
+    let conflicts = model.project.validate();
+    if !conflicts.is_empty() {
+        ui.label(
+            RichText::new(format!("⚠ Patch problems: {}", conflicts.len()))
+                .color(Color32::LIGHT_RED),
+        )
+        .on_hover_text(
+            conflicts
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join("\n"),
+        );
+        ui.separator();
+    }
 
     for (i, fixture) in model.project.fixtures.iter_mut().enumerate() {
         let config = &fixture.config;
         // ----------------
         ui.horizontal(|ui| {
-            ui.heading(&fixture.label);
+            let is_selected = model.selected_fixtures.contains(&fixture.label);
+            if ui
+                .selectable_label(
+                    is_selected,
+                    RichText::new(&fixture.label)
+                        .heading()
+                        .color(if fixture.enabled {
+                            Color32::WHITE
+                        } else {
+                            Color32::GRAY
+                        }),
+                )
+                .on_hover_text("Click to select; Ctrl/Shift-click to select several")
+                .clicked()
+            {
+                clicked_index = Some(i);
+            }
+            if ui
+                .checkbox(&mut fixture.enabled, "Enabled")
+                .on_hover_text("Disabled fixtures output zero, and ignore macros and scenes")
+                .changed()
+            {
+                enabled_changed_index = Some(i);
+            }
             if ui.button("🗑").clicked() {
                 remove_index = Some(i);
             }
+            let fixture_conflicts: Vec<String> = conflicts
+                .iter()
+                .filter(|x| x.involves(&fixture.label))
+                .map(|x| x.to_string())
+                .collect();
+            if !fixture_conflicts.is_empty() {
+                ui.label(RichText::new("⚠ Patch").color(Color32::LIGHT_RED))
+                    .on_hover_text(fixture_conflicts.join("\n"));
+            }
+            if ui
+                .button("⟳")
+                .on_hover_text("Reload fixture definition from file")
+                .clicked()
+            {
+                reload_index = Some(i);
+            }
         });
         // ----------------
         ui.horizontal(|ui| {
             ui.label((config.name).to_string());
             ui.hyperlink_to("Reference/manual", &config.reference);
         });
+        egui::CollapsingHeader::new(if fixture.notes.is_empty() {
+            "Notes"
+        } else {
+            "Notes 📝"
+        })
+        .id_source(format!("notes_{}", i))
+        .show(ui, |ui| {
+            ui.text_edit_multiline(&mut fixture.notes);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Presets:");
+            for preset_label in fixture.presets.keys() {
+                if ui.button(preset_label).clicked() {
+                    apply_preset = Some((fixture.label.clone(), preset_label.clone()));
+                }
+            }
+            if ui
+                .button("+")
+                .on_hover_text("Save current macro values as a new preset")
+                .clicked()
+            {
+                save_preset_index = Some(i);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Mode:");
+            let mut selected_mode_index = fixture.mode_index;
+            egui::ComboBox::from_id_source(format!("mode_{}", i))
+                .selected_text(&config.active_mode.name)
+                .show_ui(ui, |ui| {
+                    for (mode_index, mode) in config.modes.iter().enumerate() {
+                        ui.selectable_value(&mut selected_mode_index, mode_index, &mode.name);
+                    }
+                });
+            if selected_mode_index != fixture.mode_index {
+                change_mode = Some((i, selected_mode_index));
+            }
+        });
         // ----------------
+        let max_offset = fixture.max_offset(model.universe_size);
         ui.horizontal(|ui| {
             ui.label("Offset channels:");
-            ui.add(
-                DragValue::new(&mut fixture.offset_channels)
-                    .clamp_range(0..=255)
-                    .speed(1),
-            );
+            if ui
+                .add(
+                    DragValue::new(&mut fixture.offset_channels)
+                        .clamp_range(0..=max_offset)
+                        .speed(1),
+                )
+                .changed()
+            {
+                offsets_changed = true;
+            }
+            if ui
+                .add_enabled(fixture.offset_channels > 0, egui::Button::new("-1"))
+                .clicked()
+            {
+                fixture.offset_channels -= 1;
+                offsets_changed = true;
+            }
+            if ui
+                .add_enabled(
+                    fixture.offset_channels < max_offset,
+                    egui::Button::new("+1"),
+                )
+                .clicked()
+            {
+                fixture.offset_channels += 1;
+                offsets_changed = true;
+            }
+            ui.label("Address:");
+            let mut address = fixture.offset_channels + 1;
+            if ui
+                .add(
+                    DragValue::new(&mut address)
+                        .clamp_range(1..=max_offset + 1)
+                        .speed(1),
+                )
+                .changed()
+            {
+                fixture.offset_channels = address - 1;
+                offsets_changed = true;
+            }
         });
 
         // ----------------
-        let current_mode = &config.modes[fixture.mode_index];
+        let current_mode = match config.modes.get(fixture.mode_index) {
+            Some(_) => &config.active_mode,
+            None => {
+                ui.label(
+                    RichText::new(format!("No mode with index {}", fixture.mode_index))
+                        .color(Color32::RED),
+                );
+                ui.separator();
+                continue;
+            }
+        };
         ui.heading("Mappings");
+        ui.horizontal(|ui| {
+            ui.label("Universe:");
+            if ui
+                .add(DragValue::new(&mut fixture.universe).clamp_range(0..=MAX_UNIVERSE))
+                .changed()
+            {
+                offsets_changed = true;
+            }
+        });
+        let channels_state = if fixture.universe == DEFAULT_UNIVERSE {
+            &mut model.channels_state
+        } else {
+            model
+                .extra_universes
+                .entry(fixture.universe)
+                .or_insert_with(|| [0].repeat(model.universe_size as usize))
+        };
 
         Grid::new(format!("mappings_{}", i))
             .num_columns(3)
             .show(ui, |ui| {
                 for m in &current_mode.mappings {
-                    let channel_index = m.channel + fixture.offset_channels - 1;
+                    let channel_index = fixture.channel_index(m.channel);
+                    if channel_index >= channels_state.len() {
+                        ui.label(&m.label);
+                        ui.label(
+                            RichText::new(format!(
+                                "Channel #{} beyond universe",
+                                channel_index.wrapping_add(1)
+                            ))
+                            .color(Color32::RED),
+                        );
+                        ui.end_row();
+                        continue;
+                    }
                     ui.horizontal(|ui| {
                         ui.label(&m.label);
                         if let Some(notes) = &m.notes {
@@ -110,12 +352,13 @@ fn fixture_controls_in_project(model: &mut Model, ui: &mut Ui) {
                                 notes
                             ));
                         }
+                        if let Some([min, max]) = fixture.channel_limits.get(&m.channel) {
+                            ui.label(RichText::new("⚠").color(Color32::LIGHT_RED))
+                                .on_hover_text(format!("Output limited to {}-{}", min, max));
+                        }
                     });
                     if ui
-                        .add(Slider::new(
-                            &mut model.channels_state[(channel_index) as usize],
-                            0..=255,
-                        ))
+                        .add(Slider::new(&mut channels_state[channel_index], 0..=255))
                         .changed()
                     {
                         model.apply_macros = false;
@@ -124,8 +367,8 @@ fn fixture_controls_in_project(model: &mut Model, ui: &mut Ui) {
                         ui.label("Mode/Programme:");
                         let current_range = range_sections.iter().find(|x| {
                             let [start, end] = x.range;
-                            model.channels_state[(channel_index) as usize] >= start
-                                && model.channels_state[(channel_index) as usize] <= end
+                            channels_state[channel_index] >= start
+                                && channels_state[channel_index] <= end
                         });
                         match current_range {
                             Some(r) => {
@@ -141,10 +384,277 @@ fn fixture_controls_in_project(model: &mut Model, ui: &mut Ui) {
                     ui.end_row();
                 }
             });
+        // Much quicker than setting each colour channel's slider in turn
+        for m in fixture.config.active_mode.macros.iter_mut() {
+            if let FixtureMacro::Colour(colour_macro) = m {
+                ui.horizontal(|ui| {
+                    ui.label(&colour_macro.label);
+                    ui.add_enabled_ui(colour_macro.animation.is_none(), |ui| {
+                        if ui
+                            .color_edit_button_srgba(&mut colour_macro.current_value)
+                            .changed()
+                        {
+                            colour_macro.kelvin = None;
+                            colour_changed = true;
+                        }
+                    });
+                });
+            }
+        }
         ui.separator();
     }
+    if colour_changed {
+        model.apply_macros = true;
+    }
+    if let Some(index) = clicked_index {
+        select_fixture(model, index, ui);
+    }
     if let Some(index) = remove_index {
         debug!("Delete fixture with index {}", index);
         model.project.fixtures.remove(index);
+        model.clear_macro_feedback();
+        offsets_changed = true;
+    }
+    if offsets_changed {
+        model.update_channels_assigned();
+    }
+    if let Some(fixture) = save_preset_index.and_then(|i| model.project.fixtures.get_mut(i)) {
+        let label = format!("Preset {}", fixture.presets.len() + 1);
+        debug!("Save {} for fixture {}", &label, &fixture.label);
+        let state = fixture.current_state();
+        fixture.presets.insert(label, state);
+    }
+    if let Some((fixture_label, preset_label)) = apply_preset {
+        if let Err(e) = model.apply_preset(&fixture_label, &preset_label) {
+            error!("Failed to apply preset: {}", e);
+        }
+    }
+    if let Some(index) = enabled_changed_index {
+        model.apply_fixture_home_values(index);
+    }
+    if let Some((index, mode_index)) = change_mode {
+        if let Err(e) = model.set_fixture_mode(index, mode_index) {
+            error!("Failed to change fixture mode: {}", e);
+        }
+    }
+    if let Some(index) = reload_index {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("text", &["json"])
+            .set_directory("./fixtures")
+            .pick_file()
+        {
+            if let Err(e) = model.reload_fixture_config(index, &path.display().to_string()) {
+                error!(
+                    "Failed to reload fixture config from \"{}\": {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Update the fixture selection after a click on a fixture heading: a plain
+/// click selects only that fixture (or clears it, if it was the only one
+/// selected), Ctrl/Cmd toggles it, and Shift selects a range
+pub(super) fn select_fixture(model: &mut Model, index: usize, ui: &Ui) {
+    let modifiers = ui.input(|i| i.modifiers);
+    let label = String::from(&model.project.fixtures[index].label);
+    if modifiers.shift {
+        let anchor = model.selection_anchor.unwrap_or(index);
+        for fixture in model
+            .project
+            .fixtures
+            .iter()
+            .take(anchor.max(index) + 1)
+            .skip(anchor.min(index))
+        {
+            model.selected_fixtures.insert(String::from(&fixture.label));
+        }
+        return;
+    }
+    if modifiers.command {
+        if !model.selected_fixtures.shift_remove(&label) {
+            model.selected_fixtures.insert(label);
+        }
+    } else if model.selected_fixtures.len() == 1 && model.selected_fixtures.contains(&label) {
+        model.selected_fixtures.clear();
+    } else {
+        model.selected_fixtures.clear();
+        model.selected_fixtures.insert(label);
+    }
+    model.selection_anchor = Some(index);
+}
+
+/// Named groups of fixtures: clicking a group selects its fixtures, so that
+/// macros can be set for the whole group via the bulk edit controls
+pub(super) fn render_groups(model: &mut Model, ui: &mut Ui) {
+    let mut select_index = None;
+    let mut delete_index = None;
+    egui::CollapsingHeader::new(format!("Groups ({})", model.project.groups.len()))
+        .id_source("fixture_groups")
+        .show(ui, |ui| {
+            for (i, group) in model.project.groups.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let is_selected = !group.fixture_labels.is_empty()
+                        && model.selected_fixtures.len() == group.fixture_labels.len()
+                        && group
+                            .fixture_labels
+                            .iter()
+                            .all(|x| model.selected_fixtures.contains(x));
+                    if ui
+                        .selectable_label(is_selected, "Select")
+                        .on_hover_text(group.fixture_labels.join(", "))
+                        .clicked()
+                    {
+                        select_index = Some(i);
+                    }
+                    ui.text_edit_singleline(&mut group.label);
+                    ui.label(format!("{} fixtures", group.fixture_labels.len()));
+                    if ui.button("🗑").clicked() {
+                        delete_index = Some(i);
+                    }
+                });
+            }
+            if ui
+                .add_enabled(
+                    !model.selected_fixtures.is_empty(),
+                    egui::Button::new("+ Group from selection"),
+                )
+                .clicked()
+            {
+                let label = format!("Group {}", model.project.groups.len() + 1);
+                model.project.groups.push(FixtureGroup {
+                    label,
+                    fixture_labels: model.selected_fixtures.iter().cloned().collect(),
+                });
+            }
+        });
+    if let Some(group) = select_index.and_then(|i| model.project.groups.get(i)) {
+        model.selected_fixtures = group.fixture_labels.iter().cloned().collect();
+        model.selection_anchor = None;
+    }
+    if let Some(i) = delete_index {
+        model.project.groups.remove(i);
+    }
+}
+
+/// One control per macro shared by all the selected fixtures (same label and
+/// type). The first selected fixture's value is shown; changes are applied
+/// to every selected fixture, as if by a remote macro message.
+pub(super) fn render_bulk_edit(model: &mut Model, ui: &mut Ui) {
+    let selected_fixtures: Vec<&FixtureInstance> = model
+        .project
+        .fixtures
+        .iter()
+        .filter(|f| model.selected_fixtures.contains(&f.label))
+        .collect();
+
+    let mut clear_selection = false;
+    ui.horizontal(|ui| {
+        ui.heading(format!("{} Selected", selected_fixtures.len()));
+        clear_selection = ui.button("Clear").clicked();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Fade");
+        ui.add(
+            DragValue::new(&mut model.bulk_fade_ms)
+                .speed(10)
+                .suffix("ms"),
+        );
+        ui.label("Stagger");
+        ui.add_enabled(
+            model.bulk_fade_ms > 0,
+            DragValue::new(&mut model.bulk_stagger_ms)
+                .speed(10)
+                .suffix("ms"),
+        )
+        .on_hover_text("Delay each successive fixture, for a chase (needs a fade)");
+    });
+
+    // Macro label, current value (of the first fixture), and any wheel slots
+    let mut shared_macros: Vec<(String, SceneValue, Vec<String>)> = Vec::new();
+    if let Some((first, others)) = selected_fixtures.split_first() {
+        let current_state = first.current_state();
+        for m in first.config.active_mode.macros.iter().filter(|m| {
+            others.iter().all(|f| {
+                f.config.active_mode.macros.iter().any(|other| {
+                    other.label().eq_ignore_ascii_case(m.label())
+                        && std::mem::discriminant(other) == std::mem::discriminant(*m)
+                })
+            })
+        }) {
+            let slots = match m {
+                FixtureMacro::Wheel(wheel_macro) => wheel_macro
+                    .slots
+                    .iter()
+                    .map(|s| String::from(&s.label))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if let Some(value) = current_state.get(m.label()) {
+                shared_macros.push((String::from(m.label()), value.clone(), slots));
+            }
+        }
+    }
+
+    let mut change: Option<(String, RemoteMacroValue)> = None;
+    if shared_macros.is_empty() {
+        ui.label("No macros shared by all selected fixtures");
+    } else {
+        Grid::new("bulk_edit").num_columns(2).show(ui, |ui| {
+            for (macro_label, value, slots) in shared_macros {
+                ui.label(&macro_label);
+                match value {
+                    SceneValue::ControlValue(mut value) => {
+                        if ui.add(Slider::new(&mut value, 0..=255)).changed() {
+                            change = Some((macro_label, RemoteMacroValue::ControlValue(value)));
+                        }
+                    }
+                    SceneValue::ColourValue(mut colour) => {
+                        if ui.color_edit_button_srgba(&mut colour).changed() {
+                            change = Some((macro_label, RemoteMacroValue::ColourValue(colour)));
+                        }
+                    }
+                    SceneValue::Slot(slot_label) => {
+                        egui::ComboBox::from_id_source(format!("bulk_wheel_{}", &macro_label))
+                            .selected_text(&slot_label)
+                            .show_ui(ui, |ui| {
+                                for slot in slots {
+                                    if ui.selectable_label(slot == slot_label, &slot).clicked() {
+                                        change = Some((
+                                            String::from(&macro_label),
+                                            RemoteMacroValue::Slot(slot),
+                                        ));
+                                    }
+                                }
+                            });
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    if clear_selection {
+        model.selected_fixtures.clear();
+    }
+    if let Some((macro_label, value)) = change {
+        model.apply_macros = true;
+        model.handle_macro_message(RemoteMacroMessage {
+            fixture_labels: Some(model.selected_fixtures.iter().cloned().collect()),
+            macro_label,
+            value,
+            ms: if model.bulk_fade_ms > 0 {
+                Some(model.bulk_fade_ms)
+            } else {
+                None
+            },
+            beats: None,
+            colour_space: None,
+            stagger_ms: Some(model.bulk_stagger_ms),
+            repeat: None,
+            easing: None,
+        });
     }
 }
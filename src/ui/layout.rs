@@ -0,0 +1,123 @@
+use egui::{Align2, Color32, FontId, Rect, Sense, Stroke, Ui, Vec2};
+
+use crate::{
+    model::Model,
+    project::fixture::{FixtureInstance, FixtureMacro},
+};
+
+use super::fixture_controls::select_fixture;
+
+const NODE_RADIUS: f32 = 16.;
+/// Fixtures which have not been placed yet are lined up in rows of this many
+const UNPLACED_PER_ROW: usize = 8;
+
+/// Where to draw a fixture which has no position yet, as (x, y) from 0 to 1
+fn default_position(index: usize) -> (f32, f32) {
+    let column = index % UNPLACED_PER_ROW;
+    let row = index / UNPLACED_PER_ROW;
+    (
+        (column as f32 + 0.5) / UNPLACED_PER_ROW as f32,
+        0.1 + row as f32 * 0.1,
+    )
+}
+
+/// The fixture's current colour (from its first Colour Macro, if it has one)
+fn node_colour(fixture: &FixtureInstance) -> Color32 {
+    if !fixture.enabled {
+        return Color32::from_gray(40);
+    }
+    fixture
+        .config
+        .active_mode
+        .macros
+        .iter()
+        .find_map(|m| match m {
+            FixtureMacro::Colour(c) => Some(c.current_value),
+            _ => None,
+        })
+        .unwrap_or(Color32::GRAY)
+}
+
+/// A plan of the stage, with each fixture drawn (in its current colour) where
+/// it is in the physical rig. Drag fixtures to position them; click to select,
+/// as in the fixture list.
+pub fn render_layout(model: &mut Model, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.heading("Layout");
+        ui.label("Drag fixtures into place; click (Ctrl/Shift+click) to select");
+    });
+    ui.separator();
+
+    let (canvas, painter) = ui.allocate_painter(ui.available_size(), Sense::click());
+    let area = canvas.rect.shrink(NODE_RADIUS);
+    painter.rect_filled(canvas.rect, 4., Color32::from_gray(16));
+
+    let mut clicked: Option<usize> = None;
+    for (index, fixture) in model.project.fixtures.iter_mut().enumerate() {
+        let (x, y) = fixture.position.unwrap_or_else(|| default_position(index));
+        let centre = area.min + Vec2::new(x, y) * area.size();
+        let node = ui.interact(
+            Rect::from_center_size(centre, Vec2::splat(NODE_RADIUS * 2.)),
+            ui.id().with(("layout_fixture", index)),
+            Sense::click_and_drag(),
+        );
+
+        let centre = if node.dragged() {
+            let moved = (centre + node.drag_delta()).clamp(area.min, area.max);
+            let relative = (moved - area.min) / area.size();
+            fixture.position = Some((relative.x, relative.y));
+            moved
+        } else {
+            centre
+        };
+        if node.clicked() {
+            clicked = Some(index);
+        }
+
+        let is_selected = model.selected_fixtures.contains(&fixture.label);
+        let outline = if is_selected {
+            Stroke::new(3., Color32::LIGHT_BLUE)
+        } else if node.hovered() {
+            Stroke::new(2., Color32::WHITE)
+        } else {
+            Stroke::new(1., Color32::DARK_GRAY)
+        };
+        painter.circle(centre, NODE_RADIUS, node_colour(fixture), outline);
+        painter.text(
+            centre + Vec2::new(0., NODE_RADIUS + 2.),
+            Align2::CENTER_TOP,
+            &fixture.label,
+            FontId::proportional(12.),
+            if fixture.enabled {
+                Color32::LIGHT_GRAY
+            } else {
+                Color32::DARK_GRAY
+            },
+        );
+        node.on_hover_text(format!(
+            "{} (universe {}, channel {})",
+            &fixture.config_name,
+            fixture.universe,
+            fixture.offset_channels + 1
+        ));
+    }
+
+    match clicked {
+        Some(index) => select_fixture(model, index, ui),
+        None => {
+            if canvas.clicked() {
+                model.selected_fixtures.clear();
+            }
+        }
+    }
+
+    if model.project.fixtures.is_empty() {
+        painter.text(
+            canvas.rect.center(),
+            Align2::CENTER_CENTER,
+            "No fixtures in this Project yet",
+            FontId::proportional(16.),
+            Color32::GRAY,
+        );
+    }
+}
@@ -1,25 +1,88 @@
-use egui::{Color32, Grid, RichText, ScrollArea, Slider, Ui};
+use egui::{Color32, DragValue, Grid, RichText, ScrollArea, Slider, Ui};
 
 use crate::{
-    artnet::{random, zero},
     model::Model,
-    project::fixture::FixtureMacro,
+    project::{fixture::FixtureMacro, midiconfig::MidiConfig},
 };
 
+use super::{render_colour_temperature, render_palette, render_wheel_slots};
+
 pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
     ui.horizontal(|ui| {
         ui.heading("All");
         if ui.button("HOME").clicked() {
             model.apply_macros = false;
+            model.startup_fade = None;
             model.apply_home_values();
         }
         if ui.button("ZERO").clicked() {
             model.apply_macros = false;
-            zero(&mut model.channels_state);
+            model.startup_fade = None;
+            model.zero_all();
         }
         if ui.button("RANDOM").clicked() {
             model.apply_macros = false;
-            random(&mut model.channels_state);
+            model.startup_fade = None;
+            model.random_all();
+        }
+        if ui
+            .button(RichText::new("RESET ALL").color(Color32::LIGHT_RED))
+            .on_hover_text("Cancel all animations and overrides; macros and channels back to home")
+            .clicked()
+        {
+            model.reset_all();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.heading("Master");
+        let mut colour = model.master_colour;
+        if ui
+            .color_edit_button_srgba(&mut colour)
+            .on_hover_text("Set every fixture with a Colour Macro to this colour")
+            .changed()
+        {
+            model.apply_macros = true;
+            model.set_all_colour(colour);
+        }
+        let mut use_tempo = model.current_bpm.is_some();
+        if ui
+            .checkbox(&mut use_tempo, "Tempo")
+            .on_hover_text("Used for fades given in beats")
+            .changed()
+        {
+            model.current_bpm = if use_tempo { Some(120.) } else { None };
+        }
+        if let Some(bpm) = &mut model.current_bpm {
+            ui.add(DragValue::new(bpm).clamp_range(20.0..=300.0).suffix(" BPM"));
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Grand Master");
+        ui.add(Slider::new(&mut model.grand_master, 0..=255))
+            .on_hover_text("Scales every channel with the Intensity role, on output only");
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Gradient");
+        let (colour_a, colour_b) = &mut model.gradient_colours;
+        ui.color_edit_button_srgba(colour_a);
+        ui.color_edit_button_srgba(colour_b);
+        if ui
+            .button("Gradient")
+            .on_hover_text("Fill a gradient across all fixtures with a Colour Macro, in order")
+            .clicked()
+        {
+            let (colour_a, colour_b) = model.gradient_colours;
+            let group: Vec<String> = model
+                .project
+                .fixtures
+                .iter()
+                .map(|f| String::from(&f.label))
+                .collect();
+            model.apply_macros = true;
+            model.apply_gradient(&group, colour_a, colour_b);
         }
     });
 
@@ -27,11 +90,13 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
 
     ui.horizontal(|ui| {
         ui.heading("Macros");
-        ui.label(if model.apply_macros {
+        let status_text = if model.apply_macros {
             RichText::new("active").color(Color32::DARK_GREEN)
         } else {
             RichText::new("inactive").color(Color32::GRAY)
-        });
+        };
+        ui.toggle_value(&mut model.apply_macros, status_text)
+            .on_hover_text("Disable to freeze output on current channel values");
     });
 
     ScrollArea::vertical()
@@ -46,8 +111,17 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
                     {
                         model.selected_macro_group_index = i;
                     }
-                    ui.heading(&fixture.label);
+                    ui.horizontal(|ui| {
+                        ui.heading(&fixture.label);
+                        if !fixture.notes.is_empty() {
+                            ui.label("📝").on_hover_text(&fixture.notes);
+                        }
+                    });
                     ui.label(&fixture.config.name);
+                    if !fixture.enabled {
+                        ui.label(RichText::new("Disabled").color(Color32::GRAY));
+                        ui.set_enabled(false);
+                    }
                     let current_mode = &mut fixture.config.active_mode;
 
                     Grid::new(format!("macros_{}", i))
@@ -61,12 +135,41 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
                                             .iter()
                                             .map(|c| c + fixture.offset_channels)
                                             .collect();
-                                        let channel_list = format!(
+                                        let mut channel_list = format!(
                                             "{:?} => {:?}",
                                             &control_macro.channels, remapped_channels
                                         );
+                                        if control_macro.output_min.is_some()
+                                            || control_macro.output_max.is_some()
+                                        {
+                                            channel_list.push_str(&format!(
+                                                "\nOutput range {}-{}",
+                                                control_macro.output_min.unwrap_or(0),
+                                                control_macro.output_max.unwrap_or(255)
+                                            ));
+                                        }
+                                        for influence in &control_macro.influences {
+                                            channel_list.push_str(&format!(
+                                                "\n{} => {} + {} x value",
+                                                influence.channel,
+                                                influence.offset,
+                                                influence.scale
+                                            ));
+                                        }
                                         ui.label(&control_macro.label).on_hover_text(channel_list);
-                                        if ui
+                                        if control_macro.is_16bit {
+                                            let mut value = control_macro.value_16bit();
+                                            if ui
+                                                .add_enabled(
+                                                    control_macro.animation.is_none(),
+                                                    Slider::new(&mut value, 0..=65535).step_by(1.0),
+                                                )
+                                                .changed()
+                                            {
+                                                control_macro.set_value_16bit(value);
+                                                model.apply_macros = true;
+                                            }
+                                        } else if ui
                                             .add_enabled(
                                                 control_macro.animation.is_none(),
                                                 Slider::new(
@@ -97,14 +200,26 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
                                     FixtureMacro::Colour(colour_macro) => {
                                         ui.label(&colour_macro.label);
                                         ui.add_enabled_ui(colour_macro.animation.is_none(), |ui| {
-                                            if ui
-                                                .color_edit_button_srgba(
-                                                    &mut colour_macro.current_value,
-                                                )
-                                                .changed()
-                                            {
-                                                model.apply_macros = true;
-                                            }
+                                            ui.horizontal(|ui| {
+                                                if ui
+                                                    .color_edit_button_srgba(
+                                                        &mut colour_macro.current_value,
+                                                    )
+                                                    .changed()
+                                                {
+                                                    model.apply_macros = true;
+                                                }
+                                                if render_colour_temperature(colour_macro, ui) {
+                                                    model.apply_macros = true;
+                                                }
+                                                if render_palette(
+                                                    colour_macro,
+                                                    &model.project.palette,
+                                                    ui,
+                                                ) {
+                                                    model.apply_macros = true;
+                                                }
+                                            });
                                         });
                                         {};
                                         if let Some((animation, _start, _end)) =
@@ -122,8 +237,24 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
                                             ui.label("");
                                         }
                                     }
+                                    FixtureMacro::Wheel(wheel_macro) => {
+                                        ui.label(&wheel_macro.label);
+                                        let id = format!("wheel_{}_{}", i, &wheel_macro.label);
+                                        if render_wheel_slots(wheel_macro, id, ui) {
+                                            model.apply_macros = true;
+                                        }
+                                        ui.label("");
+                                    }
                                 }
 
+                                render_midi_learn(
+                                    &mut model.midi_learn_target,
+                                    &mut model.project.midi_config,
+                                    &fixture.label,
+                                    m.label(),
+                                    ui,
+                                );
+
                                 ui.end_row();
                             }
                         });
@@ -131,3 +262,38 @@ pub fn render_macro_controls(model: &mut Model, ui: &mut Ui) {
             }
         });
 }
+
+/// Bind the next incoming MIDI Control Change to this macro, or (right-click)
+/// remove any existing binding
+fn render_midi_learn(
+    learn_target: &mut Option<(String, String)>,
+    midi_config: &mut MidiConfig,
+    fixture_label: &str,
+    macro_label: &str,
+    ui: &mut Ui,
+) {
+    let is_learning = learn_target
+        .as_ref()
+        .is_some_and(|(f, m)| f == fixture_label && m == macro_label);
+    let controllers = midi_config.controllers_for(fixture_label, macro_label);
+    let text = if is_learning {
+        String::from("Learning...")
+    } else if controllers.is_empty() {
+        String::from("Learn")
+    } else {
+        format!("CC {:?}", controllers)
+    };
+    let response = ui
+        .selectable_label(is_learning, text)
+        .on_hover_text("Move a MIDI control to bind it to this macro; right-click to unbind");
+    if response.clicked() {
+        *learn_target = if is_learning {
+            None
+        } else {
+            Some((String::from(fixture_label), String::from(macro_label)))
+        };
+    }
+    if response.secondary_clicked() {
+        midi_config.unbind_macro(fixture_label, macro_label);
+    }
+}
@@ -3,19 +3,33 @@ use log::{error, info, warn};
 
 use crate::{
     artnet::ArtNetInterface,
+    colour::{kelvin_to_colour, DEFAULT_KELVIN, MAX_KELVIN, MIN_KELVIN},
     model::{BehaviourOnExit, Model},
-    project::{artnetconfig::get_artnet_interface, Project},
-    settings::CHANNELS_PER_UNIVERSE,
+    project::{
+        fixture::{ColourMacro, WheelMacro},
+        Palette, Project,
+    },
 };
 
 use self::{
-    fixture_controls::render_fixture_controls, macro_controls::render_macro_controls,
-    network_controls::render_network_controls, scenes::render_scenes,
+    channel_overrides::render_channel_overrides,
+    channels::render_channels,
+    fixture_controls::{render_bulk_edit, render_fixture_controls, render_groups},
+    layout::render_layout,
+    macro_controls::render_macro_controls,
+    network_controls::render_network_controls,
+    pixel_maps::render_pixel_maps,
+    scenes::render_scenes,
 };
 
+mod channel_overrides;
+mod channels;
+mod cue_lists;
 mod fixture_controls;
+mod layout;
 mod macro_controls;
 mod network_controls;
+mod pixel_maps;
 mod scenes;
 
 pub const NARROW_WINDOW: Vec2 = Vec2::new(800., 1024.0);
@@ -27,10 +41,14 @@ pub const WIDER_WINDOW: Vec2 = Vec2::new(1280., 900.);
 pub enum ViewMode {
     Scenes,
     Setup,
+    Layout,
+    Channels,
 }
 
 pub fn render_gui(model: &mut Model, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
-    ctx.request_repaint();
+    // Keep frames coming at (no more than) the ArtNet rate, so that output
+    // and animations are updated even without any input
+    ctx.request_repaint_after(model.time_until_next_frame());
 
     if ctx.input(|i| i.viewport().close_requested()) {
         if model.allowed_to_close {
@@ -41,12 +59,33 @@ pub fn render_gui(model: &mut Model, ctx: &eframe::egui::Context, frame: &mut ef
         }
     }
 
+    if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::B)) {
+        model.blackout = !model.blackout;
+        warn!("Blackout {}", if model.blackout { "on" } else { "off" });
+    }
+
+    if !ctx.wants_keyboard_input() {
+        // Text boxes have their own undo
+        if ctx.input_mut(|i| {
+            i.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            )
+        }) {
+            model.redo();
+        } else if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)) {
+            model.undo();
+        }
+    }
+
     render_mode_switcher(model, ctx, frame);
+    render_auto_mode_banner(model, ctx);
 
     match model.view_mode {
         ViewMode::Scenes => {
             egui::SidePanel::left("LeftPanel").show(ctx, |ui| {
                 render_network_controls(model, ui);
+                render_pixel_maps(model, ui);
                 render_macro_controls(model, ui);
             });
             egui::CentralPanel::default().show(ctx, |ui| {
@@ -56,10 +95,12 @@ pub fn render_gui(model: &mut Model, ctx: &eframe::egui::Context, frame: &mut ef
         ViewMode::Setup => {
             egui::SidePanel::left("LeftPanel").show(ctx, |ui| {
                 render_network_controls(model, ui);
+                render_pixel_maps(model, ui);
                 render_macro_controls(model, ui);
             });
 
             egui::SidePanel::right("RightPanel").show(ctx, |ui| {
+                render_channel_overrides(model, ui);
                 render_sliders(model, ui);
             });
 
@@ -67,6 +108,42 @@ pub fn render_gui(model: &mut Model, ctx: &eframe::egui::Context, frame: &mut ef
                 render_fixture_controls(model, ui);
             });
         }
+        ViewMode::Layout => {
+            egui::SidePanel::left("LeftPanel").show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    render_groups(model, ui);
+                    ui.separator();
+                    if !model.selected_fixtures.is_empty() {
+                        render_bulk_edit(model, ui);
+                    }
+                });
+            });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                render_layout(model, ui);
+            });
+        }
+        ViewMode::Channels => {
+            egui::SidePanel::left("LeftPanel").show(ctx, |ui| {
+                render_network_controls(model, ui);
+                render_channel_overrides(model, ui);
+            });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                render_channels(model, ui);
+            });
+        }
+    }
+
+    if let Some(message) = model.artnet_error.clone() {
+        egui::Window::new("ArtNet Unavailable")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(message).color(Color32::LIGHT_RED));
+                ui.label("No ArtNet will be sent until you reconnect (from the network controls) or restart with different settings.");
+                if ui.button("OK").clicked() {
+                    model.artnet_error = None;
+                }
+            });
     }
 
     if model.show_confirm_exit {
@@ -105,8 +182,13 @@ pub fn render_gui(model: &mut Model, ctx: &eframe::egui::Context, frame: &mut ef
                     );
                 });
             });
-    } else {
-        model.update();
+    } else if model.update() {
+        ctx.request_repaint();
+    }
+
+    // Only between edits, so that e.g. a whole slider drag is one undo step
+    if !ctx.input(|i| i.pointer.any_down()) && !ctx.wants_keyboard_input() {
+        model.record_undo_checkpoint();
     }
 }
 
@@ -132,12 +214,48 @@ pub fn render_mode_switcher(
                 {
                     ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(WIDER_WINDOW));
                 }
+                if ui
+                    .selectable_value(&mut model.view_mode, ViewMode::Layout, "Layout")
+                    .clicked()
+                {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(WIDER_WINDOW));
+                }
+                if ui
+                    .selectable_value(&mut model.view_mode, ViewMode::Channels, "Channels")
+                    .clicked()
+                {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(WIDER_WINDOW));
+                }
+                ui.label("|");
+                let blackout_text = if model.blackout {
+                    RichText::new("BLACKOUT").color(Color32::RED).strong()
+                } else {
+                    RichText::new("Blackout")
+                };
+                ui.toggle_value(&mut model.blackout, blackout_text)
+                    .on_hover_text("Output all zeros, keeping everything else as-is (Ctrl+B)");
+                ui.label("|");
+                if ui
+                    .add_enabled(!model.undo_stack.is_empty(), egui::Button::new("⟲"))
+                    .on_hover_text("Undo Project edit (Ctrl+Z)")
+                    .clicked()
+                {
+                    model.undo();
+                }
+                if ui
+                    .add_enabled(!model.redo_stack.is_empty(), egui::Button::new("⟳"))
+                    .on_hover_text("Redo Project edit (Ctrl+Shift+Z)")
+                    .clicked()
+                {
+                    model.redo();
+                }
                 ui.label("|");
                 if ui.button("New").clicked() {
                     // TODO: ask for confirmation first!
                     warn!("Clearing current project from memory");
                     model.project = Project::new();
                     model.current_project_path = None;
+                    model.clear_macro_feedback();
                 }
                 match &model.current_project_path {
                     Some(existing_project_path) => {
@@ -175,23 +293,12 @@ pub fn render_mode_switcher(
                         .add_filter("text", &["json"])
                         .pick_file()
                     {
-                        match Project::load(&path.display().to_string()) {
-                            Ok(p) => {
-                                model.project = p;
-                                model.current_project_path = Some(path.display().to_string());
-                                model.artnet =
-                                    match get_artnet_interface(&model.settings, &model.project) {
-                                        Ok(a) => Some(a),
-                                        Err(_e) => None,
-                                    }
-                            }
-                            Err(e) => {
-                                error!(
-                                    "Failed to load project from path \"{}\"; {:?}",
-                                    &path.display(),
-                                    e
-                                );
-                            }
+                        if let Err(e) = model.load_project(&path.display().to_string()) {
+                            error!(
+                                "Failed to load project from path \"{}\"; {:?}",
+                                &path.display(),
+                                e
+                            );
                         }
                     }
                 }
@@ -214,6 +321,28 @@ pub fn render_mode_switcher(
         });
 }
 
+/// Make it obvious when one of the "auto" test modes is overwriting channels
+pub fn render_auto_mode_banner(model: &mut Model, ctx: &eframe::egui::Context) {
+    let text = if model.settings.auto_random {
+        "⚠ Auto Random test mode: all channels are randomised every second"
+    } else if model.settings.auto_zero {
+        "⚠ Auto Zero test mode: all channels are zeroed every second"
+    } else {
+        return;
+    };
+    egui::TopBottomPanel::top("AutoModeBanner").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(text).color(Color32::YELLOW).heading());
+            if ui.button("Disable").clicked() {
+                warn!("Auto test mode disabled from GUI");
+                model.settings.auto_random = false;
+                model.settings.auto_zero = false;
+                model.auto_tick_rx = None;
+            }
+        });
+    });
+}
+
 pub fn render_sliders(model: &mut Model, ui: &mut Ui) {
     ui.heading("Global Slider Controls");
 
@@ -221,22 +350,108 @@ pub fn render_sliders(model: &mut Model, ui: &mut Ui) {
         .auto_shrink([false, false])
         .show(ui, |ui| {
             Grid::new("sliders").num_columns(2).show(ui, |ui| {
-                for i in 0..CHANNELS_PER_UNIVERSE {
-                    let text = format!("Channel #{}", i + 1);
+                let channel_count = model.universe_size.min(model.channels_state.len() as u16);
+                for i in 0..channel_count {
+                    let is_overridden = model.channel_overrides.contains_key(&(i + 1));
+                    let text = if is_overridden {
+                        format!("Channel #{} (override)", i + 1)
+                    } else {
+                        format!("Channel #{}", i + 1)
+                    };
                     let is_assigned = model.channels_assigned[i as usize];
-                    ui.label(RichText::new(text).color(if is_assigned {
+                    ui.label(RichText::new(text).color(if is_overridden {
+                        Color32::LIGHT_RED
+                    } else if is_assigned {
                         Color32::GREEN
                     } else {
                         Color32::GRAY
                     }));
-                    if ui
-                        .add(Slider::new(&mut model.channels_state[i as usize], 0..=255))
-                        .changed()
-                    {
-                        model.apply_macros = false;
-                    };
+                    match model.channel_overrides.get_mut(&(i + 1)) {
+                        Some(value) => {
+                            // Adjust the override itself; the channel state
+                            // underneath returns once it is cleared
+                            ui.add(Slider::new(value, 0..=255));
+                        }
+                        None => {
+                            if ui
+                                .add(Slider::new(&mut model.channels_state[i as usize], 0..=255))
+                                .changed()
+                            {
+                                model.apply_macros = false;
+                            };
+                        }
+                    }
                     ui.end_row();
                 }
             });
         });
 }
+
+/// Optionally control a Colour Macro via colour temperature (Kelvin) instead of
+/// RGB. Returns true if the colour was changed.
+pub fn render_colour_temperature(colour_macro: &mut ColourMacro, ui: &mut Ui) -> bool {
+    let mut changed = false;
+    let mut use_kelvin = colour_macro.kelvin.is_some();
+    if ui
+        .checkbox(&mut use_kelvin, "K")
+        .on_hover_text("Set by colour temperature")
+        .changed()
+    {
+        colour_macro.kelvin = if use_kelvin {
+            Some(DEFAULT_KELVIN)
+        } else {
+            None
+        };
+        changed = use_kelvin;
+    }
+    if let Some(kelvin) = &mut colour_macro.kelvin {
+        changed |= ui
+            .add(Slider::new(kelvin, MIN_KELVIN..=MAX_KELVIN).suffix("K"))
+            .changed();
+        if changed {
+            colour_macro.current_value = kelvin_to_colour(*kelvin, colour_macro.current_value.a());
+        }
+    }
+    changed
+}
+
+/// Set a Colour Macro to one of the named colours in the Project palette.
+/// Returns true if the colour was changed.
+pub fn render_palette(colour_macro: &mut ColourMacro, palette: &Palette, ui: &mut Ui) -> bool {
+    let mut changed = false;
+    ui.menu_button("🎨", |ui| {
+        for (name, colour) in palette.iter() {
+            if ui.button(RichText::new(name).color(*colour)).clicked() {
+                colour_macro.kelvin = None;
+                colour_macro.current_value = *colour;
+                changed = true;
+                ui.close_menu();
+            }
+        }
+    })
+    .response
+    .on_hover_text("Pick a named colour from the palette");
+    changed
+}
+
+/// Pick a Wheel Macro slot from a dropdown list. Returns true if the selection
+/// was changed.
+pub fn render_wheel_slots(
+    wheel_macro: &mut WheelMacro,
+    id: impl std::hash::Hash,
+    ui: &mut Ui,
+) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id)
+        .selected_text(wheel_macro.current_slot_label())
+        .show_ui(ui, |ui| {
+            for (index, slot) in wheel_macro.slots.iter().enumerate() {
+                let [start, end] = slot.range;
+                changed |= ui
+                    .selectable_value(&mut wheel_macro.current_value, index, &slot.label)
+                    .on_hover_text(format!("{}-{}", start, end))
+                    .changed();
+            }
+        });
+    changed
+}
@@ -1,15 +1,12 @@
-use std::{
-    net::{Ipv4Addr, SocketAddr},
-    str::FromStr,
-};
+use std::{net::Ipv4Addr, str::FromStr};
 
 use egui::{Color32, RichText, Ui};
-use log::debug;
+use log::{debug, error};
 
 use crate::{
-    artnet::{ArtNetInterface, ArtNetMode},
+    artnet::{ArtNetInterface, ArtNetOutput},
     model::{attempt_connection, Model, TetherStatus},
-    project::artnetconfig::ArtNetConfigMode,
+    project::artnetconfig::{broadcast_mode, get_artnet_routes, unicast_mode, ArtNetConfigMode},
     settings::{UNICAST_DST_STRING, UNICAST_SRC_STRING},
 };
 use anyhow::anyhow;
@@ -26,6 +23,9 @@ pub fn render_network_controls(model: &mut Model, ui: &mut Ui) {
             TetherStatus::Connected => {
                 ui.label(RichText::new("Connected").color(Color32::LIGHT_GREEN));
             }
+            TetherStatus::Reconnecting => {
+                ui.label(RichText::new("Connection lost; reconnecting...").color(Color32::YELLOW));
+            }
             TetherStatus::Errored(msg) => {
                 ui.label(RichText::new(msg).color(Color32::RED));
                 offer_tether_connect(model, ui);
@@ -37,16 +37,11 @@ pub fn render_network_controls(model: &mut Model, ui: &mut Ui) {
         let mut should_clear = false;
         ui.horizontal(|ui| {
             ui.heading("ArtNet");
-            match artnet.mode_in_use() {
-                ArtNetMode::Broadcast => {
-                    ui.label(RichText::new("Broadcast Mode").color(Color32::LIGHT_YELLOW));
-                }
-                ArtNetMode::Unicast(src, dst) => {
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new("Unicast Mode: ").color(Color32::LIGHT_GREEN));
-                        ui.small(format!("{} => {}", src, dst));
-                    });
-                }
+            if artnet.is_dry_run() {
+                ui.label(RichText::new("Dry run (not sending)").color(Color32::LIGHT_RED));
+                ui.small(artnet.describe_output());
+            } else {
+                ui.label(RichText::new(artnet.describe_output()).color(Color32::LIGHT_GREEN));
             }
             if ui.button("✏").clicked() {
                 debug!("Edit (and disable) ArtNet interface");
@@ -74,6 +69,7 @@ pub fn render_network_controls(model: &mut Model, ui: &mut Ui) {
                 "Unicast mode",
             );
         });
+        let mut should_discover = false;
         match &mut model.artnet_edit_mode {
             ArtNetConfigMode::Broadcast => (), // no settings for broadcast
             ArtNetConfigMode::Unicast(src, dst) => {
@@ -85,34 +81,76 @@ pub fn render_network_controls(model: &mut Model, ui: &mut Ui) {
                     ui.label("Destination/ArtNet IP");
                     ui.text_edit_singleline(dst);
                 });
+                ui.horizontal(|ui| {
+                    if model.discovery_rx.is_some() {
+                        ui.spinner();
+                        ui.label("Discovering nodes...");
+                    } else if ui
+                        .button("Discover")
+                        .on_hover_text("Send an ArtPoll to find ArtNet nodes on the network")
+                        .clicked()
+                    {
+                        should_discover = true;
+                    }
+                    if !model.discovered_nodes.is_empty() {
+                        egui::ComboBox::from_id_source("discovered_nodes")
+                            .selected_text("Pick a node")
+                            .show_ui(ui, |ui| {
+                                for node in model.discovered_nodes.iter() {
+                                    let address = node.address.to_string();
+                                    ui.selectable_value(
+                                        dst,
+                                        address.clone(),
+                                        format!(
+                                            "{} ({}) universes {:?}",
+                                            &node.short_name, address, &node.universes
+                                        ),
+                                    )
+                                    .on_hover_text(&node.long_name);
+                                }
+                            });
+                    }
+                });
             }
         }
+        if should_discover {
+            model.start_discovery();
+        }
         if ui.button("Apply & Connect").clicked() {
-            let new_artnet_interface: Result<ArtNetInterface, anyhow::Error> =
-                match &model.artnet_edit_mode {
-                    ArtNetConfigMode::Broadcast => ArtNetInterface::new(
-                        ArtNetMode::Broadcast,
-                        model.settings.artnet_update_frequency,
-                    ),
-                    ArtNetConfigMode::Unicast(src, dst) => {
-                        let src_parsed = Ipv4Addr::from_str(src);
-                        let dst_parsed = Ipv4Addr::from_str(dst);
-                        if src_parsed.is_ok() && dst_parsed.is_ok() {
-                            ArtNetInterface::new(
-                                ArtNetMode::Unicast(
-                                    SocketAddr::from((Ipv4Addr::from_str(src).unwrap(), 6453)),
-                                    SocketAddr::from((Ipv4Addr::from_str(dst).unwrap(), 6454)),
-                                ),
-                                model.settings.artnet_update_frequency,
-                            )
-                        } else {
-                            Err(anyhow!("Invalid IP address string"))
+            let new_artnet_output: Result<ArtNetOutput, anyhow::Error> = match &model
+                .artnet_edit_mode
+            {
+                ArtNetConfigMode::Broadcast => ArtNetOutput::new(broadcast_mode(&model.settings)),
+                ArtNetConfigMode::Unicast(src, dst) => {
+                    match (Ipv4Addr::from_str(src), Ipv4Addr::from_str(dst)) {
+                        (Ok(src), Ok(dst)) => {
+                            ArtNetOutput::new(unicast_mode(&model.settings, src.into(), dst.into()))
                         }
+                        _ => Err(anyhow!("Invalid IP address string")),
                     }
-                };
-            if let Ok(interface) = new_artnet_interface {
-                model.project.artnet_config = Some(ArtNetConfigMode::from(&interface));
-                model.artnet = Some(interface);
+                }
+            };
+            match new_artnet_output {
+                Ok(output) => {
+                    model.artnet_error = None;
+                    model.project.artnet_config = Some(ArtNetConfigMode::from(output.mode()));
+                    let output = output
+                        .with_routes(get_artnet_routes(&model.settings, &model.project))
+                        .with_sync(model.settings.artnet_sync);
+                    model.artnet = Some(
+                        ArtNetInterface::new(
+                            Box::new(output),
+                            model.settings.artnet_update_frequency,
+                        )
+                        .with_smoothing(model.settings.artnet_smoothing)
+                        .with_universe_size(model.universe_size)
+                        .with_max_fps(model.settings.artnet_max_fps),
+                    );
+                }
+                Err(e) => {
+                    error!("Could not connect ArtNet: {}", e);
+                    model.artnet_error = Some(e.to_string());
+                }
             }
         }
     }
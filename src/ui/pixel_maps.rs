@@ -0,0 +1,59 @@
+use egui::{DragValue, Ui};
+
+use crate::{model::Model, project::pixelmap::PixelEffect};
+
+pub fn render_pixel_maps(model: &mut Model, ui: &mut Ui) {
+    if model.project.pixel_maps.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new("Pixel Maps").show(ui, |ui| {
+        for (i, map) in model.project.pixel_maps.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut map.enabled, &map.label)
+                    .on_hover_text(format!(
+                        "{}x{} pixels from channel #{}",
+                        map.width,
+                        map.height,
+                        map.offset_channels + 1
+                    ));
+                let mut effect_name = map.effect.name();
+                egui::ComboBox::from_id_source(format!("pixel_effect_{}", i))
+                    .selected_text(effect_name)
+                    .show_ui(ui, |ui| {
+                        for name in PixelEffect::NAMES {
+                            ui.selectable_value(&mut effect_name, name, name);
+                        }
+                    });
+                if effect_name != map.effect.name() {
+                    map.effect = PixelEffect::from_name(effect_name);
+                }
+                match &mut map.effect {
+                    PixelEffect::Off => {}
+                    PixelEffect::Solid(colour) => {
+                        ui.color_edit_button_srgba(colour);
+                    }
+                    PixelEffect::Gradient(start, end) => {
+                        ui.color_edit_button_srgba(start);
+                        ui.color_edit_button_srgba(end);
+                    }
+                    PixelEffect::Chase {
+                        colour,
+                        background,
+                        length,
+                        speed,
+                    } => {
+                        ui.color_edit_button_srgba(colour);
+                        ui.color_edit_button_srgba(background);
+                        ui.add(
+                            DragValue::new(length)
+                                .clamp_range(1..=map.width)
+                                .prefix("x"),
+                        );
+                        ui.add(DragValue::new(speed).speed(0.5).suffix("px/s"));
+                    }
+                }
+            });
+        }
+    });
+    ui.separator();
+}
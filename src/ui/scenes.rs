@@ -1,24 +1,100 @@
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
-use egui::{Grid, RichText, ScrollArea, Slider, Ui};
+use egui::{Color32, Grid, Key, Modifiers, RichText, ScrollArea, Slider, Ui};
 use log::debug;
 
 use crate::{
+    animation::Easing,
     model::Model,
     project::{fixture::FixtureMacro, Scene, SceneState, SceneValue},
 };
 
+use super::{
+    cue_lists::render_cue_lists, render_colour_temperature, render_palette, render_wheel_slots,
+};
+
+/// Number keys which trigger Scenes, in order, within the current bank
+const SCENE_KEYS: [Key; SCENES_PER_BANK] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+const SCENES_PER_BANK: usize = 9;
+
+/// The number key for a Scene, if it is in the current bank
+fn scene_hotkey(scene_index: usize, bank: usize) -> Option<usize> {
+    (scene_index / SCENES_PER_BANK == bank).then_some(scene_index % SCENES_PER_BANK + 1)
+}
+
 pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
-    ui.heading("Scenes");
+    let bank_count = model.project.scenes.len().div_ceil(SCENES_PER_BANK).max(1);
+    model.scene_bank = model.scene_bank.min(bank_count - 1);
+
+    ui.horizontal(|ui| {
+        ui.heading("Scenes");
+        if bank_count > 1 {
+            ui.label("|");
+            if ui.button("◀").clicked() {
+                model.scene_bank = (model.scene_bank + bank_count - 1) % bank_count;
+            }
+            ui.label(format!(
+                "Keys 1-9: bank {}/{}",
+                model.scene_bank + 1,
+                bank_count
+            ))
+            .on_hover_text("Press 0 for the next bank");
+            if ui.button("▶").clicked() {
+                model.scene_bank = (model.scene_bank + 1) % bank_count;
+            }
+        }
+    });
 
     ui.separator();
 
     let mut go_scene: Option<(usize, Option<u64>)> = None;
+
+    // Number keys go to a Scene "immediately", like clicking its label; not
+    // while typing, e.g. a Scene label
+    if !ui.ctx().wants_keyboard_input() {
+        if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Num0)) {
+            model.scene_bank = (model.scene_bank + 1) % bank_count;
+        }
+        for (n, key) in SCENE_KEYS.iter().enumerate() {
+            if ui.input_mut(|i| i.consume_key(Modifiers::NONE, *key)) {
+                let scene_index = model.scene_bank * SCENES_PER_BANK + n;
+                if scene_index < model.project.scenes.len() {
+                    go_scene = Some((scene_index, None));
+                }
+            }
+        }
+    }
+
+    egui::CollapsingHeader::new("Cue Lists").show(ui, |ui| {
+        render_cue_lists(model, ui);
+    });
+    ui.separator();
+
+    if model.project.scenes.len() >= 2 {
+        egui::CollapsingHeader::new("Compare Scenes").show(ui, |ui| {
+            render_scene_comparison(model, ui);
+        });
+        ui.separator();
+    }
+
+    let mut release_scene: Option<(usize, Option<u64>)> = None;
     let mut edit_scene: Option<usize> = None;
     let mut update_scene: Option<usize> = None;
     let mut delete_scene: Option<usize> = None;
     let mut add_scene: Option<Scene> = None;
 
+    let scene_issues = model.project.audit_scenes();
+
     ScrollArea::new([false, true]).show(ui, |ui| {
         if ui.button("+ Add New").clicked() {
             let label = format!("New Scene {}", model.project.scenes.len());
@@ -35,6 +111,9 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                         FixtureMacro::Colour(colour_macro) => {
                             m_state.insert(String::from(&colour_macro.label), SceneValue::ColourValue(colour_macro.current_value));
                         },
+                        FixtureMacro::Wheel(wheel_macro) => {
+                            m_state.insert(String::from(&wheel_macro.label), SceneValue::Slot(String::from(wheel_macro.current_slot_label())));
+                        },
                     };
                 }
                 state.insert(String::from(&fixture.label), m_state);
@@ -43,6 +122,9 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
             add_scene = Some(Scene {
                 label,
                 state,
+                easing: Easing::default(),
+                excluded_fixtures: IndexSet::new(),
+                notes: String::new(),
                 is_editing: true,
                 last_active: false,
                 next_transition: 0.
@@ -55,8 +137,14 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
             ui.group(|ui| {
                 if scene.is_editing {
                     ui.text_edit_singleline(&mut scene.label);
+                    ui.label("Notes:");
+                    ui.text_edit_multiline(&mut scene.notes);
                 } else {
                     ui.horizontal(|ui| {
+                        if let Some(key) = scene_hotkey(scene_index, model.scene_bank) {
+                            ui.label(RichText::new(format!("[{}]", key)).weak())
+                                .on_hover_text(format!("Press {} to go to this Scene", key));
+                        }
                         if ui
                             .button(RichText::new(&scene.label).size(24.0))
                             .clicked()
@@ -66,6 +154,18 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                         if scene.last_active {
                             ui.label("★");
                         }
+                        if !scene.notes.is_empty() {
+                            ui.label("📝").on_hover_text(&scene.notes);
+                        }
+                        let issues: Vec<String> = scene_issues
+                            .iter()
+                            .filter(|x| x.scene_label() == scene.label)
+                            .map(|x| x.to_string())
+                            .collect();
+                        if !issues.is_empty() {
+                            ui.label(RichText::new("⚠ Needs repair").color(Color32::LIGHT_RED))
+                                .on_hover_text(issues.join("\n"));
+                        }
                     });
                     ui.separator();
 
@@ -86,7 +186,17 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                           if ui.button("Go").clicked() {
                               go_scene = Some((scene_index, Some((scene.next_transition * 1000.) as u64)));
                           }
+                          if ui.button("Release").on_hover_text("Fade this Scene's fixtures back to home, over the custom time").clicked() {
+                              release_scene = Some((scene_index, Some((scene.next_transition * 1000.) as u64)));
+                          }
                         });
+                        egui::ComboBox::from_id_source(format!("scene-{}-easing", scene_index))
+                            .selected_text(format!("{:?}", scene.easing))
+                            .show_ui(ui, |ui| {
+                                for easing in Easing::ALL {
+                                    ui.selectable_value(&mut scene.easing, easing, format!("{:?}", easing));
+                                }
+                            });
                     });
                 }
 
@@ -94,7 +204,21 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                     for (fixture_index, s) in scene.state.iter_mut().enumerate() {
                         let (fixture_label, states) = s;
                         // ----------------
-                        ui.heading(fixture_label);
+                        ui.horizontal(|ui| {
+                            ui.heading(fixture_label);
+                            let mut is_excluded = scene.excluded_fixtures.contains(fixture_label);
+                            if ui
+                                .checkbox(&mut is_excluded, "Exclude")
+                                .on_hover_text("Never apply this Scene to this fixture")
+                                .changed()
+                            {
+                                if is_excluded {
+                                    scene.excluded_fixtures.insert(String::from(fixture_label));
+                                } else {
+                                    scene.excluded_fixtures.shift_remove(fixture_label);
+                                }
+                            }
+                        });
                         // ----------------
                         ui.add_enabled_ui(true, |ui| {
                             Grid::new(format!("scene-{}-state-{}", scene_index, fixture_index))
@@ -117,12 +241,7 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                                                 .active_mode
                                                 .macros
                                                 .iter_mut()
-                                                .find(|x|  match x {
-                                                    FixtureMacro::Control(m) => m.label.eq(macro_label),
-                                                    FixtureMacro::Colour(m) => m.label.eq(macro_label),
-                                                }
-
-                                            )
+                                                .find(|x| x.label().eq(macro_label))
                                             {
                                                 match matched_macro {
                                                     FixtureMacro::Control(m) => {
@@ -132,7 +251,15 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                                                     } ,
                                                     FixtureMacro::Colour(m) => {
                                                         // let mut dummy_value = m.current_value;
-                                                        ui.color_edit_button_srgba(&mut m.current_value);
+                                                        ui.horizontal(|ui| {
+                                                            ui.color_edit_button_srgba(&mut m.current_value);
+                                                            render_colour_temperature(m, ui);
+                                                            render_palette(m, &model.project.palette, ui);
+                                                        });
+                                                    }
+                                                    FixtureMacro::Wheel(m) => {
+                                                        let id = format!("scene-{}-wheel-{}-{}", scene_index, fixture_index, macro_label);
+                                                        render_wheel_slots(m, id, ui);
                                                     }
                                                 };
                                                 ui.small("Adjust values in Macros panel");
@@ -160,8 +287,23 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
                         if ui.button("✏").clicked() {
                             // Mark scene for editing
                             edit_scene = Some(scene_index);
-                            // Also go to this scene (immediately)
-                            go_scene = Some((scene_index, None));
+                            // Also go to this scene, using its own (custom)
+                            // transition time, if any
+                            go_scene = Some((
+                                scene_index,
+                                if scene.next_transition > 0. {
+                                    Some((scene.next_transition * 1000.) as u64)
+                                } else {
+                                    None
+                                },
+                            ));
+                        }
+                        if ui
+                            .button("⟳")
+                            .on_hover_text("Update from live: capture the current macro values into this Scene")
+                            .clicked()
+                        {
+                            update_scene = Some(scene_index);
                         }
                         if ui.button("🗑").clicked() {
                             delete_scene = Some(scene_index);
@@ -191,30 +333,18 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
         let scene = &mut model.project.scenes[scene_index];
         scene.is_editing = false;
 
-        for fixture in model.project.fixtures.iter() {
-            let mut m_state = IndexMap::new();
-            for m in fixture.config.active_mode.macros.iter() {
-                match m {
-                    FixtureMacro::Control(control_macro) => {
-                        m_state.insert(
-                            String::from(&control_macro.label),
-                            SceneValue::ControlValue(control_macro.current_value),
-                        );
-                    }
-                    FixtureMacro::Colour(colour_macro) => {
-                        m_state.insert(
-                            String::from(&colour_macro.label),
-                            SceneValue::ColourValue(colour_macro.current_value),
-                        );
-                    }
-                }
-            }
-            scene.state.insert(String::from(&fixture.label), m_state);
-        }
+        // Rebuilt from scratch, so that fixtures added since are included and
+        // fixtures removed since are dropped
+        scene.state = model
+            .project
+            .fixtures
+            .iter()
+            .map(|fixture| (String::from(&fixture.label), fixture.current_state()))
+            .collect();
     }
 
     if let Some((scene_index, ms)) = go_scene {
-        model.apply_scene(scene_index, ms, None);
+        model.apply_scene(scene_index, ms, None, None);
 
         for (index, scene) in model.project.scenes.iter_mut().enumerate() {
             if index == scene_index {
@@ -228,11 +358,88 @@ pub fn render_scenes(model: &mut Model, ui: &mut Ui) {
         }
     }
 
+    if let Some((scene_index, ms)) = release_scene {
+        model.release_scene(scene_index, ms);
+    }
+
     if let Some(scene_index) = delete_scene {
-        model.project.scenes.remove(scene_index);
+        let label = String::from(&model.project.scenes[scene_index].label);
+        model.confirm_delete_scene = Some((scene_index, label));
+    }
+
+    if let Some((scene_index, label)) = model.confirm_delete_scene.clone() {
+        egui::Window::new("Delete Scene?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.label(format!("The Scene \"{}\" will be deleted.", &label));
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(RichText::new("Delete 🗑").color(Color32::LIGHT_RED))
+                        .clicked()
+                    {
+                        model.confirm_delete_scene = None;
+                        // Only if the Scenes have not changed in the meantime
+                        if model
+                            .project
+                            .scenes
+                            .get(scene_index)
+                            .is_some_and(|s| s.label == label)
+                        {
+                            debug!("Delete Scene \"{}\"", &label);
+                            model.project.scenes.remove(scene_index);
+                        }
+                    }
+                    if ui.button("Cancel 🗙").clicked() {
+                        model.confirm_delete_scene = None;
+                    }
+                });
+            });
     }
 
     if let Some(scene) = add_scene {
         model.project.scenes.push(scene);
     }
 }
+
+/// Pick two Scenes and list the macro values which differ between them
+fn render_scene_comparison(model: &mut Model, ui: &mut Ui) {
+    let scenes = &model.project.scenes;
+    let (a_index, b_index) = &mut model.compare_scenes;
+    *a_index = (*a_index).min(scenes.len() - 1);
+    *b_index = (*b_index).min(scenes.len() - 1);
+
+    ui.horizontal(|ui| {
+        for (id, index) in [("compare_a", &mut *a_index), ("compare_b", &mut *b_index)] {
+            egui::ComboBox::from_id_source(id)
+                .selected_text(&scenes[*index].label)
+                .show_ui(ui, |ui| {
+                    for (i, scene) in scenes.iter().enumerate() {
+                        ui.selectable_value(index, i, &scene.label);
+                    }
+                });
+        }
+    });
+
+    let differences = scenes[*a_index].diff(&scenes[*b_index]);
+    if differences.is_empty() {
+        ui.label("No differences");
+        return;
+    }
+    let format_value = |value: &Option<SceneValue>| match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    };
+    Grid::new("scene_differences")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            for difference in differences {
+                ui.label(&difference.fixture_label);
+                ui.label(&difference.macro_label);
+                ui.label(format_value(&difference.a_value));
+                ui.label(format_value(&difference.b_value));
+                ui.end_row();
+            }
+        });
+}